@@ -0,0 +1,192 @@
+use crate::config::Config;
+use crate::transform::Adjustment;
+use crate::{
+    capture_frame, encode_selection, output_dir, process_selection, sanitize_title,
+    selection_outlines, write_encoded,
+};
+use screenshots::Screen;
+use std::collections::HashMap;
+
+// 一行 CSV：名称和区域
+struct Row {
+    line_no: usize,
+    name: String,
+    region: (i32, i32, i32, i32),
+}
+
+// 批量裁剪（--batch <file.csv>）：截一次屏，按 CSV 中每行 name,x,y,w,h 裁剪并以 name 为文件名保存。
+// 名称可含逗号（区域取最后四列）；空行、# 开头的注释行和无法解析为数字的首行（表头）跳过
+pub fn run(path: &str, config: &Config) {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path, e);
+            return;
+        }
+    };
+    let rows = parse_rows(path, &text);
+    if rows.is_empty() {
+        eprintln!("{}: no rows to capture", path);
+        return;
+    }
+
+    let screens = match Screen::all() {
+        Ok(screens) if !screens.is_empty() => screens,
+        Ok(_) => {
+            eprintln!("Failed to enumerate screens: no screen found");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Failed to enumerate screens: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let primary_screen = &screens[0];
+    let image = match capture_frame(primary_screen, config) {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!("Failed to capture screen: {}", e);
+            return;
+        }
+    };
+    let (width, height) = image.dimensions();
+    let (screen_width, screen_height) = if config.capture_all_screens {
+        (width, height)
+    } else {
+        (
+            primary_screen.display_info.width,
+            primary_screen.display_info.height,
+        )
+    };
+    let extension = config.save_format.extension();
+    let dir_name = output_dir(screen_width, screen_height, extension, None, config);
+
+    let mut saved = 0;
+    // 已使用的文件名（不区分大小写）及其所在行，同名的后一行不再覆盖前一行的文件
+    let mut used: HashMap<String, usize> = HashMap::new();
+    for row in &rows {
+        let (x, y, w, h) = row.region;
+        if x < 0
+            || y < 0
+            || x as i64 + w as i64 > width as i64
+            || y as i64 + h as i64 > height as i64
+        {
+            eprintln!(
+                "Row {} '{}': {},{},{},{} is outside the {}x{} capture",
+                row.line_no, row.name, x, y, w, h, width, height
+            );
+            continue;
+        }
+        let Some(name) = sanitize_title(&row.name) else {
+            eprintln!(
+                "Row {}: '{}' is not a usable file name",
+                row.line_no, row.name
+            );
+            continue;
+        };
+        if let Some(first) = used.get(&name.to_lowercase()) {
+            eprintln!(
+                "Row {} '{}': the name is already used by row {}, skipped",
+                row.line_no, row.name, first
+            );
+            continue;
+        }
+        used.insert(name.to_lowercase(), row.line_no);
+
+        let Some(cropped) = process_selection(
+            &image,
+            (x, y, w as u32, h as u32),
+            None,
+            config.selection_shape,
+            Adjustment::default(),
+            config,
+        ) else {
+            continue;
+        };
+        // 与 Ctrl+S 相同按 save_format 编码
+        let outlines = selection_outlines(&cropped, false, config);
+        let Some(data) = encode_selection(&cropped, &outlines, config) else {
+            continue;
+        };
+        let file_name = format!("{}/{}.{}", dir_name, name, extension);
+        if write_encoded(&data, &file_name, row.region) {
+            saved += 1;
+        } else {
+            eprintln!(
+                "Row {} '{}': failed to write {}",
+                row.line_no, row.name, file_name
+            );
+        }
+    }
+    println!("Saved {}/{} crops from {}", saved, rows.len(), path);
+}
+
+fn parse_rows(path: &str, text: &str) -> Vec<Row> {
+    let mut rows = Vec::new();
+    let mut first = true;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let is_first = std::mem::replace(&mut first, false);
+
+        let fields: Vec<&str> = line.rsplitn(5, ',').collect();
+        let numbers: Result<Vec<i32>, _> = fields
+            .iter()
+            .take(4)
+            .map(|f| f.trim().parse::<i32>())
+            .collect();
+        match (numbers, fields.get(4)) {
+            (Ok(numbers), Some(name)) if numbers.len() == 4 => {
+                let (h, w, y, x) = (numbers[0], numbers[1], numbers[2], numbers[3]);
+                if w <= 0 || h <= 0 {
+                    eprintln!("{}:{}: expected a positive size", path, i + 1);
+                    continue;
+                }
+                rows.push(Row {
+                    line_no: i + 1,
+                    name: name.trim().trim_matches('"').to_string(),
+                    region: (x, y, w, h),
+                });
+            }
+            // 第一条非注释行解析失败时视为表头
+            _ if is_first => {}
+            _ => eprintln!("{}:{}: expected name,x,y,w,h", path, i + 1),
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_skip_header_comments_and_bad_sizes() {
+        let text = "name,x,y,w,h\n\
+                    # comment\n\
+                    \n\
+                    logo, 10, 20, 30, 40\n\
+                    \"Price, total\",1,2,3,4\n\
+                    empty,0,0,0,5\n\
+                    broken,1,2\n";
+        let rows = parse_rows("test.csv", text);
+        let parsed: Vec<(usize, &str, (i32, i32, i32, i32))> = rows
+            .iter()
+            .map(|row| (row.line_no, row.name.as_str(), row.region))
+            .collect();
+        // 名称中的逗号保留（区域取最后四列），引号去掉
+        assert_eq!(
+            parsed,
+            [
+                (4, "logo", (10, 20, 30, 40)),
+                (5, "Price, total", (1, 2, 3, 4)),
+            ]
+        );
+
+        // 首行能解析时不当作表头
+        let rows = parse_rows("test.csv", "a,0,0,1,1\nb,1,1,2,2");
+        assert_eq!(rows.len(), 2);
+    }
+}
@@ -0,0 +1,172 @@
+use image::{ImageBuffer, Rgba};
+use std::io::Write;
+use std::time::SystemTime;
+
+// 缩略图最大高度
+const THUMBNAIL_HEIGHT: u32 = 64;
+
+// 待导出列表中的一项：按保存流程处理后的图像（process_selection）及生成文件名所需的信息，导出时与普通保存一样写入
+pub struct CartItem {
+    pub image: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    pub region: (i32, i32, i32, i32), // 保存的框（截图坐标）
+    pub green: bool,                  // 保存的是绿框（SVG 标注的颜色）
+    pub screen_size: (u32, u32),      // 归档目录 W{w}H{h} 所用的屏幕尺寸
+    pub dpi: Option<u32>,
+    pub offset: (i32, i32), // 文件名坐标的偏移（coordinate_offset）
+    pub captured_at: Option<SystemTime>,
+    pub thumbnail: Vec<u32>, // ARGB 缩略图
+    pub thumbnail_width: u32,
+    pub thumbnail_height: u32,
+}
+
+impl CartItem {
+    pub fn new(
+        image: ImageBuffer<Rgba<u8>, Vec<u8>>,
+        region: (i32, i32, i32, i32),
+        green: bool,
+        screen_size: (u32, u32),
+        dpi: Option<u32>,
+        offset: (i32, i32),
+        captured_at: Option<SystemTime>,
+    ) -> Self {
+        // 最近邻缩放生成缩略图
+        let (width, height) = image.dimensions();
+        let thumbnail_height = height.clamp(1, THUMBNAIL_HEIGHT);
+        let thumbnail_width = (width * thumbnail_height / height.max(1)).max(1);
+        let mut thumbnail = Vec::with_capacity((thumbnail_width * thumbnail_height) as usize);
+        for ty in 0..thumbnail_height {
+            for tx in 0..thumbnail_width {
+                let pixel =
+                    image.get_pixel(tx * width / thumbnail_width, ty * height / thumbnail_height);
+                let [r, g, b, _] = pixel.0;
+                thumbnail.push(0xFF000000 | (r as u32) << 16 | (g as u32) << 8 | b as u32);
+            }
+        }
+
+        Self {
+            image,
+            region,
+            green,
+            screen_size,
+            dpi,
+            offset,
+            captured_at,
+            thumbnail,
+            thumbnail_width,
+            thumbnail_height,
+        }
+    }
+}
+
+// 写入不压缩（store）的 zip 文件，WebP 已是压缩格式，无需再次压缩
+pub fn write_zip(path: &str, entries: &[(String, Vec<u8>)]) -> std::io::Result<()> {
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+
+    for (name, data) in entries {
+        let offset = out.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+
+        // 本地文件头
+        out.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        out.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0]); // 版本、标志、方法、时间、日期
+        out.extend_from_slice(&crc.to_le_bytes());
+        out.extend_from_slice(&size.to_le_bytes());
+        out.extend_from_slice(&size.to_le_bytes());
+        out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(data);
+
+        // 中央目录项
+        central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central.extend_from_slice(&[20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        central.extend_from_slice(&crc.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        central.extend_from_slice(&[0; 12]); // 扩展字段、注释、磁盘号、属性
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    // 中央目录结束记录
+    let central_offset = out.len() as u32;
+    out.extend_from_slice(&central);
+    out.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    out.extend_from_slice(&central_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+
+    std::fs::File::create(path)?.write_all(&out)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(data: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([data[at], data[at + 1]])
+    }
+
+    fn u32_at(data: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn zip_entries_can_be_read_back() {
+        let entries = vec![
+            ("a_1.webp".to_string(), b"first".to_vec()),
+            ("a_2.webp".to_string(), b"second entry".to_vec()),
+        ];
+        let path =
+            std::env::temp_dir().join(format!("captrue_zip_test_{}.zip", std::process::id()));
+        write_zip(path.to_str().unwrap(), &entries).unwrap();
+        let zip = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        // 从结束记录找到中央目录，再按其中的偏移读出每个本地文件
+        let end = zip.len() - 22;
+        assert_eq!(u32_at(&zip, end), 0x06054b50);
+        assert_eq!(u16_at(&zip, end + 10) as usize, entries.len());
+        let mut at = u32_at(&zip, end + 16) as usize;
+        for (name, data) in &entries {
+            assert_eq!(u32_at(&zip, at), 0x02014b50);
+            assert_eq!(u32_at(&zip, at + 16), crc32(data));
+            let name_len = u16_at(&zip, at + 28) as usize;
+            assert_eq!(&zip[at + 46..at + 46 + name_len], name.as_bytes());
+
+            let local = u32_at(&zip, at + 42) as usize;
+            assert_eq!(u32_at(&zip, local), 0x04034b50);
+            let size = u32_at(&zip, local + 18) as usize;
+            let start = local + 30 + u16_at(&zip, local + 26) as usize;
+            assert_eq!(&zip[start..start + size], data.as_slice());
+            at += 46 + name_len;
+        }
+    }
+}
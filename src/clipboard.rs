@@ -0,0 +1,172 @@
+use crate::config::ClipboardFormat;
+use image::{ImageBuffer, Rgba};
+use std::mem::{size_of, zeroed};
+use std::ptr::null_mut;
+use winapi::um::winbase::{GMEM_MOVEABLE, GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock};
+use winapi::um::wingdi::{BI_RGB, BITMAPINFOHEADER};
+use winapi::um::winuser::{
+    CF_DIB, CF_UNICODETEXT, CloseClipboard, EmptyClipboard, OpenClipboard,
+    RegisterClipboardFormatW, SetClipboardData,
+};
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// data:<mime>;base64,<数据>
+pub fn data_uri(mime: &str, data: &[u8]) -> String {
+    format!("data:{};base64,{}", mime, base64(data))
+}
+
+// 标准 Base64（带 = 填充）
+fn base64(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let group = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(BASE64_ALPHABET[(group >> (18 - i * 6)) as usize & 0x3F] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+// 把文本放入剪贴板（CF_UNICODETEXT）
+pub fn set_text(text: &str) -> Result<(), String> {
+    let bytes: Vec<u8> = text
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    set_data(&[(CF_UNICODETEXT, bytes)])
+}
+
+// 把图像以 formats 中的各格式同时放入剪贴板：CF_DIB（32 位 BGRA，兼容旧程序）和/或
+// 注册格式 "PNG"（无损，保留透明，浏览器等优先使用）
+pub fn set_image(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    formats: &[ClipboardFormat],
+) -> Result<(), String> {
+    let mut entries = Vec::with_capacity(formats.len());
+    for format in formats {
+        match format {
+            ClipboardFormat::Dib => entries.push((CF_DIB, dib(image))),
+            ClipboardFormat::Png => {
+                let png = crate::icc::encode_png(image)?;
+                let name: Vec<u16> = "PNG\0".encode_utf16().collect();
+                let id = unsafe { RegisterClipboardFormatW(name.as_ptr()) };
+                if id == 0 {
+                    return Err("cannot register the PNG clipboard format".to_string());
+                }
+                entries.push((id, png));
+            }
+        }
+    }
+    if entries.is_empty() {
+        return Err("no clipboard formats configured".to_string());
+    }
+    set_data(&entries)
+}
+
+// BITMAPINFOHEADER + 自下而上的 BGRA 像素行
+fn dib(image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let pixels = (width * height * 4) as usize;
+    let mut data = Vec::with_capacity(size_of::<BITMAPINFOHEADER>() + pixels);
+
+    let mut header: BITMAPINFOHEADER = unsafe { zeroed() };
+    header.biSize = size_of::<BITMAPINFOHEADER>() as u32;
+    header.biWidth = width as i32;
+    header.biHeight = height as i32;
+    header.biPlanes = 1;
+    header.biBitCount = 32;
+    header.biCompression = BI_RGB;
+    header.biSizeImage = pixels as u32;
+    let header_bytes = unsafe {
+        std::slice::from_raw_parts(
+            &header as *const BITMAPINFOHEADER as *const u8,
+            size_of::<BITMAPINFOHEADER>(),
+        )
+    };
+    data.extend_from_slice(header_bytes);
+
+    for row in image.rows().rev() {
+        for pixel in row {
+            data.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+        }
+    }
+    data
+}
+
+// 清空剪贴板后依次放入各格式的数据
+fn set_data(entries: &[(u32, Vec<u8>)]) -> Result<(), String> {
+    unsafe {
+        if OpenClipboard(null_mut()) == 0 {
+            return Err("cannot open the clipboard".to_string());
+        }
+        EmptyClipboard();
+
+        let result = entries
+            .iter()
+            .try_for_each(|(format, bytes)| put(*format, bytes));
+
+        CloseClipboard();
+        result
+    }
+}
+
+// 复制到全局内存并放入剪贴板（须已打开剪贴板）
+unsafe fn put(format: u32, bytes: &[u8]) -> Result<(), String> {
+    unsafe {
+        let memory = GlobalAlloc(GMEM_MOVEABLE, bytes.len());
+        if memory.is_null() {
+            return Err("out of memory".to_string());
+        }
+        let target = GlobalLock(memory) as *mut u8;
+        if target.is_null() {
+            GlobalFree(memory);
+            return Err("cannot lock clipboard memory".to_string());
+        }
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), target, bytes.len());
+        GlobalUnlock(memory);
+
+        // 成功后内存归剪贴板所有
+        if SetClipboardData(format, memory as _).is_null() {
+            GlobalFree(memory);
+            return Err("cannot set clipboard data".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_matches_the_rfc_vectors() {
+        for (data, text) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64(data.as_bytes()), text);
+        }
+        assert_eq!(base64(&[0xFB, 0xFF]), "+/8=");
+        assert_eq!(
+            data_uri("image/webp", b"foo"),
+            "data:image/webp;base64,Zm9v"
+        );
+    }
+}
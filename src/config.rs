@@ -0,0 +1,1143 @@
+use minifb::Key;
+use winapi::um::winuser::{MOD_ALT, MOD_CONTROL, MOD_SHIFT, MOD_WIN};
+
+// 配置文件路径（相对于工作目录）
+pub const CONFIG_FILE: &str = "captrue.ini";
+
+// 热键：修饰键 + 虚拟键码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hotkey {
+    pub modifiers: u32,
+    pub vk: u32,
+}
+
+impl Hotkey {
+    // 解析形如 "Ctrl+Alt+D"、"Shift+F2" 的热键字符串
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut modifiers = 0u32;
+        let mut vk = None;
+
+        for part in text.split('+').map(|p| p.trim()) {
+            let name = part.to_ascii_lowercase();
+            if let Some(modifier) = parse_modifier(&name) {
+                modifiers |= modifier;
+                continue;
+            }
+            match name.as_str() {
+                "" => return Err(format!("empty key in hotkey '{}'", text)),
+                name => {
+                    if vk.is_some() {
+                        return Err(format!("more than one key in hotkey '{}'", text));
+                    }
+                    vk = Some(parse_vk(name).ok_or_else(|| format!("unknown key '{}'", part))?);
+                }
+            }
+        }
+
+        match vk {
+            Some(vk) => Ok(Self { modifiers, vk }),
+            None => Err(format!("missing key in hotkey '{}'", text)),
+        }
+    }
+
+    // 窗口内按键匹配用：虚拟键码对应的 minifb 按键。minifb 的 Key 只到 F15，F16–F24（parse_vk 接受）
+    // 没有对应按键，返回 None，窗口内的保存键由主循环直接查询虚拟键状态
+    pub fn key(&self) -> Option<Key> {
+        #[rustfmt::skip]
+        const LETTERS: [Key; 26] = [
+            Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I,
+            Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R,
+            Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+        ];
+        #[rustfmt::skip]
+        const DIGITS: [Key; 10] = [
+            Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4,
+            Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+        ];
+        #[rustfmt::skip]
+        const FUNCTIONS: [Key; 15] = [
+            Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8,
+            Key::F9, Key::F10, Key::F11, Key::F12, Key::F13, Key::F14, Key::F15,
+        ];
+
+        match self.vk {
+            0x41..=0x5A => Some(LETTERS[(self.vk - 0x41) as usize]),
+            0x30..=0x39 => Some(DIGITS[(self.vk - 0x30) as usize]),
+            0x70..=0x7E => Some(FUNCTIONS[(self.vk - 0x70) as usize]),
+            0x20 => Some(Key::Space),
+            0x0D => Some(Key::Enter),
+            0x09 => Some(Key::Tab),
+            0x2D => Some(Key::Insert),
+            0x2E => Some(Key::Delete),
+            0x24 => Some(Key::Home),
+            0x23 => Some(Key::End),
+            0x21 => Some(Key::PageUp),
+            0x22 => Some(Key::PageDown),
+            _ => None,
+        }
+    }
+
+    // 由窗口内按下的键构造热键（用于设置向导录入热键）
+    pub fn from_key(modifiers: u32, key: Key) -> Option<Self> {
+        (0x08..=0x87)
+            .map(|vk| Self { modifiers, vk })
+            .find(|hotkey| hotkey.key() == Some(key))
+    }
+}
+
+impl std::fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (flag, name) in [
+            (MOD_CONTROL, "Ctrl"),
+            (MOD_ALT, "Alt"),
+            (MOD_SHIFT, "Shift"),
+            (MOD_WIN, "Win"),
+        ] {
+            if self.modifiers & flag as u32 != 0 {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match self.vk {
+            0x30..=0x39 | 0x41..=0x5A => write!(f, "{}", self.vk as u8 as char),
+            0x70..=0x87 => write!(f, "F{}", self.vk - 0x70 + 1),
+            0x2C => write!(f, "PrintScreen"),
+            vk => match self.key() {
+                Some(key) => write!(f, "{:?}", key),
+                None => write!(f, "0x{:02X}", vk),
+            },
+        }
+    }
+}
+
+// 修饰键名称到 MOD_* 标志
+fn parse_modifier(name: &str) -> Option<u32> {
+    match name {
+        "ctrl" | "control" => Some(MOD_CONTROL as u32),
+        "alt" => Some(MOD_ALT as u32),
+        "shift" => Some(MOD_SHIFT as u32),
+        "win" | "super" => Some(MOD_WIN as u32),
+        _ => None,
+    }
+}
+
+// 解析形如 "Ctrl+Alt" 的修饰键组合
+fn parse_modifiers(text: &str) -> Result<u32, String> {
+    text.split('+')
+        .map(|part| {
+            parse_modifier(&part.trim().to_ascii_lowercase())
+                .ok_or_else(|| format!("unknown modifier '{}'", part.trim()))
+        })
+        .try_fold(0, |modifiers, modifier| Ok(modifiers | modifier?))
+}
+
+// 解析鼠标手势："down,right" 形式的方向列表，none 或空值表示不启用
+fn parse_gesture(value: &str) -> Result<Vec<Direction>, String> {
+    if value.eq_ignore_ascii_case("none") {
+        return Ok(Vec::new());
+    }
+    value
+        .split(',')
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .map(|part| match part.to_ascii_lowercase().as_str() {
+            "up" | "u" => Ok(Direction::Up),
+            "down" | "d" => Ok(Direction::Down),
+            "left" | "l" => Ok(Direction::Left),
+            "right" | "r" => Ok(Direction::Right),
+            _ => Err(format!("expected up, down, left or right, got '{}'", part)),
+        })
+        .collect()
+}
+
+// 按键名称到虚拟键码
+fn parse_vk(name: &str) -> Option<u32> {
+    let bytes = name.as_bytes();
+    if bytes.len() == 1 && bytes[0].is_ascii_alphanumeric() {
+        return Some(bytes[0].to_ascii_uppercase() as u32);
+    }
+    if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<u32>().ok())
+        && (1..=24).contains(&n)
+    {
+        return Some(0x70 + n - 1);
+    }
+    match name {
+        "space" => Some(0x20),
+        "enter" | "return" => Some(0x0D),
+        "tab" => Some(0x09),
+        "printscreen" | "prtsc" => Some(0x2C),
+        "insert" => Some(0x2D),
+        "delete" => Some(0x2E),
+        "home" => Some(0x24),
+        "end" => Some(0x23),
+        "pageup" => Some(0x21),
+        "pagedown" => Some(0x22),
+        _ => None,
+    }
+}
+
+// 选框边框样式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderStyle {
+    Crisp,  // 1px 实线（默认，最快）
+    Smooth, // 带半透明过渡的抗锯齿边框
+    Ants,   // 流动的虚线（蚂蚁线），在复杂背景上更醒目
+}
+
+// 保存的选区形状：矩形，或内切于选框的椭圆（椭圆外透明）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelectionShape {
+    #[default]
+    Rectangle,
+    Ellipse,
+}
+
+// 延时截图倒计时样式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountdownStyle {
+    Ring,
+    Bar,
+}
+
+// 覆盖窗口模式：显示冻结的截图，或在实时桌面上叠加半透明遮罩（保存时才截图）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayMode {
+    Frozen,
+    Live,
+}
+
+// 复制为 data URI 时的图像格式（保存为 SVG 时也用作内嵌图像的格式）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataUriFormat {
+    Webp,
+    Png,
+}
+
+// 缩小显示截图时的插值方式：最近邻像素清晰，适合精确框选；双线性更平滑
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Nearest,
+    Bilinear,
+}
+
+// 快速截取松开鼠标后的去向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuickTarget {
+    Clipboard,
+    File,
+}
+
+// 保存选区的文件格式：WebP，或内嵌图像、选框为矢量元素的 SVG
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveFormat {
+    Webp,
+    Svg,
+}
+
+impl SaveFormat {
+    // 扩展名（不含点）
+    pub fn extension(self) -> &'static str {
+        match self {
+            SaveFormat::Webp => "webp",
+            SaveFormat::Svg => "svg",
+        }
+    }
+}
+
+// 保存时水印所在的角
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatermarkCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+// 文件名和保存回调中选区坐标的参照：截图左上角（拼接截图时即虚拟桌面左上角），或选区中心所在显示器的左上角
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoordinateSpace {
+    Desktop,
+    Monitor,
+}
+
+// 拼接截图（J 键）时新选区相对已有图像的方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppendDirection {
+    Horizontal,
+    Vertical,
+}
+
+// 复制图像到剪贴板时提供的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardFormat {
+    Dib,
+    Png,
+}
+
+// 框选拖动距离小于阈值（宽或高不超过 10 像素）时的处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmallDragAction {
+    Revert,        // 放弃本次框选，回到全屏截图状态（默认）
+    PickColor,     // 视为单击：取该像素的颜色，#RRGGBB 复制到剪贴板
+    DefaultRegion, // 以该点为中心框选 small_drag_size 大小的区域（限制在截图内）
+}
+
+// 鼠标手势的一段移动方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+// 截图缓冲区的通道顺序，部分显卡/后端返回 BGRA
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelOrder {
+    Rgba,
+    Bgra,
+}
+
+// 命名的固定区域：按热键直接截取并保存指定屏幕上的区域
+#[derive(Debug, Clone)]
+pub struct Preset {
+    pub name: String,
+    pub region: (i32, i32, i32, i32), // 相对该屏幕左上角
+    pub monitor: usize,               // Screen::all() 中的序号
+    pub hotkey: Hotkey,
+}
+
+impl Preset {
+    // 配置文件中的一行，与 parse 的格式一致
+    pub fn line(&self) -> String {
+        let (x, y, w, h) = self.region;
+        format!(
+            "preset = {} | {},{},{},{} | {} | {}",
+            self.name, x, y, w, h, self.monitor, self.hotkey
+        )
+    }
+
+    // 解析 "名称 | x,y,w,h | 屏幕序号 | 热键"
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.split('|').map(|p| p.trim()).collect::<Vec<_>>()[..] {
+            [name, region, monitor, hotkey] if !name.is_empty() => Ok(Self {
+                name: name.to_string(),
+                region: parse_region(region)?,
+                monitor: parse_number(monitor)?,
+                hotkey: Hotkey::parse(hotkey)?,
+            }),
+            _ => Err(format!(
+                "expected name | x,y,w,h | monitor | hotkey, got '{}'",
+                value
+            )),
+        }
+    }
+}
+
+// 程序配置
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub capture_hotkey: Hotkey,
+    pub save_hotkey: Hotkey,
+    // true: 保存键注册为全局热键；false: 仅在截图窗口聚焦时响应
+    pub save_hotkey_global: bool,
+    // 定时截图（--timer）：区域、间隔和停止热键，区域未配置时使用上次保存的区域
+    pub timer_region: Option<(i32, i32, i32, i32)>,
+    pub timer_interval_ms: u64,
+    pub stop_hotkey: Hotkey,
+    // 暂停/恢复全局热键：暂停时注销截图、保存、取消保存和命名区域热键，并停用鼠标手势
+    pub suspend_hotkey: Hotkey,
+    // 快速截取热键（none 为不注册）：拖动一次，松开即按 quick_target 复制到剪贴板或保存为文件并隐藏窗口
+    pub quick_hotkey: Option<Hotkey>,
+    pub quick_target: QuickTarget,
+    // 按屏幕截图：修饰键 + 1..9 截取 Screen::all() 中对应序号的显示器（默认不注册，如 Ctrl+Alt）
+    pub monitor_hotkey_modifiers: Option<u32>,
+    // 打开截图查看器（浏览输出目录中已保存的图片）的全局热键（默认不注册）
+    pub viewer_hotkey: Option<Hotkey>,
+    // 复制最近保存文件的绝对路径到剪贴板的全局热键（默认不注册）
+    pub copy_path_hotkey: Option<Hotkey>,
+    // 批量导出：逐个保存文件 / 打包为 zip
+    pub cart_export_files: bool,
+    pub cart_export_zip: bool,
+    // 放大镜：放大倍数（0 关闭）、网格线开关和颜色（ARGB，alpha 为混合强度）
+    pub loupe_zoom: u32,
+    pub loupe_grid: bool,
+    pub loupe_grid_color: u32,
+    // 保存时自动去除纯色边框，容差为各通道允许的差值
+    pub auto_trim: bool,
+    pub auto_trim_tolerance: u8,
+    pub border_style: BorderStyle,
+    // 后台保存：编码和写盘不阻塞界面；取消热键放弃正在进行的保存
+    pub async_save: bool,
+    pub cancel_save_hotkey: Hotkey,
+    // 连续截图：保存后留在同一帧上继续选择下一个区域
+    pub continuous_capture: bool,
+    // 松开鼠标即保存红框，跳过确认和绿框选择
+    pub auto_save_on_release: bool,
+    // 截图大于覆盖窗口（如高 DPI 缩放）时按 1:1 显示其中一部分并可平移，而不是缩小显示
+    pub pan_and_scan: bool,
+    // 截图大于窗口时默认缩小到适合窗口显示（Z 键在适合窗口与 1:1 之间切换），选区仍按原始分辨率计算
+    pub fit_to_window: bool,
+    pub display_interpolation: Interpolation,
+    // 编号标记（N 键）：圆的颜色和半径；stamp_reset_per_capture 为 false 时序号在本次运行内持续递增
+    pub stamp_color: u32,
+    pub stamp_radius: u32,
+    pub stamp_reset_per_capture: bool,
+    // 先标注后裁剪：截图后直接在整帧上进入编号标记模式（不预选前台窗口），按 N 结束后再框选；
+    // 标记画进整帧后再裁剪，选区外的部分不会保存
+    pub annotate_first: bool,
+    // 延时截图：按下截图热键后等待的毫秒数（0 立即截图）及倒计时样式
+    pub capture_delay_ms: u64,
+    pub countdown_style: CountdownStyle,
+    pub channel_order: ChannelOrder,
+    // 历史记录条数（0 关闭）：保存缩略图，首次触发截图时显示画廊
+    pub history_size: usize,
+    // 保存的区域同时记录相对前台窗口的坐标，定时截图时按窗口当前位置换算
+    pub relative_to_window: bool,
+    // 截图后自动把前台窗口的可见边框选为红框，可直接保存或调整（ESC 回到空白选择）
+    pub preselect_foreground_window: bool,
+    // 截取窗口（Tab 选择、预选前台窗口、--process-windows）时把窗口打开的菜单、下拉列表、提示框等弹出窗口一并框入
+    pub include_popups: bool,
+    // B 键框选两个界面元素时，除顶层窗口外也可点选窗口内的子控件（EnumChildWindows）
+    pub pick_child_controls: bool,
+    // 拖动选框时，距离屏幕边缘不超过 edge_snap_px 像素即吸附到边缘（默认关闭，保持原有的逐像素拖动）
+    pub edge_snap: bool,
+    pub edge_snap_px: u32,
+    // 保存到按日期分级的子目录（W..H../YYYY/MM/DD/）
+    pub date_folders: bool,
+    // 保存根目录（空为工作目录）
+    pub output_dir: String,
+    // 松开鼠标后先进入可调整的待确认状态，按 Enter 才确认选区
+    pub deferred_confirm: bool,
+    // 截取所有屏幕并拼接为虚拟桌面，选区可跨越多个屏幕
+    pub capture_all_screens: bool,
+    // 整屏截图（含按屏幕截图和 --server 的 full）只截取工作区，不含任务栏；capture_all_screens 时不生效
+    pub work_area_only: bool,
+    // 保存后的反馈：快门声 / 系统通知（失败时为错误提示）
+    pub save_sound: bool,
+    pub save_toast: bool,
+    // 文件名附加选区所在显示器的 DPI（_DPI144 等）
+    pub dpi_tag: bool,
+    // 选中红框后按 T 用系统 OCR 检测文字，建议裁剪到最大文字块（较慢，默认关闭）
+    pub ocr_text_crop: bool,
+    // 多倍率导出（如 "2,1"）：原尺寸视为最大倍率，其余倍率缩小后另存为 name@{倍率}x.webp，空为只保存原尺寸
+    pub scale_variants: Vec<f32>,
+    // 预截图（0 关闭）：空闲时每 precapture_interval_ms 截屏一次，保留最近 precapture_ms 内的帧，
+    // 总大小不超过 precapture_max_mb；按下截图热键后可用左右方向键选择之前的帧
+    pub precapture_ms: u64,
+    pub precapture_interval_ms: u64,
+    pub precapture_max_mb: usize,
+    // 冻结前实时预览：按下截图热键后每 live_preview_interval_ms 重新截屏，按 Space/Enter 或点击时冻结
+    // （需要覆盖窗口能排除在截图之外；每次重新截屏都有开销，默认关闭）
+    pub live_preview: bool,
+    pub live_preview_interval_ms: u64,
+    // 截图窗口显示期间超过该秒数没有键盘/鼠标输入时自动取消并隐藏窗口（0 关闭）
+    pub idle_timeout_secs: u64,
+    // 主循环帧率：截图时 active_fps，窗口隐藏空闲时 idle_fps（影响热键响应延迟）；
+    // battery_fps 非 0 时使用电池供电下两者都不超过该值（在空闲/截图切换时检测）
+    pub active_fps: u32,
+    pub idle_fps: u32,
+    pub battery_fps: u32,
+    // 默认选区形状，截图时可按 O 键切换
+    pub selection_shape: SelectionShape,
+    // 矩形选区的圆角半径（0 关闭），圆角外透明；超过短边一半时按一半处理
+    pub corner_radius: u32,
+    // 调整红框时的手柄：显示边长，以及按下鼠标时距边/角多少像素内算作抓住（触摸或高 DPI 时可调大）
+    pub handle_size: u32,
+    pub handle_grab_tolerance: u32,
+    // 投影（shadow_margin 为 0 关闭）：画布四周扩展 shadow_margin 像素，
+    // 阴影颜色 #AARRGGBB、模糊半径、向右下的偏移
+    pub shadow_margin: u32,
+    pub shadow_color: u32,
+    pub shadow_blur: u32,
+    pub shadow_offset: u32,
+    // 命名区域，每行一个 preset，可重复
+    pub presets: Vec<Preset>,
+    // 按格式覆盖保存目录：(扩展名, 目录)，未覆盖的格式使用 output_dir
+    pub format_dirs: Vec<(String, String)>,
+    // 保存前把半透明像素合成到该纯色背景上（#RRGGBB，none 保留透明度）
+    pub flatten_background: Option<u32>,
+    // 选区外的遮罩：dim_tint 为 #RRGGBB 时向该颜色混合 dim_tint_strength%（0-100），none 为默认的灰度化
+    pub dim_tint: Option<u32>,
+    pub dim_tint_strength: u32,
+    // 文件名和保存回调中的选区坐标：desktop 相对截图左上角，monitor 相对选区中心所在显示器的左上角
+    pub coordinate_space: CoordinateSpace,
+    // 保存的图像加水印：watermark_image 为图片路径（优先），否则为 watermark_text 文字，均为空时不加
+    pub watermark_text: String,
+    pub watermark_image: String,
+    pub watermark_color: u32,
+    pub watermark_corner: WatermarkCorner,
+    pub watermark_opacity: u32, // 0-100
+    pub overlay_mode: OverlayMode,
+    // U 键把选区复制为 data:image/...;base64 文本时使用的格式
+    pub data_uri_format: DataUriFormat,
+    // X 键把选区坐标复制为文本时的格式：%x %y %w %h（或 {x} {y} {w} {h}）为左上角和大小，
+    // %r %b（{right} {bottom}）为右、下边，%m（{monitor}）为所在屏幕序号，%% 为 %，其余字符原样保留
+    pub coordinate_format: String,
+    // 每次保存时同时按 coordinate_format 复制选区坐标
+    pub copy_coordinates_on_save: bool,
+    // 选区保存格式（webp/svg）；svg 时图像按 data_uri_format 编码内嵌，bake_border 的选框输出为 <rect> 而不画进像素
+    pub save_format: SaveFormat,
+    // 文件名中用标题代替时间戳：Tab 选择窗口时取窗口标题，否则 OCR 识别选区第一行文字（较慢，默认关闭）；
+    // 识别不到文字或同名文件已存在时仍用时间戳
+    pub title_names: bool,
+    // 文件名时间戳（及 date_folders 的日期）用冻结帧的截取时刻而不是保存时刻，与画面中的时钟一致
+    pub capture_time_names: bool,
+    // 选区确定后按 P 写入临时文件并用系统默认编辑器打开
+    pub open_in_editor: bool,
+    // J 键拼接截图的方向
+    pub append_direction: AppendDirection,
+    // Ctrl+C 复制图像时同时提供的剪贴板格式（依次放入）
+    pub clipboard_formats: Vec<ClipboardFormat>,
+    // 保存后保留冻结的截图和选框，可微调后再次保存（优先于 continuous_capture）
+    pub stay_after_save: bool,
+    // 与上一次保存的选区像素完全相同（重复按保存、重复截取同一区域）时不写入新文件
+    pub skip_duplicate_saves: bool,
+    // 在覆盖层左上角显示截屏、构建显示缓存、每帧绘制和编码的耗时
+    pub debug_timings: bool,
+    // 上传（upload_url 为空关闭）：保存的选区以 multipart/form-data 的 upload_field 字段 POST 到 upload_url，
+    // 响应按 JSON 取 upload_response_path（点分隔，数字为数组下标，空为整个正文）得到链接并复制到剪贴板；
+    // upload_save_file = false 时只上传、不写入磁盘
+    pub upload_url: String,
+    pub upload_field: String,
+    pub upload_response_path: String,
+    pub upload_save_file: bool,
+    // 保存的 WebP/PNG 嵌入 sRGB ICC 配置文件，便于色彩管理软件正确显示
+    pub embed_srgb_profile: bool,
+    // 截到 HDR 显示器时提示并做色调映射（gamma 曲线，hdr_gamma 越大越暗、对比度越高；1.0 不改变）
+    pub hdr_tone_map: bool,
+    pub hdr_gamma: f32,
+    // 按住 gesture_modifiers 时用鼠标画出 mouse_gesture 触发截图；为空时不安装鼠标钩子
+    pub mouse_gesture: Vec<Direction>,
+    pub gesture_modifiers: u32,
+    // 截图时 +/- 调整亮度、[ ] 调整对比度，预览并应用到保存的图像
+    pub adjust_keys: bool,
+    // 把红框/绿框画进保存的图像（默认只保存原始像素）
+    pub bake_border: bool,
+    // 拖动距离过小时的处理，及 default_region 时的区域大小
+    pub small_drag_action: SmallDragAction,
+    pub small_drag_width: u32,
+    pub small_drag_height: u32,
+    // default_region 的磁吸：在点击点周围 magnet_radius 像素内检测高对比度的边缘，把区域中心移到最近的特征（图标、按钮）的外接框中心
+    pub magnet: bool,
+    pub magnet_radius: u32,
+    // 联系表（--contact-sheet）：区域（未配置时用 timer_region）、间隔、张数、列数，成品大小上限
+    pub sheet_region: Option<(i32, i32, i32, i32)>,
+    pub sheet_interval_ms: u64,
+    pub sheet_count: u32,
+    pub sheet_columns: u32,
+    pub sheet_max_mb: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            capture_hotkey: Hotkey {
+                modifiers: (MOD_CONTROL | MOD_ALT) as u32,
+                vk: 'D' as u32,
+            },
+            save_hotkey: Hotkey {
+                modifiers: MOD_CONTROL as u32,
+                vk: 'S' as u32,
+            },
+            save_hotkey_global: true,
+            timer_region: None,
+            timer_interval_ms: 5000,
+            stop_hotkey: Hotkey {
+                modifiers: (MOD_CONTROL | MOD_ALT) as u32,
+                vk: 'Q' as u32,
+            },
+            suspend_hotkey: Hotkey {
+                modifiers: (MOD_CONTROL | MOD_ALT) as u32,
+                vk: 0x7B, // F12
+            },
+            quick_hotkey: None,
+            quick_target: QuickTarget::Clipboard,
+            monitor_hotkey_modifiers: None,
+            viewer_hotkey: None,
+            copy_path_hotkey: None,
+            cart_export_files: true,
+            cart_export_zip: false,
+            loupe_zoom: 0,
+            loupe_grid: true,
+            loupe_grid_color: 0x50808080,
+            auto_trim: false,
+            auto_trim_tolerance: 8,
+            border_style: BorderStyle::Crisp,
+            async_save: false,
+            cancel_save_hotkey: Hotkey {
+                modifiers: (MOD_CONTROL | MOD_ALT) as u32,
+                vk: 'X' as u32,
+            },
+            continuous_capture: false,
+            auto_save_on_release: false,
+            pan_and_scan: false,
+            fit_to_window: false,
+            display_interpolation: Interpolation::Nearest,
+            stamp_color: 0xFFE53935,
+            stamp_radius: 14,
+            stamp_reset_per_capture: true,
+            annotate_first: false,
+            capture_delay_ms: 0,
+            countdown_style: CountdownStyle::Ring,
+            channel_order: ChannelOrder::Rgba,
+            history_size: 0,
+            relative_to_window: false,
+            preselect_foreground_window: true,
+            include_popups: false,
+            pick_child_controls: true,
+            edge_snap: false,
+            edge_snap_px: 8,
+            date_folders: false,
+            output_dir: String::new(),
+            deferred_confirm: false,
+            capture_all_screens: false,
+            work_area_only: false,
+            save_sound: false,
+            save_toast: false,
+            dpi_tag: false,
+            ocr_text_crop: false,
+            scale_variants: Vec::new(),
+            precapture_ms: 0,
+            precapture_interval_ms: 250,
+            precapture_max_mb: 256,
+            live_preview: false,
+            live_preview_interval_ms: 100,
+            idle_timeout_secs: 120,
+            active_fps: 60,
+            idle_fps: 15,
+            battery_fps: 0,
+            selection_shape: SelectionShape::Rectangle,
+            corner_radius: 0,
+            handle_size: 7,
+            handle_grab_tolerance: 6,
+            shadow_margin: 0,
+            shadow_color: 0x80000000,
+            shadow_blur: 8,
+            shadow_offset: 4,
+            presets: Vec::new(),
+            format_dirs: Vec::new(),
+            flatten_background: None,
+            dim_tint: None,
+            dim_tint_strength: 40,
+            coordinate_space: CoordinateSpace::Desktop,
+            watermark_text: String::new(),
+            watermark_image: String::new(),
+            watermark_color: 0xFFFFFFFF,
+            watermark_corner: WatermarkCorner::BottomRight,
+            watermark_opacity: 50,
+            overlay_mode: OverlayMode::Frozen,
+            data_uri_format: DataUriFormat::Webp,
+            coordinate_format: "%x,%y,%w,%h".to_string(),
+            copy_coordinates_on_save: false,
+            save_format: SaveFormat::Webp,
+            title_names: false,
+            capture_time_names: false,
+            open_in_editor: false,
+            bake_border: false,
+            adjust_keys: false,
+            mouse_gesture: Vec::new(),
+            debug_timings: false,
+            embed_srgb_profile: false,
+            upload_url: String::new(),
+            upload_field: "file".to_string(),
+            upload_response_path: "url".to_string(),
+            upload_save_file: true,
+            hdr_tone_map: true,
+            hdr_gamma: 1.3,
+            stay_after_save: false,
+            skip_duplicate_saves: false,
+            append_direction: AppendDirection::Horizontal,
+            clipboard_formats: vec![ClipboardFormat::Png, ClipboardFormat::Dib],
+            gesture_modifiers: MOD_CONTROL as u32,
+            small_drag_action: SmallDragAction::Revert,
+            small_drag_width: 400,
+            small_drag_height: 300,
+            magnet: false,
+            magnet_radius: 32,
+            sheet_region: None,
+            sheet_interval_ms: 1000,
+            sheet_count: 12,
+            sheet_columns: 4,
+            sheet_max_mb: 64,
+        }
+    }
+}
+
+impl Config {
+    // 读取配置文件，文件不存在时使用默认值；单项解析失败时保留该项默认值
+    pub fn load() -> Self {
+        let mut config = Self::default();
+        let Ok(text) = std::fs::read_to_string(CONFIG_FILE) else {
+            return config;
+        };
+
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                eprintln!("{}:{}: expected key = value", CONFIG_FILE, line_no + 1);
+                continue;
+            };
+            if let Err(e) = config.set(key.trim(), value.trim()) {
+                eprintln!("{}:{}: {}", CONFIG_FILE, line_no + 1, e);
+            }
+        }
+
+        config
+    }
+
+    // 写入设置向导选择的配置项，其余项保持默认
+    pub fn write_setup(&self) -> std::io::Result<()> {
+        let text = format!(
+            "# captrue 配置（由首次运行向导生成）\n\
+             output_dir = {}\n\
+             save_format = {}\n\
+             capture_hotkey = {}\n\
+             save_hotkey = {}\n",
+            self.output_dir,
+            self.save_format.extension(),
+            self.capture_hotkey,
+            self.save_hotkey
+        );
+        std::fs::write(CONFIG_FILE, text)
+    }
+
+    // 指定格式（扩展名，不含点）的保存根目录：有 format_dir 覆盖时用覆盖值，否则为 output_dir
+    pub fn output_dir_for(&self, format: &str) -> &str {
+        self.format_dirs
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(format))
+            .map_or(&self.output_dir, |(_, dir)| dir)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "capture_hotkey" => self.capture_hotkey = Hotkey::parse(value)?,
+            "save_hotkey" => self.save_hotkey = Hotkey::parse(value)?,
+            "save_hotkey_global" => self.save_hotkey_global = parse_bool(value)?,
+            "timer_region" => self.timer_region = Some(parse_region(value)?),
+            "timer_interval_ms" => self.timer_interval_ms = parse_number(value)?,
+            "stop_hotkey" => self.stop_hotkey = Hotkey::parse(value)?,
+            "cancel_save_hotkey" => self.cancel_save_hotkey = Hotkey::parse(value)?,
+            "suspend_hotkey" => self.suspend_hotkey = Hotkey::parse(value)?,
+            "quick_hotkey" => {
+                self.quick_hotkey = match value {
+                    "" | "none" => None,
+                    _ => Some(Hotkey::parse(value)?),
+                }
+            }
+            "monitor_hotkey_modifiers" => {
+                self.monitor_hotkey_modifiers = match value {
+                    "" | "none" => None,
+                    _ => Some(parse_modifiers(value)?),
+                }
+            }
+            "viewer_hotkey" => {
+                self.viewer_hotkey = match value {
+                    "" | "none" => None,
+                    _ => Some(Hotkey::parse(value)?),
+                }
+            }
+            "copy_path_hotkey" => {
+                self.copy_path_hotkey = match value {
+                    "" | "none" => None,
+                    _ => Some(Hotkey::parse(value)?),
+                }
+            }
+            "quick_target" => {
+                self.quick_target = match value {
+                    "clipboard" => QuickTarget::Clipboard,
+                    "file" => QuickTarget::File,
+                    _ => return Err(format!("expected clipboard or file, got '{}'", value)),
+                }
+            }
+            "cart_export_files" => self.cart_export_files = parse_bool(value)?,
+            "cart_export_zip" => self.cart_export_zip = parse_bool(value)?,
+            "loupe_zoom" => self.loupe_zoom = parse_number(value)?,
+            "loupe_grid" => self.loupe_grid = parse_bool(value)?,
+            "loupe_grid_color" => self.loupe_grid_color = parse_color(value)?,
+            "auto_trim" => self.auto_trim = parse_bool(value)?,
+            "auto_trim_tolerance" => self.auto_trim_tolerance = parse_number(value)?,
+            "async_save" => self.async_save = parse_bool(value)?,
+            "continuous_capture" => self.continuous_capture = parse_bool(value)?,
+            "auto_save_on_release" => self.auto_save_on_release = parse_bool(value)?,
+            "pan_and_scan" => self.pan_and_scan = parse_bool(value)?,
+            "fit_to_window" => self.fit_to_window = parse_bool(value)?,
+            "stamp_color" => self.stamp_color = parse_color(value)?,
+            "stamp_radius" => self.stamp_radius = parse_number::<u32>(value)?.max(4),
+            "stamp_reset_per_capture" => self.stamp_reset_per_capture = parse_bool(value)?,
+            "annotate_first" => self.annotate_first = parse_bool(value)?,
+            "capture_delay_ms" => self.capture_delay_ms = parse_number(value)?,
+            "countdown_style" => {
+                self.countdown_style = match value {
+                    "ring" => CountdownStyle::Ring,
+                    "bar" => CountdownStyle::Bar,
+                    _ => return Err(format!("expected ring or bar, got '{}'", value)),
+                }
+            }
+            "history_size" => self.history_size = parse_number(value)?,
+            "relative_to_window" => self.relative_to_window = parse_bool(value)?,
+            "preselect_foreground_window" => self.preselect_foreground_window = parse_bool(value)?,
+            "include_popups" => self.include_popups = parse_bool(value)?,
+            "pick_child_controls" => self.pick_child_controls = parse_bool(value)?,
+            "edge_snap" => self.edge_snap = parse_bool(value)?,
+            "edge_snap_px" => self.edge_snap_px = parse_number(value)?,
+            "date_folders" => self.date_folders = parse_bool(value)?,
+            "output_dir" => self.output_dir = value.to_string(),
+            "deferred_confirm" => self.deferred_confirm = parse_bool(value)?,
+            "capture_all_screens" => self.capture_all_screens = parse_bool(value)?,
+            "work_area_only" => self.work_area_only = parse_bool(value)?,
+            "save_sound" => self.save_sound = parse_bool(value)?,
+            "save_toast" => self.save_toast = parse_bool(value)?,
+            "dpi_tag" => self.dpi_tag = parse_bool(value)?,
+            "ocr_text_crop" => self.ocr_text_crop = parse_bool(value)?,
+            "scale_variants" => self.scale_variants = parse_scales(value)?,
+            "precapture_ms" => self.precapture_ms = parse_number(value)?,
+            "precapture_interval_ms" => self.precapture_interval_ms = parse_number(value)?,
+            "precapture_max_mb" => self.precapture_max_mb = parse_number(value)?,
+            "live_preview" => self.live_preview = parse_bool(value)?,
+            "live_preview_interval_ms" => self.live_preview_interval_ms = parse_number(value)?,
+            "idle_timeout_secs" => self.idle_timeout_secs = parse_number(value)?,
+            "active_fps" => self.active_fps = parse_number(value)?,
+            "idle_fps" => self.idle_fps = parse_number(value)?,
+            "battery_fps" => self.battery_fps = parse_number(value)?,
+            "channel_order" => {
+                self.channel_order = match value.to_ascii_lowercase().as_str() {
+                    "rgba" => ChannelOrder::Rgba,
+                    "bgra" => ChannelOrder::Bgra,
+                    _ => return Err(format!("expected RGBA or BGRA, got '{}'", value)),
+                }
+            }
+            "border_style" => {
+                self.border_style = match value {
+                    "crisp" => BorderStyle::Crisp,
+                    "smooth" => BorderStyle::Smooth,
+                    "ants" => BorderStyle::Ants,
+                    _ => return Err(format!("expected crisp, smooth or ants, got '{}'", value)),
+                }
+            }
+            "corner_radius" => self.corner_radius = parse_number(value)?,
+            "handle_size" => self.handle_size = parse_number::<u32>(value)?.max(1),
+            "handle_grab_tolerance" => self.handle_grab_tolerance = parse_number(value)?,
+            "sheet_region" => self.sheet_region = Some(parse_region(value)?),
+            "sheet_interval_ms" => self.sheet_interval_ms = parse_number(value)?,
+            "sheet_count" => self.sheet_count = parse_number(value)?,
+            "sheet_columns" => self.sheet_columns = parse_number(value)?,
+            "sheet_max_mb" => self.sheet_max_mb = parse_number(value)?,
+            "append_direction" => {
+                self.append_direction = match value {
+                    "horizontal" => AppendDirection::Horizontal,
+                    "vertical" => AppendDirection::Vertical,
+                    _ => return Err(format!("expected horizontal or vertical, got '{}'", value)),
+                }
+            }
+            "clipboard_formats" => {
+                self.clipboard_formats = value
+                    .split(',')
+                    .map(|part| part.trim())
+                    .filter(|part| !part.is_empty())
+                    .map(|part| match part.to_ascii_lowercase().as_str() {
+                        "dib" => Ok(ClipboardFormat::Dib),
+                        "png" => Ok(ClipboardFormat::Png),
+                        _ => Err(format!("expected dib or png, got '{}'", part)),
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+            "stay_after_save" => self.stay_after_save = parse_bool(value)?,
+            "skip_duplicate_saves" => self.skip_duplicate_saves = parse_bool(value)?,
+            "debug_timings" => self.debug_timings = parse_bool(value)?,
+            "embed_srgb_profile" => self.embed_srgb_profile = parse_bool(value)?,
+            "upload_url" => self.upload_url = value.to_string(),
+            "upload_field" => self.upload_field = value.to_string(),
+            "upload_response_path" => self.upload_response_path = value.to_string(),
+            "upload_save_file" => self.upload_save_file = parse_bool(value)?,
+            "hdr_tone_map" => self.hdr_tone_map = parse_bool(value)?,
+            "hdr_gamma" => self.hdr_gamma = parse_number(value)?,
+            "mouse_gesture" => self.mouse_gesture = parse_gesture(value)?,
+            "gesture_modifiers" => self.gesture_modifiers = parse_modifiers(value)?,
+            "adjust_keys" => self.adjust_keys = parse_bool(value)?,
+            "bake_border" => self.bake_border = parse_bool(value)?,
+            "small_drag_action" => {
+                self.small_drag_action = match value {
+                    "revert" => SmallDragAction::Revert,
+                    "pick_color" => SmallDragAction::PickColor,
+                    "default_region" => SmallDragAction::DefaultRegion,
+                    _ => {
+                        return Err(format!(
+                            "expected revert, pick_color or default_region, got '{}'",
+                            value
+                        ));
+                    }
+                }
+            }
+            "small_drag_width" => self.small_drag_width = parse_number(value)?,
+            "small_drag_height" => self.small_drag_height = parse_number(value)?,
+            "magnet" => self.magnet = parse_bool(value)?,
+            "magnet_radius" => self.magnet_radius = parse_number(value)?,
+            "open_in_editor" => self.open_in_editor = parse_bool(value)?,
+            "title_names" => self.title_names = parse_bool(value)?,
+            "capture_time_names" => self.capture_time_names = parse_bool(value)?,
+            "display_interpolation" => {
+                self.display_interpolation = match value {
+                    "nearest" => Interpolation::Nearest,
+                    "bilinear" => Interpolation::Bilinear,
+                    _ => return Err(format!("expected nearest or bilinear, got '{}'", value)),
+                }
+            }
+            "coordinate_format" => self.coordinate_format = value.to_string(),
+            "copy_coordinates_on_save" => self.copy_coordinates_on_save = parse_bool(value)?,
+            "data_uri_format" => {
+                self.data_uri_format = match value {
+                    "webp" => DataUriFormat::Webp,
+                    "png" => DataUriFormat::Png,
+                    _ => return Err(format!("expected webp or png, got '{}'", value)),
+                }
+            }
+            "save_format" => {
+                self.save_format = match value {
+                    "webp" => SaveFormat::Webp,
+                    "svg" => SaveFormat::Svg,
+                    _ => return Err(format!("expected webp or svg, got '{}'", value)),
+                }
+            }
+            "overlay_mode" => {
+                self.overlay_mode = match value {
+                    "frozen" => OverlayMode::Frozen,
+                    "live" => OverlayMode::Live,
+                    _ => return Err(format!("expected frozen or live, got '{}'", value)),
+                }
+            }
+            "flatten_background" => {
+                self.flatten_background = match value {
+                    "none" | "" => None,
+                    color => Some(parse_color(color)?),
+                }
+            }
+            "dim_tint" => {
+                self.dim_tint = match value {
+                    "none" | "" => None,
+                    color => Some(parse_color(color)?),
+                }
+            }
+            "coordinate_space" => {
+                self.coordinate_space = match value {
+                    "desktop" => CoordinateSpace::Desktop,
+                    "monitor" => CoordinateSpace::Monitor,
+                    _ => return Err(format!("expected desktop or monitor, got '{}'", value)),
+                }
+            }
+            "watermark_text" => self.watermark_text = value.to_string(),
+            "watermark_image" => self.watermark_image = value.to_string(),
+            "watermark_color" => self.watermark_color = parse_color(value)?,
+            "watermark_corner" => {
+                self.watermark_corner = match value {
+                    "top-left" => WatermarkCorner::TopLeft,
+                    "top-right" => WatermarkCorner::TopRight,
+                    "bottom-left" => WatermarkCorner::BottomLeft,
+                    "bottom-right" => WatermarkCorner::BottomRight,
+                    _ => {
+                        return Err(format!(
+                            "expected top-left, top-right, bottom-left or bottom-right, got '{}'",
+                            value
+                        ));
+                    }
+                }
+            }
+            "watermark_opacity" => self.watermark_opacity = parse_number(value)?,
+            "dim_tint_strength" => self.dim_tint_strength = parse_number(value)?,
+            "preset" => self.presets.push(Preset::parse(value)?),
+            "format_dir" => {
+                let (format, dir) = value
+                    .split_once('|')
+                    .ok_or_else(|| format!("expected 'format | directory', got '{}'", value))?;
+                let format = format.trim().trim_start_matches('.').to_ascii_lowercase();
+                self.format_dirs.retain(|(existing, _)| *existing != format);
+                self.format_dirs.push((format, dir.trim().to_string()));
+            }
+            "shadow_margin" => self.shadow_margin = parse_number(value)?,
+            "shadow_color" => self.shadow_color = parse_color(value)?,
+            "shadow_blur" => self.shadow_blur = parse_number(value)?,
+            "shadow_offset" => self.shadow_offset = parse_number(value)?,
+            "selection_shape" => {
+                self.selection_shape = match value {
+                    "rectangle" => SelectionShape::Rectangle,
+                    "ellipse" => SelectionShape::Ellipse,
+                    _ => return Err(format!("expected rectangle or ellipse, got '{}'", value)),
+                }
+            }
+            _ => return Err(format!("unknown option '{}'", key)),
+        }
+        Ok(())
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(format!("expected true/false, got '{}'", value)),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("expected a number, got '{}'", value))
+}
+
+// 逗号分隔的正数倍率列表，空值表示不启用
+fn parse_scales(value: &str) -> Result<Vec<f32>, String> {
+    value
+        .split(',')
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .map(
+            |part| match part.trim_end_matches(['x', 'X']).parse::<f32>() {
+                Ok(scale) if scale > 0.0 => Ok(scale),
+                _ => Err(format!("expected a positive scale factor, got '{}'", part)),
+            },
+        )
+        .collect()
+}
+
+// 解析颜色："#RRGGBB"（不透明）或 "#AARRGGBB" / "0xAARRGGBB"
+pub fn parse_color(value: &str) -> Result<u32, String> {
+    let hex = value
+        .strip_prefix('#')
+        .or_else(|| value.strip_prefix("0x"))
+        .unwrap_or(value);
+    let color = u32::from_str_radix(hex, 16)
+        .map_err(|_| format!("expected a hex color, got '{}'", value))?;
+    match hex.len() {
+        6 => Ok(0xFF000000 | color),
+        8 => Ok(color),
+        _ => Err(format!("expected #RRGGBB or #AARRGGBB, got '{}'", value)),
+    }
+}
+
+// 配置文件中一行 preset 的名称，其他行为 None
+fn preset_name(line: &str) -> Option<&str> {
+    let (key, value) = line.split_once('=')?;
+    (key.trim() == "preset").then(|| value.split('|').next().unwrap_or("").trim())
+}
+
+// 配置文件中是否已有同名（不区分大小写）的 preset
+pub fn preset_exists(name: &str) -> bool {
+    std::fs::read_to_string(CONFIG_FILE).is_ok_and(|text| {
+        text.lines()
+            .filter_map(preset_name)
+            .any(|existing| existing.eq_ignore_ascii_case(name))
+    })
+}
+
+// 把命名区域写入配置文件：已有同名 preset 行时替换该行，否则追加到末尾；下次启动时生效
+pub fn save_preset(preset: &Preset) -> std::io::Result<()> {
+    let text = match std::fs::read_to_string(CONFIG_FILE) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    std::fs::write(CONFIG_FILE, with_preset(&text, preset))
+}
+
+// 配置文件文本中替换同名 preset 行或在末尾追加一行，其余内容（包括换行符 CRLF/LF）保持不变
+pub fn with_preset(text: &str, preset: &Preset) -> String {
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut result = String::with_capacity(text.len() + 80);
+    let mut replaced = false;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\r', '\n']);
+        if !replaced
+            && preset_name(content).is_some_and(|name| name.eq_ignore_ascii_case(&preset.name))
+        {
+            result.push_str(&preset.line());
+            result.push_str(&line[content.len()..]);
+            replaced = true;
+        } else {
+            result.push_str(line);
+        }
+    }
+    if !replaced {
+        if !result.is_empty() && !result.ends_with('\n') {
+            result.push_str(newline);
+        }
+        result.push_str(&preset.line());
+        result.push_str(newline);
+    }
+    result
+}
+
+// 解析 "x,y,w,h" 形式的区域
+pub fn parse_region(value: &str) -> Result<(i32, i32, i32, i32), String> {
+    let parts = value
+        .split(',')
+        .map(|p| parse_number::<i32>(p.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    match parts[..] {
+        [x, y, w, h] if w > 0 && h > 0 => Ok((x, y, w, h)),
+        _ => Err(format!(
+            "expected x,y,w,h with positive size, got '{}'",
+            value
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hotkeys_round_trip_through_display() {
+        for text in [
+            "Ctrl+Alt+D",
+            "Shift+F2",
+            "Shift+Win+7",
+            "Ctrl+F24",
+            "PrintScreen",
+        ] {
+            let hotkey = Hotkey::parse(text).unwrap();
+            assert_eq!(hotkey.to_string(), text);
+            assert_eq!(Hotkey::parse(&hotkey.to_string()), Ok(hotkey));
+        }
+        // 名称不区分大小写，修饰键顺序按 Ctrl、Alt、Shift、Win 输出
+        let hotkey = Hotkey::parse("shift + ctrl + f13").unwrap();
+        assert_eq!(hotkey.vk, 0x7C);
+        assert_eq!(hotkey.to_string(), "Ctrl+Shift+F13");
+
+        assert_eq!(parse_vk("f1"), Some(0x70));
+        assert_eq!(parse_vk("f24"), Some(0x87));
+        assert_eq!(parse_vk("f25"), None);
+        assert!(Hotkey::parse("Ctrl+A+B").is_err());
+        assert!(Hotkey::parse("Ctrl+").is_err());
+    }
+
+    #[test]
+    fn saving_a_preset_keeps_the_other_lines_and_line_endings() {
+        let preset = Preset {
+            name: "Chat".to_string(),
+            region: (1, 2, 3, 4),
+            monitor: 0,
+            hotkey: Hotkey {
+                modifiers: MOD_CONTROL as u32,
+                vk: 'K' as u32,
+            },
+        };
+        let line = preset.line();
+
+        // 同名（不区分大小写）的行原地替换，CRLF 保留
+        let text = "capture_delay_ms = 0\r\npreset = chat | 0,0,9,9 | 1 | Ctrl+J\r\n# end";
+        assert_eq!(
+            with_preset(text, &preset),
+            format!("capture_delay_ms = 0\r\n{}\r\n# end", line)
+        );
+        // 没有同名行时按原文件的换行符追加
+        assert_eq!(
+            with_preset("a = 1\r\n", &preset),
+            format!("a = 1\r\n{}\r\n", line)
+        );
+        assert_eq!(with_preset("a = 1", &preset), format!("a = 1\n{}\n", line));
+        assert_eq!(with_preset("", &preset), format!("{}\n", line));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::config::Config;
+use crate::timer::spawn_stop_listener;
+use crate::{
+    NameContext, capture_region, clamp_to_image, hotkey, output_file_name, transform, write_webp,
+};
+use image::{ImageBuffer, Rgba};
+use screenshots::Screen;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+
+// 格与格之间以及四周的间隔（像素）
+const SHEET_GAP: u32 = 4;
+
+// 联系表（--contact-sheet）：每隔 sheet_interval_ms 截取一次 sheet_region（未配置时用 timer_region），
+// 共 sheet_count 张，按 sheet_columns 列拼成一张图保存；按停止热键提前结束并保存已截取的部分
+pub fn run(config: &Config) {
+    let Some(region) = config.sheet_region.or(config.timer_region) else {
+        eprintln!("No region for the contact sheet: set sheet_region in captrue.ini");
+        return;
+    };
+
+    // 枚举屏幕失败时报错退出，不启动停止热键监听
+    let screens = match Screen::all() {
+        Ok(screens) if !screens.is_empty() => screens,
+        Ok(_) => {
+            eprintln!("Failed to enumerate screens: no screen found");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Failed to enumerate screens: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let primary_screen = &screens[0];
+
+    // 先裁到屏幕内，每一格都按同一尺寸截取，tile 才能按第一格的尺寸排列
+    let info = &primary_screen.display_info;
+    let (sx, sy, sw, sh) = region;
+    let Some((left, top, width, height)) =
+        clamp_to_image((sx, sy, sw as u32, sh as u32), (info.width, info.height))
+    else {
+        eprintln!(
+            "Contact sheet region {},{} {}x{} is outside the screen",
+            sx, sy, sw, sh
+        );
+        return;
+    };
+    let (x, y, w, h) = (left as i32, top as i32, width as i32, height as i32);
+    let count = config.sheet_count.max(1);
+    let columns = config.sheet_columns.clamp(1, count);
+
+    // 成品超过 sheet_max_mb 时按比例缩小每一格
+    let (sheet_width, sheet_height) = sheet_size(w as u32, h as u32, count, columns);
+    let max_bytes = config.sheet_max_mb as f64 * 1024.0 * 1024.0;
+    let ratio = (max_bytes / (sheet_width as f64 * sheet_height as f64 * 4.0))
+        .sqrt()
+        .min(1.0) as f32;
+    if ratio < 1.0 {
+        println!(
+            "Contact sheet would exceed {} MB, tiles are scaled to {:.0}%",
+            config.sheet_max_mb,
+            ratio * 100.0
+        );
+    }
+
+    let rx = spawn_stop_listener(config);
+    let interval = Duration::from_millis(config.sheet_interval_ms);
+    println!(
+        "Contact sheet of Lx{}Ty{}W{}H{}: {} captures every {} ms, press {} to stop early",
+        x, y, w, h, count, config.sheet_interval_ms, config.stop_hotkey
+    );
+
+    let mut tiles = Vec::with_capacity(count as usize);
+    loop {
+        match capture_region(
+            primary_screen,
+            (x, y, w as u32, h as u32),
+            config.channel_order,
+        ) {
+            Ok(crop) => {
+                tiles.push(transform::scale(&crop, ratio));
+                println!("Captured {}/{}", tiles.len(), count);
+            }
+            Err(e) => eprintln!("Failed to capture screen: {}", e),
+        }
+        if tiles.len() >= count as usize {
+            break;
+        }
+
+        match rx.recv_timeout(interval) {
+            Err(RecvTimeoutError::Timeout) => continue,
+            _ => {
+                println!("Stopped early after {} captures", tiles.len());
+                break;
+            }
+        }
+    }
+    hotkey::shutdown();
+
+    if tiles.is_empty() {
+        return;
+    }
+    let sheet = tile(&tiles, columns);
+    let file_name = format!(
+        "{}_sheet{}.webp",
+        output_file_name(
+            &NameContext {
+                region: (x, y, w as u32, h as u32),
+                screen: (
+                    primary_screen.display_info.width,
+                    primary_screen.display_info.height
+                ),
+                title: None,
+                ext: "webp",
+                dpi: None,
+                captured_at: None,
+            },
+            config,
+        ),
+        tiles.len()
+    );
+    write_webp(&sheet, &file_name, (x, y, w, h));
+}
+
+// 把尺寸相同的图像按 columns 列依次排列，格间和四周填充深灰色
+pub fn tile(
+    tiles: &[ImageBuffer<Rgba<u8>, Vec<u8>>],
+    columns: u32,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let (tile_width, tile_height) = tiles.first().map_or((0, 0), |t| t.dimensions());
+    let columns = columns.clamp(1, (tiles.len() as u32).max(1));
+    let (width, height) = sheet_size(tile_width, tile_height, tiles.len() as u32, columns);
+
+    let mut sheet = ImageBuffer::from_pixel(width, height, Rgba([32, 32, 32, 255]));
+    for (i, tile) in tiles.iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        let left = SHEET_GAP + column * (tile_width + SHEET_GAP);
+        let top = SHEET_GAP + row * (tile_height + SHEET_GAP);
+        image::imageops::replace(&mut sheet, tile, left as i64, top as i64);
+    }
+    sheet
+}
+
+fn sheet_size(tile_width: u32, tile_height: u32, count: u32, columns: u32) -> (u32, u32) {
+    let rows = count.div_ceil(columns);
+    (
+        columns * (tile_width + SHEET_GAP) + SHEET_GAP,
+        rows * (tile_height + SHEET_GAP) + SHEET_GAP,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_fill_rows_left_to_right_with_gaps() {
+        let colors = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]];
+        let tiles: Vec<_> = colors
+            .iter()
+            .map(|&color| ImageBuffer::from_pixel(3, 2, Rgba(color)))
+            .collect();
+
+        // 3 格 2 列：两行，第二行只有一格
+        let sheet = tile(&tiles, 2);
+        assert_eq!(
+            sheet.dimensions(),
+            (
+                2 * (3 + SHEET_GAP) + SHEET_GAP,
+                2 * (2 + SHEET_GAP) + SHEET_GAP
+            )
+        );
+        let gap = SHEET_GAP;
+        assert_eq!(sheet.get_pixel(gap, gap).0, colors[0]);
+        assert_eq!(sheet.get_pixel(gap * 2 + 3, gap).0, colors[1]);
+        assert_eq!(sheet.get_pixel(gap, gap * 2 + 2).0, colors[2]);
+        // 格间和空出的格子为背景色
+        assert_eq!(sheet.get_pixel(0, 0).0, [32, 32, 32, 255]);
+        assert_eq!(
+            sheet.get_pixel(gap * 2 + 3, gap * 2 + 2).0,
+            [32, 32, 32, 255]
+        );
+
+        // 列数超过格数时按格数排成一行
+        assert_eq!(
+            tile(&tiles, 10).dimensions(),
+            (3 * (3 + SHEET_GAP) + SHEET_GAP, 2 + SHEET_GAP * 2)
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use crate::capture_screen;
+use crate::config::ChannelOrder;
+use image::{ImageBuffer, Rgba};
+use screenshots::Screen;
+
+// 一块屏幕的截图及其在虚拟桌面中的位置
+type ScreenPart = ((i32, i32), ImageBuffer<Rgba<u8>, Vec<u8>>);
+
+// 所有屏幕拼成的虚拟桌面的外接矩形 (x, y, w, h)，坐标相对主屏左上角，可为负
+pub fn bounds(screens: &[Screen]) -> (i32, i32, u32, u32) {
+    let rects: Vec<_> = screens
+        .iter()
+        .map(|screen| {
+            let info = &screen.display_info;
+            (info.x, info.y, info.width, info.height)
+        })
+        .collect();
+    union(&rects)
+}
+
+fn union(rects: &[(i32, i32, u32, u32)]) -> (i32, i32, u32, u32) {
+    let left = rects.iter().map(|r| r.0).min().unwrap_or(0);
+    let top = rects.iter().map(|r| r.1).min().unwrap_or(0);
+    let right = rects.iter().map(|r| r.0 + r.2 as i32).max().unwrap_or(0);
+    let bottom = rects.iter().map(|r| r.1 + r.3 as i32).max().unwrap_or(0);
+    (left, top, (right - left) as u32, (bottom - top) as u32)
+}
+
+// 按各屏幕在虚拟桌面中的位置拼接截图，屏幕之间的空隙填充为不透明黑色
+pub fn stitch(parts: &[ScreenPart]) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let rects: Vec<_> = parts
+        .iter()
+        .map(|((x, y), image)| (*x, *y, image.width(), image.height()))
+        .collect();
+    let (left, top, width, height) = union(&rects);
+
+    let mut desktop = ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 255]));
+    for ((x, y), image) in parts {
+        image::imageops::replace(&mut desktop, image, (x - left) as i64, (y - top) as i64);
+    }
+    desktop
+}
+
+// 截取所有屏幕并拼接为一张虚拟桌面图像
+pub fn capture_all(
+    screens: &[Screen],
+    channel_order: ChannelOrder,
+) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Box<dyn std::error::Error>> {
+    let mut parts = Vec::with_capacity(screens.len());
+    for screen in screens {
+        let position = (screen.display_info.x, screen.display_info.y);
+        parts.push((position, capture_screen(screen, channel_order)?));
+    }
+    Ok(stitch(&parts))
+}
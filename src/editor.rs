@@ -0,0 +1,42 @@
+use std::path::Path;
+use std::ptr::null_mut;
+use winapi::um::shellapi::ShellExecuteW;
+use winapi::um::winuser::SW_SHOWNORMAL;
+
+// 用系统默认编辑器打开图像：先用 "edit" 动作，未注册时用 "open"；都失败时打开所在文件夹
+pub fn open(path: &Path) -> Result<(), String> {
+    if shell_execute("edit", path) || shell_execute("open", path) {
+        return Ok(());
+    }
+
+    eprintln!(
+        "No editor is registered for {}, opening its folder",
+        path.display()
+    );
+    match path.parent() {
+        Some(folder) if shell_execute("open", folder) => Ok(()),
+        _ => Err(format!("Failed to open {}", path.display())),
+    }
+}
+
+// ShellExecuteW 返回值大于 32 表示成功
+fn shell_execute(verb: &str, path: &Path) -> bool {
+    let verb: Vec<u16> = verb.encode_utf16().chain(Some(0)).collect();
+    let file: Vec<u16> = path
+        .as_os_str()
+        .to_string_lossy()
+        .encode_utf16()
+        .chain(Some(0))
+        .collect();
+    let result = unsafe {
+        ShellExecuteW(
+            null_mut(),
+            verb.as_ptr(),
+            file.as_ptr(),
+            null_mut(),
+            null_mut(),
+            SW_SHOWNORMAL,
+        )
+    };
+    result as isize > 32
+}
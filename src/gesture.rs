@@ -0,0 +1,120 @@
+use crate::AppEvent;
+use crate::config::Direction;
+use std::ptr::null_mut;
+use std::sync::Mutex;
+use std::sync::mpsc::Sender;
+use winapi::ctypes::c_int;
+use winapi::shared::minwindef::{LPARAM, LRESULT, UINT, WPARAM};
+use winapi::shared::windef::HHOOK;
+use winapi::um::winuser::{
+    CallNextHookEx, MSLLHOOKSTRUCT, SetWindowsHookExW, UnhookWindowsHookEx, WH_MOUSE_LL,
+    WM_MOUSEMOVE,
+};
+
+// 手势每一段的最小移动距离（像素）
+const SEGMENT_PX: i32 = 40;
+
+// 按住修饰键时的鼠标轨迹：每移动 SEGMENT_PX 记一次主方向（连续相同的方向合并），
+// 最近的几段与配置的手势一致时触发截图
+struct Tracker {
+    gesture: Vec<Direction>,
+    modifiers: u32,
+    anchor: Option<(i32, i32)>,
+    strokes: Vec<Direction>,
+    events: Sender<AppEvent>,
+}
+
+static TRACKER: Mutex<Option<Tracker>> = Mutex::new(None);
+
+// 低级鼠标钩子，离开作用域时卸载
+pub struct GestureHook(HHOOK);
+
+impl GestureHook {
+    // 在当前线程安装钩子（该线程须运行消息循环），识别到手势时发送 GlobalHotkeyPressed；手势为空时不安装
+    pub fn install(
+        gesture: &[Direction],
+        modifiers: u32,
+        events: Sender<AppEvent>,
+    ) -> Option<Self> {
+        if gesture.is_empty() {
+            return None;
+        }
+        *TRACKER.lock().unwrap() = Some(Tracker {
+            gesture: gesture.to_vec(),
+            modifiers,
+            anchor: None,
+            strokes: Vec::new(),
+            events,
+        });
+
+        let hook = unsafe { SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_proc), null_mut(), 0) };
+        if hook.is_null() {
+            eprintln!("Failed to install the mouse gesture hook");
+            *TRACKER.lock().unwrap() = None;
+            return None;
+        }
+        Some(Self(hook))
+    }
+}
+
+impl Drop for GestureHook {
+    fn drop(&mut self) {
+        unsafe {
+            UnhookWindowsHookEx(self.0);
+        }
+        *TRACKER.lock().unwrap() = None;
+    }
+}
+
+// 只观察鼠标移动，不拦截任何输入
+unsafe extern "system" fn mouse_proc(code: c_int, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code >= 0 && wparam as UINT == WM_MOUSEMOVE {
+        let point = unsafe { (*(lparam as *const MSLLHOOKSTRUCT)).pt };
+        if let Some(tracker) = TRACKER.lock().unwrap().as_mut() {
+            tracker.feed((point.x, point.y));
+        }
+    }
+    unsafe { CallNextHookEx(null_mut(), code, wparam, lparam) }
+}
+
+impl Tracker {
+    fn feed(&mut self, point: (i32, i32)) {
+        // 松开修饰键即放弃当前轨迹
+        if !modifiers_held(self.modifiers) {
+            self.anchor = None;
+            self.strokes.clear();
+            return;
+        }
+
+        let anchor = *self.anchor.get_or_insert(point);
+        let (dx, dy) = (point.0 - anchor.0, point.1 - anchor.1);
+        if dx.abs().max(dy.abs()) < SEGMENT_PX {
+            return;
+        }
+        self.anchor = Some(point);
+
+        let direction = match (dx.abs() > dy.abs(), dx > 0, dy > 0) {
+            (true, true, _) => Direction::Right,
+            (true, false, _) => Direction::Left,
+            (false, _, true) => Direction::Down,
+            (false, _, false) => Direction::Up,
+        };
+        if self.strokes.last() == Some(&direction) {
+            return;
+        }
+        self.strokes.push(direction);
+        if self.strokes.len() > self.gesture.len() {
+            self.strokes.remove(0);
+        }
+
+        if self.strokes == self.gesture {
+            self.strokes.clear();
+            let _ = self.events.send(AppEvent::GlobalHotkeyPressed);
+        }
+    }
+}
+
+// modifiers（MOD_* 组合）中的修饰键是否都按下
+fn modifiers_held(modifiers: u32) -> bool {
+    crate::Modifiers::poll().flags() & modifiers == modifiers
+}
@@ -0,0 +1,119 @@
+use image::{ImageBuffer, Rgba};
+use screenshots::Screen;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use winapi::Interface;
+use winapi::ctypes::c_void;
+use winapi::shared::dxgi::{CreateDXGIFactory1, IDXGIAdapter1, IDXGIFactory1, IDXGIOutput};
+use winapi::shared::dxgi1_6::{DXGI_OUTPUT_DESC1, IDXGIOutput6};
+use winapi::shared::dxgitype::DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020;
+use winapi::shared::windef::{HMONITOR, POINT};
+use winapi::shared::winerror::SUCCEEDED;
+use winapi::um::winuser::{MONITOR_DEFAULTTONEAREST, MonitorFromPoint};
+
+// 截到 HDR 显示器时是否做色调映射，以及映射用的 gamma（hdr_tone_map / hdr_gamma，启动时设置一次）
+static TONE_MAP: AtomicBool = AtomicBool::new(true);
+static GAMMA: AtomicU32 = AtomicU32::new(0);
+// 每次运行只提示一次
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+pub fn configure(tone_map: bool, gamma: f32) {
+    TONE_MAP.store(tone_map, Ordering::Relaxed);
+    GAMMA.store(gamma.to_bits(), Ordering::Relaxed);
+}
+
+// 截图来自 HDR 显示器时提示一次，并按配置做色调映射：
+// HDR 桌面经 GDI 截取后是未经映射的 SDR 值，整体发灰偏亮，用 gamma 曲线压暗中间调恢复对比度
+pub fn correct(image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, screen: &Screen) {
+    if !is_hdr(screen) {
+        return;
+    }
+    let tone_map = TONE_MAP.load(Ordering::Relaxed);
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        eprintln!(
+            "Warning: capturing from an HDR display, colors may look washed out{}",
+            if tone_map {
+                " (applying tone mapping, see hdr_gamma)"
+            } else {
+                ""
+            }
+        );
+    }
+    if tone_map {
+        tone_map_gamma(image, f32::from_bits(GAMMA.load(Ordering::Relaxed)));
+    }
+}
+
+// 逐通道查表：out = in^gamma，alpha 不变
+fn tone_map_gamma(image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, gamma: f32) {
+    if gamma <= 0.0 || gamma == 1.0 {
+        return;
+    }
+    let lut: Vec<u8> = (0..=255)
+        .map(|v| ((v as f32 / 255.0).powf(gamma) * 255.0).round() as u8)
+        .collect();
+    for pixel in image.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = lut[*channel as usize];
+        }
+    }
+}
+
+// 屏幕所在的显示器是否处于 HDR 模式（色彩空间为 PQ/BT.2020）
+pub fn is_hdr(screen: &Screen) -> bool {
+    let info = &screen.display_info;
+    let center = POINT {
+        x: info.x + info.width as i32 / 2,
+        y: info.y + info.height as i32 / 2,
+    };
+    let monitor = unsafe { MonitorFromPoint(center, MONITOR_DEFAULTTONEAREST) };
+    hdr_monitors().contains(&monitor)
+}
+
+// 通过 DXGI 枚举所有显卡的输出，返回处于 HDR 模式的显示器；系统不支持 IDXGIOutput6（Windows 10 1703 之前）时为空
+fn hdr_monitors() -> Vec<HMONITOR> {
+    let mut monitors = Vec::new();
+    unsafe {
+        let mut factory: *mut IDXGIFactory1 = null_mut();
+        if !SUCCEEDED(CreateDXGIFactory1(
+            &IDXGIFactory1::uuidof(),
+            &mut factory as *mut _ as *mut *mut c_void,
+        )) {
+            return monitors;
+        }
+
+        let mut adapter_index = 0;
+        loop {
+            let mut adapter: *mut IDXGIAdapter1 = null_mut();
+            if !SUCCEEDED((*factory).EnumAdapters1(adapter_index, &mut adapter)) {
+                break;
+            }
+            let mut output_index = 0;
+            loop {
+                let mut output: *mut IDXGIOutput = null_mut();
+                if !SUCCEEDED((*adapter).EnumOutputs(output_index, &mut output)) {
+                    break;
+                }
+                let mut output6: *mut IDXGIOutput6 = null_mut();
+                if SUCCEEDED((*output).QueryInterface(
+                    &IDXGIOutput6::uuidof(),
+                    &mut output6 as *mut _ as *mut *mut c_void,
+                )) {
+                    let mut desc: DXGI_OUTPUT_DESC1 = std::mem::zeroed();
+                    if SUCCEEDED((*output6).GetDesc1(&mut desc))
+                        && desc.ColorSpace == DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020
+                    {
+                        monitors.push(desc.Monitor);
+                    }
+                    (*output6).Release();
+                }
+                (*output).Release();
+                output_index += 1;
+            }
+            (*adapter).Release();
+            adapter_index += 1;
+        }
+        (*factory).Release();
+    }
+    monitors
+}
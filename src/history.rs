@@ -0,0 +1,176 @@
+use image::{ImageBuffer, Rgba};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// 缩略图尺寸上限（保持宽高比）
+pub const THUMBNAIL_WIDTH: u32 = 192;
+pub const THUMBNAIL_HEIGHT: u32 = 144;
+
+const INDEX_FILE: &str = "index.txt";
+
+// 历史记录中的一次保存
+pub struct HistoryEntry {
+    pub saved_path: String,
+    pub region: (i32, i32, i32, i32),
+    pub thumbnail: Vec<u32>, // ARGB 缩略图
+    pub thumbnail_width: u32,
+    pub thumbnail_height: u32,
+}
+
+// 历史目录：%APPDATA%\captrue\history，没有 APPDATA 时使用工作目录下的 history
+fn history_dir() -> PathBuf {
+    std::env::var_os("APPDATA")
+        .map(|dir| PathBuf::from(dir).join("captrue").join("history"))
+        .unwrap_or_else(|| PathBuf::from("history"))
+}
+
+// 索引每行：缩略图文件名\tx,y,w,h\t保存路径，最新的在前
+fn read_index() -> Vec<(String, String, String)> {
+    let text = std::fs::read_to_string(history_dir().join(INDEX_FILE)).unwrap_or_default();
+    text.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            Some((
+                parts.next()?.to_string(),
+                parts.next()?.to_string(),
+                parts.next()?.to_string(),
+            ))
+        })
+        .collect()
+}
+
+// 记录一次保存：生成缩略图并写入索引，只保留最近 limit 条
+pub fn record(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    region: (i32, i32, i32, i32),
+    saved_path: &str,
+    limit: usize,
+) {
+    let dir = history_dir();
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        eprintln!("Failed to create history directory: {}", e);
+        return;
+    }
+
+    let (x, y, w, h) = region;
+    let crop =
+        image::imageops::crop_imm(image, x.max(0) as u32, y.max(0) as u32, w as u32, h as u32)
+            .to_image();
+    let scale = (THUMBNAIL_WIDTH as f32 / crop.width() as f32)
+        .min(THUMBNAIL_HEIGHT as f32 / crop.height() as f32)
+        .min(1.0);
+    let thumbnail = image::imageops::thumbnail(
+        &crop,
+        ((crop.width() as f32 * scale) as u32).max(1),
+        ((crop.height() as f32 * scale) as u32).max(1),
+    );
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let thumbnail_file = format!("{}.png", timestamp);
+    if let Err(e) = thumbnail.save(dir.join(&thumbnail_file)) {
+        eprintln!("Failed to save history thumbnail: {}", e);
+        return;
+    }
+
+    let saved_path = std::fs::canonicalize(saved_path)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| saved_path.to_string());
+
+    let mut index = read_index();
+    index.insert(
+        0,
+        (
+            thumbnail_file,
+            format!("{},{},{},{}", x, y, w, h),
+            saved_path,
+        ),
+    );
+
+    // 删除超出数量的旧记录
+    for (old_thumbnail, _, _) in index.drain(limit.min(index.len())..) {
+        let _ = std::fs::remove_file(dir.join(old_thumbnail));
+    }
+
+    let text: String = index
+        .iter()
+        .map(|(thumbnail, region, path)| format!("{}\t{}\t{}\n", thumbnail, region, path))
+        .collect();
+    if let Err(e) = std::fs::write(dir.join(INDEX_FILE), text) {
+        eprintln!("Failed to write history index: {}", e);
+    }
+}
+
+// 读取历史记录及缩略图，无法读取的条目跳过
+pub fn load() -> Vec<HistoryEntry> {
+    let dir = history_dir();
+    read_index()
+        .into_iter()
+        .filter_map(|(thumbnail_file, region, saved_path)| {
+            let region = crate::config::parse_region(&region).ok()?;
+            let thumbnail = image::open(dir.join(thumbnail_file)).ok()?.to_rgba8();
+            let (thumbnail_width, thumbnail_height) = thumbnail.dimensions();
+            let thumbnail = thumbnail
+                .pixels()
+                .map(|p| 0xFF000000 | (p[0] as u32) << 16 | (p[1] as u32) << 8 | p[2] as u32)
+                .collect();
+            Some(HistoryEntry {
+                saved_path,
+                region,
+                thumbnail,
+                thumbnail_width,
+                thumbnail_height,
+            })
+        })
+        .collect()
+}
+
+// 重新打开保存过的截图：放回原来的屏幕位置，其余部分填充深色
+pub fn reopen(
+    entry: &HistoryEntry,
+    screen_width: u32,
+    screen_height: u32,
+) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String> {
+    let saved = image::open(&entry.saved_path)
+        .map_err(|e| format!("failed to open {}: {}", entry.saved_path, e))?
+        .to_rgba8();
+    let mut canvas =
+        ImageBuffer::from_pixel(screen_width, screen_height, Rgba([0x20, 0x20, 0x20, 0xFF]));
+    image::imageops::overlay(
+        &mut canvas,
+        &saved,
+        entry.region.0 as i64,
+        entry.region.1 as i64,
+    );
+    Ok(canvas)
+}
+
+// 画廊布局：每个格子放一张缩略图
+const GALLERY_MARGIN: u32 = 16;
+
+// 第 index 个格子的左上角
+pub fn cell_origin(index: usize, screen_width: u32) -> (u32, u32) {
+    let columns = gallery_columns(screen_width);
+    let index = index as u32;
+    (
+        GALLERY_MARGIN + (index % columns) * (THUMBNAIL_WIDTH + GALLERY_MARGIN),
+        GALLERY_MARGIN + (index / columns) * (THUMBNAIL_HEIGHT + GALLERY_MARGIN),
+    )
+}
+
+// 点击位置对应的条目
+pub fn hit_test(count: usize, screen_width: u32, x: i32, y: i32) -> Option<usize> {
+    (0..count).find(|&i| {
+        let (left, top) = cell_origin(i, screen_width);
+        x >= left as i32
+            && x < (left + THUMBNAIL_WIDTH) as i32
+            && y >= top as i32
+            && y < (top + THUMBNAIL_HEIGHT) as i32
+    })
+}
+
+fn gallery_columns(screen_width: u32) -> u32 {
+    (screen_width.saturating_sub(GALLERY_MARGIN) / (THUMBNAIL_WIDTH + GALLERY_MARGIN)).max(1)
+}
@@ -0,0 +1,132 @@
+use crate::config::Hotkey;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+use winapi::shared::minwindef::{LPARAM, LRESULT, UINT, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::winuser::{
+    CreateWindowExW, DefWindowProcW, PostThreadMessageW, RegisterClassW, RegisterHotKey,
+    UnregisterHotKey, WM_DISPLAYCHANGE, WM_QUIT, WNDCLASSW,
+};
+
+// 热键线程的线程ID（0 表示没有）。窗口句柄为空时热键绑定在注册它的线程上，只能由该线程注销
+static HOTKEY_THREAD: AtomicU32 = AtomicU32::new(0);
+static RELEASED: AtomicBool = AtomicBool::new(true);
+
+// 当前线程注册的热键，离开作用域（包括 panic 展开）时自动注销
+pub struct HotkeyGuard {
+    ids: Vec<i32>,
+}
+
+impl HotkeyGuard {
+    pub fn new() -> Self {
+        HOTKEY_THREAD.store(unsafe { GetCurrentThreadId() }, Ordering::SeqCst);
+        RELEASED.store(false, Ordering::SeqCst);
+        Self { ids: Vec::new() }
+    }
+
+    pub fn register(&mut self, id: i32, hotkey: Hotkey) -> bool {
+        let registered =
+            unsafe { RegisterHotKey(null_mut(), id, hotkey.modifiers, hotkey.vk) } != 0;
+        if registered {
+            self.ids.push(id);
+        }
+        registered
+    }
+
+    pub fn unregister(&mut self, id: i32) {
+        if let Some(index) = self.ids.iter().position(|&existing| existing == id) {
+            self.ids.remove(index);
+            unsafe {
+                UnregisterHotKey(null_mut(), id);
+            }
+        }
+    }
+}
+
+impl Drop for HotkeyGuard {
+    fn drop(&mut self) {
+        for id in &self.ids {
+            unsafe {
+                UnregisterHotKey(null_mut(), *id);
+            }
+        }
+        HOTKEY_THREAD.store(0, Ordering::SeqCst);
+        RELEASED.store(true, Ordering::SeqCst);
+    }
+}
+
+// 显示器增减或分辨率变化时向当前线程投递 WM_DISPLAYCHANGE（该消息只发给顶层窗口，
+// 为此创建一个隐藏的顶层窗口），供消息循环通知主循环刷新屏幕信息并重新注册按屏幕截图的热键。
+// 窗口随线程结束销毁
+pub fn watch_display_changes() -> bool {
+    unsafe extern "system" fn window_proc(
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        if msg == WM_DISPLAYCHANGE {
+            unsafe {
+                PostThreadMessageW(GetCurrentThreadId(), WM_DISPLAYCHANGE, wparam, lparam);
+            }
+        }
+        unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+    }
+
+    unsafe {
+        let class: Vec<u16> = "CaptrueDisplayWatcher\0".encode_utf16().collect();
+        let instance = GetModuleHandleW(null_mut());
+        let mut window_class: WNDCLASSW = std::mem::zeroed();
+        window_class.lpfnWndProc = Some(window_proc);
+        window_class.hInstance = instance;
+        window_class.lpszClassName = class.as_ptr();
+        RegisterClassW(&window_class);
+        let hwnd = CreateWindowExW(
+            0,
+            class.as_ptr(),
+            null_mut(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            null_mut(),
+            null_mut(),
+            instance,
+            null_mut(),
+        );
+        !hwnd.is_null()
+    }
+}
+
+// 通知热键线程退出消息循环（从而注销热键），最多等待 500ms
+pub fn shutdown() {
+    let thread_id = HOTKEY_THREAD.load(Ordering::SeqCst);
+    if thread_id == 0 {
+        return;
+    }
+    unsafe {
+        PostThreadMessageW(thread_id, WM_QUIT, 0, 0);
+    }
+
+    // 在热键线程自身上调用（如该线程 panic）时，展开过程会释放 HotkeyGuard，无需等待
+    if thread_id == unsafe { GetCurrentThreadId() } {
+        return;
+    }
+    let deadline = Instant::now() + Duration::from_millis(500);
+    while !RELEASED.load(Ordering::SeqCst) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+// panic 时先注销热键，避免热键在崩溃后仍被占用
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        shutdown();
+        default_hook(info);
+    }));
+}
@@ -0,0 +1,174 @@
+use image::codecs::png::PngEncoder;
+use image::{ImageBuffer, ImageEncoder, Rgba};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// 保存的 WebP/PNG 是否嵌入 sRGB 配置文件（embed_srgb_profile，启动时设置一次）
+static EMBED: AtomicBool = AtomicBool::new(false);
+
+// VP8X 标志位
+const VP8X_ICC: u8 = 0x20;
+const VP8X_ALPHA: u8 = 0x10;
+
+pub fn set_embed(embed: bool) {
+    EMBED.store(embed, Ordering::Relaxed);
+}
+
+pub fn embed() -> bool {
+    EMBED.load(Ordering::Relaxed)
+}
+
+// 给编码好的简单格式 WebP（RIFF + VP8L/VP8 块）加上 VP8X 和 ICCP 块；未开启或格式不认识时原样返回
+pub fn tag_webp(webp: &[u8], image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Vec<u8> {
+    if !embed() || webp.len() < 30 || &webp[0..4] != b"RIFF" || &webp[8..12] != b"WEBP" {
+        return webp.to_vec();
+    }
+    let profile = srgb();
+    let mut iccp = chunk(b"ICCP", profile);
+
+    let mut out = Vec::with_capacity(webp.len() + 18 + iccp.len());
+    out.extend_from_slice(b"RIFF\0\0\0\0WEBP");
+    if &webp[12..16] == b"VP8X" {
+        // 已有 VP8X（带动画或元数据）：设置 ICC 标志，ICCP 须紧跟在 VP8X 之后
+        let mut vp8x = webp[12..30].to_vec();
+        vp8x[8] |= VP8X_ICC;
+        out.append(&mut vp8x);
+        out.append(&mut iccp);
+        out.extend_from_slice(&webp[30..]);
+    } else {
+        let has_alpha = image.pixels().any(|p| p[3] != 255);
+        let mut header = [0u8; 10];
+        header[0] = VP8X_ICC | if has_alpha { VP8X_ALPHA } else { 0 };
+        header[4..7].copy_from_slice(&(image.width() - 1).to_le_bytes()[..3]);
+        header[7..10].copy_from_slice(&(image.height() - 1).to_le_bytes()[..3]);
+        out.append(&mut chunk(b"VP8X", &header));
+        out.append(&mut iccp);
+        out.extend_from_slice(&webp[12..]);
+    }
+    let riff_size = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    out
+}
+
+// 编码为 PNG，开启时写入 iCCP 块
+pub fn encode_png(image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    let mut encoder = PngEncoder::new(&mut png);
+    if embed() {
+        encoder
+            .set_icc_profile(srgb().to_vec())
+            .map_err(|e| e.to_string())?;
+    }
+    encoder
+        .write_image(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            image::ExtendedColorType::Rgba8,
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(png)
+}
+
+// RIFF 块：四字符码 + 小端长度 + 数据（奇数长度补 0）
+fn chunk(fourcc: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(8 + data.len() + 1);
+    out.extend_from_slice(fourcc);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+    out
+}
+
+// sRGB 显示器配置文件（ICC v4，D50 PCS），首次使用时生成
+pub fn srgb() -> &'static [u8] {
+    static PROFILE: OnceLock<Vec<u8>> = OnceLock::new();
+    PROFILE.get_or_init(srgb_profile)
+}
+
+fn srgb_profile() -> Vec<u8> {
+    // 白点与 Bradford 适配到 D50 的 sRGB 原色
+    let xyz = |x: f64, y: f64, z: f64| {
+        let mut tag = b"XYZ \0\0\0\0".to_vec();
+        for v in [x, y, z] {
+            tag.extend_from_slice(&s15f16(v));
+        }
+        tag
+    };
+    // D65 -> D50 的色适应矩阵
+    let mut chad = b"sf32\0\0\0\0".to_vec();
+    for v in [
+        1.047882, 0.022918, -0.050217, 0.029586, 0.990478, -0.017075, -0.009247, 0.015075, 0.751678,
+    ] {
+        chad.extend_from_slice(&s15f16(v));
+    }
+    // sRGB 传递函数：Y = (aX + b)^g（X >= d），否则 Y = cX
+    let mut trc = b"para\0\0\0\0\0\x03\0\0".to_vec();
+    for v in [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045] {
+        trc.extend_from_slice(&s15f16(v));
+    }
+
+    let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"desc", mluc("sRGB")),
+        (b"cprt", mluc("No copyright, use freely")),
+        (b"wtpt", xyz(0.9642, 1.0, 0.8249)),
+        (b"chad", chad),
+        (b"rXYZ", xyz(0.4361, 0.2225, 0.0139)),
+        (b"gXYZ", xyz(0.3851, 0.7169, 0.0971)),
+        (b"bXYZ", xyz(0.1431, 0.0606, 0.7141)),
+        (b"rTRC", trc.clone()),
+        (b"gTRC", trc.clone()),
+        (b"bTRC", trc),
+    ];
+
+    // 标签表之后依次存放标签数据，各自按 4 字节对齐
+    let data_start = 128 + 4 + tags.len() * 12;
+    let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+    let mut data = Vec::new();
+    for (signature, tag) in &tags {
+        table.extend_from_slice(*signature);
+        table.extend_from_slice(&((data_start + data.len()) as u32).to_be_bytes());
+        table.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+        data.extend_from_slice(tag);
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+    }
+
+    let size = 128 + table.len() + data.len();
+    let mut header = vec![0u8; 128];
+    header[0..4].copy_from_slice(&(size as u32).to_be_bytes());
+    header[8..12].copy_from_slice(&[4, 0x30, 0, 0]); // 版本 4.3
+    header[12..16].copy_from_slice(b"mntr");
+    header[16..20].copy_from_slice(b"RGB ");
+    header[20..24].copy_from_slice(b"XYZ ");
+    header[24..36].copy_from_slice(&[0x07, 0xE0, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0]); // 2016-01-01
+    header[36..40].copy_from_slice(b"acsp");
+    header[68..72].copy_from_slice(&s15f16(0.9642));
+    header[72..76].copy_from_slice(&s15f16(1.0));
+    header[76..80].copy_from_slice(&s15f16(0.8249));
+
+    let mut profile = header;
+    profile.extend_from_slice(&table);
+    profile.extend_from_slice(&data);
+    profile
+}
+
+// 单条 en-US 记录的多语言文本（UTF-16BE）
+fn mluc(text: &str) -> Vec<u8> {
+    let utf16: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+    let mut tag = b"mluc\0\0\0\0".to_vec();
+    tag.extend_from_slice(&1u32.to_be_bytes());
+    tag.extend_from_slice(&12u32.to_be_bytes());
+    tag.extend_from_slice(b"enUS");
+    tag.extend_from_slice(&(utf16.len() as u32).to_be_bytes());
+    tag.extend_from_slice(&28u32.to_be_bytes());
+    tag.extend_from_slice(&utf16);
+    tag
+}
+
+fn s15f16(value: f64) -> [u8; 4] {
+    ((value * 65536.0).round() as i32).to_be_bytes()
+}
@@ -0,0 +1,90 @@
+use std::mem::{size_of, zeroed};
+use std::ptr::null_mut;
+use winapi::shared::windef::{HWND, POINT, SIZE};
+use winapi::um::wingdi::{
+    AC_SRC_ALPHA, AC_SRC_OVER, BI_RGB, BITMAPINFO, BITMAPINFOHEADER, BLENDFUNCTION,
+    CreateCompatibleDC, CreateDIBSection, DIB_RGB_COLORS, DeleteDC, DeleteObject, SelectObject,
+};
+use winapi::um::winuser::{
+    GWL_EXSTYLE, GetDC, GetWindowLongPtrW, ReleaseDC, SetWindowLongPtrW, ULW_ALPHA,
+    UpdateLayeredWindow, WS_EX_LAYERED,
+};
+
+// 分层窗口：按每像素 alpha 把缓冲区合成到桌面上（live 覆盖模式）
+
+// 把窗口设为分层窗口；之后只能通过 present 显示内容
+pub fn enable(hwnd: HWND) {
+    unsafe {
+        let style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        SetWindowLongPtrW(hwnd, GWL_EXSTYLE, style | WS_EX_LAYERED as isize);
+    }
+}
+
+// 用 ARGB 缓冲区更新分层窗口的内容和尺寸（位置不变）。alpha 为 0 的像素鼠标会穿透到下层窗口
+pub fn present(hwnd: HWND, buffer: &[u32], width: usize, height: usize) -> Result<(), String> {
+    unsafe {
+        let screen_dc = GetDC(null_mut());
+        let memory_dc = CreateCompatibleDC(screen_dc);
+
+        let mut info: BITMAPINFO = zeroed();
+        info.bmiHeader.biSize = size_of::<BITMAPINFOHEADER>() as u32;
+        info.bmiHeader.biWidth = width as i32;
+        info.bmiHeader.biHeight = -(height as i32); // 自上而下
+        info.bmiHeader.biPlanes = 1;
+        info.bmiHeader.biBitCount = 32;
+        info.bmiHeader.biCompression = BI_RGB;
+        let mut bits = null_mut();
+        let bitmap = CreateDIBSection(memory_dc, &info, DIB_RGB_COLORS, &mut bits, null_mut(), 0);
+
+        let result = if bitmap.is_null() {
+            Err("CreateDIBSection failed".to_string())
+        } else {
+            // UpdateLayeredWindow 要求预乘 alpha
+            let pixels = std::slice::from_raw_parts_mut(bits as *mut u32, width * height);
+            for (dst, &src) in pixels.iter_mut().zip(buffer) {
+                *dst = premultiply(src);
+            }
+
+            let previous = SelectObject(memory_dc, bitmap as _);
+            let mut size = SIZE {
+                cx: width as i32,
+                cy: height as i32,
+            };
+            let mut source = POINT { x: 0, y: 0 };
+            let mut blend = BLENDFUNCTION {
+                BlendOp: AC_SRC_OVER,
+                BlendFlags: 0,
+                SourceConstantAlpha: 255,
+                AlphaFormat: AC_SRC_ALPHA,
+            };
+            let updated = UpdateLayeredWindow(
+                hwnd,
+                screen_dc,
+                null_mut(),
+                &mut size,
+                memory_dc,
+                &mut source,
+                0,
+                &mut blend,
+                ULW_ALPHA,
+            );
+            SelectObject(memory_dc, previous);
+            DeleteObject(bitmap as _);
+            if updated != 0 {
+                Ok(())
+            } else {
+                Err("UpdateLayeredWindow failed".to_string())
+            }
+        };
+
+        DeleteDC(memory_dc);
+        ReleaseDC(null_mut(), screen_dc);
+        result
+    }
+}
+
+fn premultiply(color: u32) -> u32 {
+    let alpha = color >> 24;
+    let scale = |shift: u32| (((color >> shift) & 0xFF) * alpha / 255) << shift;
+    (alpha << 24) | scale(16) | scale(8) | scale(0)
+}
@@ -1,19 +1,70 @@
+mod batch;
+mod cart;
+mod clipboard;
+mod config;
+mod contact_sheet;
+mod desktop;
+mod editor;
+mod font;
+mod gesture;
+mod hdr;
+mod history;
+mod hotkey;
+mod icc;
+mod layered;
+mod metrics;
+mod notify;
+mod ocr;
+mod pointer;
+mod precapture;
+mod process_windows;
+mod save_hook;
+mod saver;
+mod server;
+mod setup;
+mod stamp;
+mod svg;
+mod timer;
+mod transform;
+mod upload;
+mod viewer;
+mod watermark;
+mod window_info;
+
+use cart::CartItem;
+use config::{
+    AppendDirection, BorderStyle, ChannelOrder, Config, CoordinateSpace, CountdownStyle,
+    DataUriFormat, Interpolation, OverlayMode, QuickTarget, SaveFormat, SelectionShape,
+    SmallDragAction,
+};
+use history::HistoryEntry;
 use image::{ImageBuffer, Rgba};
+use metrics::Metric;
 use minifb::{Key, MouseButton, MouseMode, Scale, ScaleMode, Window, WindowOptions};
+use precapture::PreCapture;
+use saver::{Cancelled, SaveJob, SaveWorker, Saved};
 use screenshots::Screen;
+use stamp::Stamp;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::mem::zeroed;
 use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::{
     collections::VecDeque,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
-use webp::{Encoder, WebPMemory};
+use transform::Adjustment;
+use webp::Encoder;
+use winapi::um::winbase::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
 use winapi::um::winuser::{
-    DispatchMessageW, GetMessageW, MOD_ALT, MOD_CONTROL, MSG, PostQuitMessage, RegisterHotKey,
-    TranslateMessage, UnregisterHotKey, WM_HOTKEY, WM_QUIT,
+    DispatchMessageW, GetAsyncKeyState, GetMessageW, MOD_ALT, MOD_CONTROL, MOD_SHIFT, MOD_WIN, MSG,
+    SWP_NOACTIVATE, SWP_NOZORDER, SetWindowPos, TranslateMessage, VK_CONTROL, VK_LWIN, VK_MENU,
+    VK_RWIN, VK_SHIFT, WM_DISPLAYCHANGE, WM_HOTKEY, WM_QUIT,
 };
+use window_info::{TopLevelWindow, UiElement};
 
 // 自定义事件枚举
 #[derive(Debug, Clone)]
@@ -25,6 +76,21 @@ enum AppEvent {
     MouseMoved(f32, f32),
     WindowResized(usize, usize),
     GlobalHotkeyPressed,
+    SaveHotkeyPressed,
+    SaveCompleted(Option<Saved>), // 后台保存完成（保存的文件，失败为 None）
+    CancelSaveHotkeyPressed,
+    PresetHotkeyPressed(usize),  // 命名区域热键（config.presets 中的序号）
+    QuickHotkeyPressed,          // 快速截取热键
+    CopyPathHotkeyPressed,       // 复制最近保存文件路径的热键
+    MonitorHotkeyPressed(usize), // 按屏幕截图热键（Screen::all() 中的序号）
+    DisplayChanged,              // 显示器增减或分辨率变化（WM_DISPLAYCHANGE）
+    ViewerHotkeyPressed,         // 打开截图查看器的热键
+    SaveCancelled,               // 后台保存被取消，未写入文件
+    TextDetected(
+        (i32, i32, i32, i32),
+        Result<Option<(i32, i32, i32, i32)>, String>,
+    ), // 后台文字识别完成（红框，最大文字块）
+    Tick,                        // 每帧一次，用于计时
     Quit,
 }
 
@@ -36,16 +102,52 @@ struct DisplayCache {
     display_buffer: Vec<u32>,    // 实际显示的缓冲区
     width: u32,
     height: u32,
+    histogram: Option<Histogram>, // 选区直方图缓存
+}
+
+// 选区直方图：R、G、B、亮度各 HISTOGRAM_BINS 档
+#[derive(Clone)]
+struct Histogram {
+    region: (i32, i32, i32, i32),
+    computed_at: Instant,
+    bins: [[u32; HISTOGRAM_BINS]; 4],
 }
 
 impl DisplayCache {
-    fn new(image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Self {
+    // live 覆盖模式：没有冻结的截图，选区内几乎全透明（alpha 1，仍可接收鼠标），选区外为半透明遮罩
+    fn live(width: u32, height: u32) -> Self {
+        let size = (width * height) as usize;
+        Self {
+            original_buffer: vec![LIVE_CLEAR; size],
+            dimmed_buffer: vec![LIVE_DIM; size],
+            display_buffer: vec![LIVE_CLEAR; size],
+            width,
+            height,
+            histogram: None,
+        }
+    }
+
+    fn new(
+        image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+        dim_tint: Option<u32>,
+        dim_tint_strength: u32,
+    ) -> Self {
+        let started = Instant::now();
         let (width, height) = image.dimensions();
         let size = (width * height) as usize;
 
         let mut original_buffer = vec![0u32; size];
         let mut dimmed_buffer = vec![0u32; size];
 
+        // dim_tint：选区外向该颜色混合 dim_tint_strength%，代替灰度化
+        let tint = dim_tint.map(|color| {
+            let strength = dim_tint_strength.min(100);
+            (
+                strength,
+                [(color >> 16) & 0xFF, (color >> 8) & 0xFF, color & 0xFF],
+            )
+        });
+
         // 预计算原始图像和灰度图像
         for (i, pixel) in image.pixels().enumerate() {
             let r = pixel[0] as u32;
@@ -56,6 +158,13 @@ impl DisplayCache {
             // 原始颜色
             original_buffer[i] = (a << 24) | (r << 16) | (g << 8) | b;
 
+            if let Some((strength, [tint_r, tint_g, tint_b])) = tint {
+                let mix = |c: u32, t: u32| (c * (100 - strength) + t * strength) / 100;
+                dimmed_buffer[i] =
+                    (a << 24) | (mix(r, tint_r) << 16) | (mix(g, tint_g) << 8) | mix(b, tint_b);
+                continue;
+            }
+
             // 灰度化：保留原始颜色但降低亮度和饱和度
             let gray = ((r * 3 + g * 6 + b * 1) / 10) as u32;
             let dimmed_r = (r * 3 + gray * 7) / 10;
@@ -65,6 +174,7 @@ impl DisplayCache {
         }
 
         let display_buffer = original_buffer.clone();
+        metrics::record(Metric::CacheBuild, started.elapsed());
 
         Self {
             original_buffer,
@@ -72,15 +182,24 @@ impl DisplayCache {
             display_buffer,
             width,
             height,
+            histogram: None,
         }
     }
 
-    fn update_display(&mut self, red_region: Option<(i32, i32, i32, i32)>, green_region: Option<(i32, i32, i32, i32)>) {
+    fn update_display(
+        &mut self,
+        red_region: Option<(i32, i32, i32, i32)>,
+        green_region: Option<(i32, i32, i32, i32)>,
+        style: BorderStyle,
+        frame: u64,
+        adjust: Adjustment,
+    ) {
         if let Some((rx, ry, rw, rh)) = red_region {
             // 先复制灰度背景
             self.display_buffer.copy_from_slice(&self.dimmed_buffer);
 
-            // 恢复红框内的原始图像
+            // 恢复红框内的原始图像（有亮度/对比度调整时按调整后预览）
+            let lut = (!adjust.is_identity()).then(|| adjust.lut());
             for y in ry.max(0)..(ry + rh).min(self.height as i32) {
                 let y_offset = y as usize * self.width as usize;
                 let start_x = rx.max(0) as usize;
@@ -88,16 +207,19 @@ impl DisplayCache {
 
                 for x in start_x..end_x {
                     let idx = y_offset + x;
-                    self.display_buffer[idx] = self.original_buffer[idx];
+                    self.display_buffer[idx] = match &lut {
+                        Some(lut) => transform::adjust_argb(self.original_buffer[idx], lut),
+                        None => self.original_buffer[idx],
+                    };
                 }
             }
 
             // 绘制红框
-            self.draw_rectangle((rx, ry, rw, rh), 0xFFFF0000);
+            self.draw_border((rx, ry, rw, rh), 0xFFFF0000, style, frame);
 
             // 绘制绿框（如果有）
             if let Some(green) = green_region {
-                self.draw_rectangle(green, 0xFF00FF00);
+                self.draw_border(green, 0xFF00FF00, style, frame);
             }
         } else {
             // 没有选择区域时显示原始图像
@@ -105,6 +227,452 @@ impl DisplayCache {
         }
     }
 
+    // 在右上角绘制选区的直方图；选区变化时最多每 100ms 重新统计一次
+    fn draw_histogram(&mut self, region: (i32, i32, i32, i32)) {
+        let stale = match &self.histogram {
+            Some(h) => h.region != region && h.computed_at.elapsed() >= Duration::from_millis(100),
+            None => true,
+        };
+        if stale {
+            self.histogram = Some(self.compute_histogram(region));
+        }
+        let Some(histogram) = &self.histogram else {
+            return;
+        };
+
+        let bar_width = 4u32;
+        let row_height = 48u32;
+        let panel_width = HISTOGRAM_BINS as u32 * bar_width + 16;
+        let panel_height = row_height * 4 + 16;
+        if panel_width + 16 > self.width || panel_height + 16 > self.height {
+            return;
+        }
+        let left = self.width - panel_width - 16;
+        let top = 16;
+
+        let mut panel = vec![0xFF202020u32; (panel_width * panel_height) as usize];
+        let colors = [0xFFFF4040, 0xFF40FF40, 0xFF4080FF, 0xFFE0E0E0];
+        for (channel, bins) in histogram.bins.iter().enumerate() {
+            let max = (*bins.iter().max().unwrap_or(&0)).max(1);
+            let baseline = 8 + row_height * (channel as u32 + 1) - 4;
+            for (i, &count) in bins.iter().enumerate() {
+                let bar_height = (count as u64 * (row_height - 8) as u64 / max as u64) as u32;
+                for y in baseline - bar_height..baseline {
+                    for x in 0..bar_width - 1 {
+                        let px = 8 + i as u32 * bar_width + x;
+                        panel[(y * panel_width + px) as usize] = colors[channel];
+                    }
+                }
+            }
+        }
+
+        for y in 0..panel_height {
+            let dst = ((top + y) * self.width + left) as usize;
+            let src = (y * panel_width) as usize;
+            self.display_buffer[dst..dst + panel_width as usize]
+                .copy_from_slice(&panel[src..src + panel_width as usize]);
+        }
+    }
+
+    fn compute_histogram(&self, region: (i32, i32, i32, i32)) -> Histogram {
+        let (x, y, w, h) = region;
+        let mut bins = [[0u32; HISTOGRAM_BINS]; 4];
+        let shift = 8 - HISTOGRAM_BINS.trailing_zeros();
+
+        for py in y.max(0)..(y + h).min(self.height as i32) {
+            let row = py as usize * self.width as usize;
+            for px in x.max(0)..(x + w).min(self.width as i32) {
+                let color = self.original_buffer[row + px as usize];
+                let r = (color >> 16) & 0xFF;
+                let g = (color >> 8) & 0xFF;
+                let b = color & 0xFF;
+                let luma = (r * 3 + g * 6 + b) / 10;
+                bins[0][(r >> shift) as usize] += 1;
+                bins[1][(g >> shift) as usize] += 1;
+                bins[2][(b >> shift) as usize] += 1;
+                bins[3][(luma >> shift) as usize] += 1;
+            }
+        }
+
+        Histogram {
+            region,
+            computed_at: Instant::now(),
+            bins,
+        }
+    }
+
+    // 在左下角绘制待导出列表的缩略图条
+    fn draw_thumbnails(&mut self, items: &[CartItem]) {
+        let margin = 8;
+        let mut left = margin;
+
+        for item in items {
+            let (tw, th) = (item.thumbnail_width, item.thumbnail_height);
+            if left + tw + margin > self.width || th + margin * 2 > self.height {
+                break;
+            }
+            let top = self.height - th - margin;
+
+            for y in 0..th {
+                let dst = ((top + y) * self.width + left) as usize;
+                let src = (y * tw) as usize;
+                self.display_buffer[dst..dst + tw as usize]
+                    .copy_from_slice(&item.thumbnail[src..src + tw as usize]);
+            }
+            self.draw_rectangle(
+                (
+                    left as i32 - 1,
+                    top as i32 - 1,
+                    tw as i32 + 2,
+                    th as i32 + 2,
+                ),
+                0xFFFFFFFF,
+            );
+
+            left += tw + margin;
+        }
+    }
+
+    // 最终输出预览：棋盘格背景（显示透明区域）上居中绘制预览图像，外加 1px 白框
+    fn draw_output_preview(&mut self, preview: &ImageBuffer<Rgba<u8>, Vec<u8>>) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        for y in 0..height {
+            for x in 0..width {
+                let light = ((x / 16) + (y / 16)) % 2 == 0;
+                self.display_buffer[(y * width + x) as usize] =
+                    if light { 0xFF404040 } else { 0xFF303030 };
+            }
+        }
+
+        let left = (width - preview.width() as i32) / 2;
+        let top = (height - preview.height() as i32) / 2;
+        for (px, py, pixel) in preview.enumerate_pixels() {
+            let (x, y) = (left + px as i32, top + py as i32);
+            if x < 0 || x >= width || y < 0 || y >= height {
+                continue;
+            }
+            let [r, g, b, a] = pixel.0;
+            let idx = (y * width + x) as usize;
+            let color = (a as u32) << 24 | (r as u32) << 16 | (g as u32) << 8 | b as u32;
+            self.display_buffer[idx] = blend(self.display_buffer[idx], color);
+        }
+        self.draw_rectangle(
+            (
+                left - 1,
+                top - 1,
+                preview.width() as i32 + 2,
+                preview.height() as i32 + 2,
+            ),
+            0xFFFFFFFF,
+        );
+    }
+
+    // 在左上角绘制各阶段最近一次的耗时（debug_timings）
+    fn draw_timings(&mut self) {
+        self.draw_text_box(&metrics::lines(), 0xFF40FF40);
+    }
+
+    // 在左上角的半透明黑底上逐行绘制文字
+    fn draw_text_box(&mut self, lines: &[String], color: u32) {
+        let line_height = (font::GLYPH_HEIGHT + 4) as i32;
+        let text_width = lines
+            .iter()
+            .map(|line| font::text_width(line, 1))
+            .max()
+            .unwrap_or(0);
+        let (box_width, box_height) =
+            (text_width as i32 + 12, lines.len() as i32 * line_height + 8);
+        for y in 0..box_height.min(self.height as i32) {
+            for x in 0..box_width.min(self.width as i32) {
+                let idx = (y * self.width as i32 + x) as usize;
+                self.display_buffer[idx] = blend(self.display_buffer[idx], 0xC0000000);
+            }
+        }
+        for (i, line) in lines.iter().enumerate() {
+            font::draw_text(
+                &mut self.display_buffer,
+                self.width,
+                self.height,
+                line,
+                font::TextStyle {
+                    x: 6,
+                    y: 6 + i as i32 * line_height,
+                    color,
+                    scale: 1,
+                },
+            );
+        }
+    }
+
+    // 在光标旁绘制放大镜，放大倍数达到阈值时可在像素之间绘制网格线
+    fn draw_loupe(&mut self, cursor: (i32, i32), zoom: u32, grid_color: Option<u32>) {
+        let zoom = zoom as i32;
+        let size = LOUPE_SOURCE_SIZE * zoom;
+        let (width, height) = (self.width as i32, self.height as i32);
+        if size + 40 > width || size + 40 > height {
+            return;
+        }
+
+        // 放在光标右下方，靠近屏幕边缘时翻到另一侧
+        let mut left = cursor.0 + 20;
+        let mut top = cursor.1 + 20;
+        if left + size > width {
+            left = cursor.0 - 20 - size;
+        }
+        if top + size > height {
+            top = cursor.1 - 20 - size;
+        }
+
+        let half = LOUPE_SOURCE_SIZE / 2;
+        let draw_grid = grid_color.filter(|_| zoom >= LOUPE_GRID_MIN_ZOOM);
+
+        for dy in 0..size {
+            let sy = (cursor.1 - half + dy / zoom).clamp(0, height - 1);
+            for dx in 0..size {
+                let sx = (cursor.0 - half + dx / zoom).clamp(0, width - 1);
+                let mut color = self.original_buffer[(sy * width + sx) as usize];
+
+                if let Some(grid) = draw_grid
+                    && (dx % zoom == 0 || dy % zoom == 0)
+                {
+                    color = blend(color, grid);
+                }
+
+                self.display_buffer[((top + dy) * width + left + dx) as usize] = color;
+            }
+        }
+
+        // 中心像素和外框
+        self.draw_rectangle(
+            (left + half * zoom, top + half * zoom, zoom, zoom),
+            0xFFFF0000,
+        );
+        self.draw_rectangle((left - 1, top - 1, size + 2, size + 2), 0xFFFFFFFF);
+    }
+
+    // 按样式绘制选框边框
+    fn draw_border(
+        &mut self,
+        rect: (i32, i32, i32, i32),
+        color: u32,
+        style: BorderStyle,
+        frame: u64,
+    ) {
+        match style {
+            BorderStyle::Crisp => self.draw_rectangle(rect, color),
+            BorderStyle::Smooth => self.draw_rectangle_smooth(rect, color),
+            BorderStyle::Ants => self.draw_rectangle_dashed(rect, color, (frame / 2) as usize),
+        }
+    }
+
+    // 虚线边框（蚂蚁线）：沿边框顺时针交替绘制彩色段和黑色段，phase 每帧递增使虚线流动
+    fn draw_rectangle_dashed(&mut self, rect: (i32, i32, i32, i32), color: u32, phase: usize) {
+        let (x, y, w, h) = rect;
+        if w <= 0 || h <= 0 {
+            return;
+        }
+        let (right, bottom) = (x + w - 1, y + h - 1);
+
+        // 顺时针依次经过上、右、下、左边
+        let top_edge = (x..=right).map(|px| (px, y));
+        let right_edge = (y + 1..=bottom).map(|py| (right, py));
+        let bottom_edge = (x..right).rev().map(|px| (px, bottom));
+        let left_edge = (y + 1..bottom).rev().map(|py| (x, py));
+        let perimeter = top_edge
+            .chain(right_edge)
+            .chain(bottom_edge)
+            .chain(left_edge);
+
+        let (width, height) = (self.width as i32, self.height as i32);
+        for (i, (px, py)) in perimeter.enumerate() {
+            if px < 0 || px >= width || py < 0 || py >= height {
+                continue;
+            }
+            let segment =
+                (i + ANTS_DASH_LENGTH * 2 - phase % (ANTS_DASH_LENGTH * 2)) / ANTS_DASH_LENGTH;
+            self.display_buffer[(py * width + px) as usize] = if segment.is_multiple_of(2) {
+                color
+            } else {
+                0xFF000000
+            };
+        }
+    }
+
+    // 抗锯齿边框：1px 实线两侧各加一圈半透明过渡，缩放显示时不易出现锯齿
+    fn draw_rectangle_smooth(&mut self, rect: (i32, i32, i32, i32), color: u32) {
+        let (x, y, w, h) = rect;
+        let color = color & 0x00FFFFFF;
+        self.blend_rectangle((x - 1, y - 1, w + 2, h + 2), color | 0x60000000);
+        self.blend_rectangle((x + 1, y + 1, w - 2, h - 2), color | 0x60000000);
+        self.draw_rectangle(rect, color | 0xFF000000);
+    }
+
+    // 与底图混合的 1px 矩形边框
+    fn blend_rectangle(&mut self, rect: (i32, i32, i32, i32), color: u32) {
+        let (x, y, w, h) = rect;
+        if w <= 0 || h <= 0 {
+            return;
+        }
+        let (width, height) = (self.width as i32, self.height as i32);
+        let mut blend_at = |px: i32, py: i32| {
+            if px >= 0 && px < width && py >= 0 && py < height {
+                let idx = (py * width + px) as usize;
+                self.display_buffer[idx] = blend(self.display_buffer[idx], color);
+            }
+        };
+
+        for i in x..x + w {
+            blend_at(i, y);
+            if h > 1 {
+                blend_at(i, y + h - 1);
+            }
+        }
+        for j in y + 1..y + h - 1 {
+            blend_at(x, j);
+            if w > 1 {
+                blend_at(x + w - 1, j);
+            }
+        }
+    }
+
+    // 在红框四角和四边中点绘制边长为 size 的调整手柄
+    fn draw_handles(&mut self, region: (i32, i32, i32, i32), size: i32) {
+        let (x, y, w, h) = region;
+        for hx in [x, x + w / 2, x + w] {
+            for hy in [y, y + h / 2, y + h] {
+                if hx == x + w / 2 && hy == y + h / 2 {
+                    continue;
+                }
+                let handle = (hx - size / 2, hy - size / 2, size, size);
+                self.fill_rectangle(handle, 0xFFFFFFFF);
+                self.draw_rectangle(handle, 0xFFFF0000);
+            }
+        }
+    }
+
+    // 遮盖区域：编辑时压暗并画黄色边框，仍能看清被遮盖的内容；保存和输出预览中为纯黑
+    fn draw_redaction(&mut self, rect: (i32, i32, i32, i32)) {
+        let (x, y, w, h) = rect;
+        let (width, height) = (self.width as i32, self.height as i32);
+        for py in y.max(0)..(y + h).min(height) {
+            for px in x.max(0)..(x + w).min(width) {
+                let idx = (py * width + px) as usize;
+                self.display_buffer[idx] = blend(self.display_buffer[idx], 0xB0000000);
+            }
+        }
+        self.draw_rectangle(rect, 0xFFFFC000);
+    }
+
+    // 贯穿整个画面的参考线
+    fn draw_guides(&mut self, guides_x: &[i32], guides_y: &[i32], color: u32) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        for &x in guides_x.iter().filter(|&&x| x >= 0 && x < width) {
+            for y in 0..height {
+                self.display_buffer[(y * width + x) as usize] = color;
+            }
+        }
+        for &y in guides_y.iter().filter(|&&y| y >= 0 && y < height) {
+            let row = (y * width) as usize;
+            self.display_buffer[row..row + width as usize].fill(color);
+        }
+    }
+
+    // 键盘框选的虚拟光标：带黑边的白色十字，中心留空以便看清所指像素
+    fn draw_crosshair(&mut self, point: (i32, i32)) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let (cx, cy) = point;
+        for (offset, color) in [(1, 0xFF000000u32), (0, 0xFFFFFFFF)] {
+            for d in 3..=CROSSHAIR_ARM {
+                for (px, py) in [
+                    (cx - d, cy + offset),
+                    (cx + d, cy + offset),
+                    (cx + offset, cy - d),
+                    (cx + offset, cy + d),
+                ] {
+                    if px >= 0 && px < width && py >= 0 && py < height {
+                        self.display_buffer[(py * width + px) as usize] = color;
+                    }
+                }
+            }
+        }
+    }
+
+    // 内切于矩形的椭圆轮廓：逐列、逐行各取一次交点，避免陡峭处断线
+    fn draw_ellipse(&mut self, rect: (i32, i32, i32, i32), color: u32) {
+        let (x, y, w, h) = rect;
+        if w <= 0 || h <= 0 {
+            return;
+        }
+        let (rx, ry) = (w as f32 / 2.0, h as f32 / 2.0);
+        let (cx, cy) = (x as f32 + rx, y as f32 + ry);
+        let (width, height) = (self.width as i32, self.height as i32);
+        let mut plot = |px: i32, py: i32| {
+            if px >= 0 && px < width && py >= 0 && py < height {
+                self.display_buffer[(py * width + px) as usize] = color;
+            }
+        };
+
+        for px in x..x + w {
+            let t = (px as f32 + 0.5 - cx) / rx;
+            let dy = ry * (1.0 - t * t).max(0.0).sqrt();
+            plot(px, (cy - dy) as i32);
+            plot(px, ((cy + dy) as i32).min(y + h - 1));
+        }
+        for py in y..y + h {
+            let t = (py as f32 + 0.5 - cy) / ry;
+            let dx = rx * (1.0 - t * t).max(0.0).sqrt();
+            plot((cx - dx) as i32, py);
+            plot(((cx + dx) as i32).min(x + w - 1), py);
+        }
+    }
+
+    // 圆角矩形轮廓，半径限制为短边的一半
+    fn draw_rounded_rectangle(&mut self, rect: (i32, i32, i32, i32), radius: i32, color: u32) {
+        let (x, y, w, h) = rect;
+        let r = radius.min(w / 2).min(h / 2);
+        if r <= 0 {
+            return;
+        }
+        let (width, height) = (self.width as i32, self.height as i32);
+        let mut plot = |px: i32, py: i32| {
+            if px >= 0 && px < width && py >= 0 && py < height {
+                self.display_buffer[(py * width + px) as usize] = color;
+            }
+        };
+
+        for px in x + r..x + w - r {
+            plot(px, y);
+            plot(px, y + h - 1);
+        }
+        for py in y + r..y + h - r {
+            plot(x, py);
+            plot(x + w - 1, py);
+        }
+
+        // 四分之一圆弧：按角度采样，步数随半径增加
+        let steps = r * 4;
+        for i in 0..=steps {
+            let angle = i as f32 / steps as f32 * std::f32::consts::FRAC_PI_2;
+            let dx = (r as f32 - 0.5) * (1.0 - angle.cos());
+            let dy = (r as f32 - 0.5) * (1.0 - angle.sin());
+            let (dx, dy) = (dx.round() as i32, dy.round() as i32);
+            plot(x + dx, y + dy);
+            plot(x + w - 1 - dx, y + dy);
+            plot(x + dx, y + h - 1 - dy);
+            plot(x + w - 1 - dx, y + h - 1 - dy);
+        }
+    }
+
+    fn fill_rectangle(&mut self, rect: (i32, i32, i32, i32), color: u32) {
+        let (x, y, w, h) = rect;
+        for py in y.max(0)..(y + h).min(self.height as i32) {
+            let row = py as usize * self.width as usize;
+            for px in x.max(0)..(x + w).min(self.width as i32) {
+                self.display_buffer[row + px as usize] = color;
+            }
+        }
+    }
+
     fn draw_rectangle(&mut self, rect: (i32, i32, i32, i32), color: u32) {
         let (x, y, w, h) = rect;
         let width = self.width as i32;
@@ -132,50 +700,443 @@ impl DisplayCache {
     }
 }
 
+// 按颜色的 alpha 通道把 color 混合到 dst 上
+fn blend(dst: u32, color: u32) -> u32 {
+    let alpha = color >> 24;
+    let mix = |shift: u32| {
+        let d = (dst >> shift) & 0xFF;
+        let c = (color >> shift) & 0xFF;
+        (c * alpha + d * (255 - alpha)) / 255
+    };
+    (dst & 0xFF000000) | (mix(16) << 16) | (mix(8) << 8) | mix(0)
+}
+
 // 程序状态
 enum State {
     Idle,
-    FullscreenCapture(ImageBuffer<Rgba<u8>, Vec<u8>>, DisplayCache),
-    SelectingRegion(ImageBuffer<Rgba<u8>, Vec<u8>>, DisplayCache, (i32, i32), (i32, i32)),
-    RegionSelected(ImageBuffer<Rgba<u8>, Vec<u8>>, DisplayCache, (i32, i32, i32, i32)),
+    Countdown(Instant, Duration), // 延时截图倒计时：开始时间、总时长
+    Gallery(Vec<HistoryEntry>),   // 最近保存的截图
+    CaptureFailed(String),        // 截图失败：显示错误信息，任意键重试，ESC 取消
+    Viewer(viewer::Viewer),       // 浏览已保存的截图
+    // 按 M 把红框存为命名区域：输入名称并按下热键，Enter 写入配置文件
+    PresetName(
+        Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+        DisplayCache,
+        (i32, i32, i32, i32),
+        PresetPrompt,
+    ),
+    // 冻结前的实时预览（live_preview）：最近一次截图及其截取时间
+    LivePreview(Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>, DisplayCache, Instant),
+    FullscreenCapture(Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>, DisplayCache),
+    SelectingRegion(
+        Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+        DisplayCache,
+        (i32, i32),
+        (i32, i32),
+    ),
+    RegionSelected(
+        Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+        DisplayCache,
+        (i32, i32, i32, i32),
+    ),
+    // 待确认的红框（deferred_confirm）：可拖动边/角调整，Enter 确认
+    EditingRegion(
+        Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+        DisplayCache,
+        (i32, i32, i32, i32),
+        Option<RegionDrag>,
+    ),
     SelectingSubRegion(
-        ImageBuffer<Rgba<u8>, Vec<u8>>,
+        Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
         DisplayCache,
         (i32, i32, i32, i32),
         (i32, i32),
         (i32, i32),
     ),
+    // OCR 建议的文字框（蓝框），Enter 采用为绿框
+    TextBoxProposed(
+        Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+        DisplayCache,
+        (i32, i32, i32, i32),
+        (i32, i32, i32, i32),
+    ),
     SubRegionSelected(
-        ImageBuffer<Rgba<u8>, Vec<u8>>,
+        Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
         DisplayCache,
         (i32, i32, i32, i32),
         (i32, i32, i32, i32),
     ),
+    // 按 Tab 逐个选择顶层窗口：窗口列表（Z 序，矩形为截图坐标）和当前高亮的序号
+    WindowPick(
+        Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+        DisplayCache,
+        Vec<TopLevelWindow>,
+        usize,
+    ),
+    // 按 B 点选两个界面元素（窗口或控件），选区为两者的外接框：元素列表（Z 序，矩形为截图坐标）和已点选的第一个元素
+    ElementPick(
+        Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+        DisplayCache,
+        Vec<UiElement>,
+        Option<(i32, i32, i32, i32)>,
+    ),
+    // 按 K 用键盘框选：虚拟光标和已确定的第一个角
+    KeyboardSelect(
+        Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+        DisplayCache,
+        (i32, i32),
+        Option<(i32, i32)>,
+    ),
+    // 按 F 预览最终输出（按保存流程处理后的图像，过大时缩小显示）：红框、绿框和预览图像
+    OutputPreview(
+        Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+        DisplayCache,
+        (i32, i32, i32, i32),
+        Option<(i32, i32, i32, i32)>,
+        Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+    ),
+}
+
+// 会话数据：在状态切换之间保留
+#[derive(Default)]
+struct Session {
+    cart: Vec<CartItem>,                              // 待批量导出的选区
+    save_worker: Option<SaveWorker>,                  // 后台保存线程（async_save 启用时）
+    events: Option<Sender<AppEvent>>,                 // 后台任务（文字识别）完成时向主循环发送事件
+    cursor: (i32, i32),                               // 当前鼠标位置
+    loupe_grid: bool,                                 // 放大镜网格线开关（G 键切换）
+    gallery_shown: bool,                              // 本次运行是否已显示过历史画廊
+    show_histogram: bool,                             // 显示选区直方图（H 键切换）
+    foreground_rect: Option<(i32, i32, i32, i32)>, // 截图时前台窗口的矩形（relative_to_window 时记录）
+    preselect: Option<(i32, i32, i32, i32)>, // 按下热键时前台窗口的可见边框（preselect_foreground_window 时记录，截图后用作红框）
+    capture_excluded: bool,                  // 覆盖窗口已排除在截图之外，截图前无需移开
+    overlay_origin: (isize, isize),          // 覆盖窗口显示时的位置（虚拟桌面左上角）
+    frame_source: Option<(i32, i32, u32, u32)>, // 冻结帧截取的桌面范围（frame_source 查询，无法确定时为空）
+    home_overlay: (isize, isize, usize, usize), // 覆盖窗口的默认位置和大小（主屏，capture_all_screens 时为整个虚拟桌面）
+    screen: Option<Screen>, // 本次截图的显示器（按屏幕截图热键指定），为空时按默认方式截取
+    precapture: Option<PreCapture>, // 后台预截图（precapture_ms > 0 时）
+    rewind: Vec<precapture::Frame>, // 本次截图可选的帧（从旧到新，最后一帧为按下热键时）
+    rewind_index: usize,    // 当前显示的帧
+    captured_at: Option<Instant>, // 冻结帧的截取时刻（live 覆盖模式为空）
+    shape: SelectionShape,  // 保存的选区形状（O 键切换）
+    selection_anchor: (i32, i32), // 框选时按下鼠标的位置
+    guides_x: Vec<i32>,     // 竖直参考线的 x 坐标（V 键在光标处添加）
+    guides_y: Vec<i32>,     // 水平参考线的 y 坐标（Shift+V 添加）
+    adjust: Adjustment,     // 亮度/对比度调整（adjust_keys 时 +/- 和 [ ] 键），ESC 重置
+    region_locked: bool,    // 红框已锁定（L 键切换）：框内点击不再开始选择绿框
+    save_red: bool,         // 有绿框时改为保存红框（R 键切换）
+    appended: Option<ImageBuffer<Rgba<u8>, Vec<u8>>>, // J 键拼接的图像，Shift+J 保存
+    appended_count: usize,  // 已拼接的选区数
+    arrow_pressed_at: Option<Instant>, // 键盘框选时方向键按下的时间，按住超过 KEY_REPEAT_DELAY 后加速移动
+    print_region: bool,                // --print-region：保存成功后向标准输出打印选区坐标
+    viewport: (i32, i32),              // pan_and_scan 时窗口左上角对应的截图坐标（未平移时为 0, 0）
+    fit_to_window: bool,               // 截图大于窗口时缩小显示（Z 键切换）
+    fit_scale: Option<f32>,            // 当前帧实际的缩小比例（截图不大于窗口或 1:1 显示时为 None）
+    last_input: Option<Instant>, // 最近一次键盘/鼠标输入的时间（idle_timeout_secs 据此自动取消）
+    quick: bool,                 // 本次截图由快速截取热键触发：松开鼠标即复制/保存并隐藏
+    stamp_mode: bool,            // 编号标记放置模式（N 键切换）：单击放置标记而不是框选
+    stamps: Vec<Stamp>,          // 本次截图已放置的编号标记，保存时画进图像
+    stamp_count: u32,            // 最近放置的序号（stamp_reset_per_capture 时每次截图归零）
+    redactions: Vec<(i32, i32, i32, i32)>, // 遮盖区域（绿框按 D 添加），保存时涂黑
+    expanded_from: Option<Expansion>, // W 键展开前后的红框，红框未再改动时再按 W 恢复
+}
+
+// W 键展开到整个屏幕前的红框和展开后的红框
+type Expansion = ((i32, i32, i32, i32), (i32, i32, i32, i32));
+
+// 存为命名区域时输入的名称和热键；overwrite 为同名区域已存在、再按 Enter 即覆盖
+#[derive(Clone, Default)]
+struct PresetPrompt {
+    name: String,
+    hotkey: Option<config::Hotkey>,
+    overwrite: bool,
 }
 
+// 命名区域名称的最大长度
+const PRESET_NAME_MAX_CHARS: usize = 32;
+
+// 调整红框时正在拖动的边（EDGE_* 位组合，四边全选为整体移动）
+#[derive(Debug, Clone, Copy)]
+struct RegionDrag {
+    edges: u8,
+    origin: (i32, i32),
+    region: (i32, i32, i32, i32),
+}
+
+const EDGE_LEFT: u8 = 1;
+const EDGE_TOP: u8 = 2;
+const EDGE_RIGHT: u8 = 4;
+const EDGE_BOTTOM: u8 = 8;
+
+// 放大镜采样的源像素边长，以及显示网格线的最小放大倍数
+const LOUPE_SOURCE_SIZE: i32 = 15;
+const LOUPE_GRID_MIN_ZOOM: i32 = 8;
+
+// 蚂蚁线每段虚线的长度（像素）
+const ANTS_DASH_LENGTH: usize = 6;
+
+// 磁吸：视为边缘的最小亮度差（0-255），以及连成同一特征的边缘像素最大间距
+const MAGNET_THRESHOLD: i32 = 48;
+const MAGNET_GAP: usize = 2;
+
+// 直方图档数（2 的幂）
+const HISTOGRAM_BINS: usize = 64;
+
+// 亮度/对比度每次按键的调整量及上下限
+const ADJUST_STEP: i32 = 10;
+const ADJUST_LIMIT: i32 = 100;
+
+// 倒计时显示区域边长
+const COUNTDOWN_SIZE: u32 = 160;
+
+// 点选界面元素的标题栏提示
+const ELEMENT_PICK_TITLE: &str = "Pick elements - Click the first window or control, ESC to cancel";
+
+// stay_after_save 保存后的标题栏提示：红框可用方向键微调，ESC 回到全屏重选
+const STAY_AFTER_SAVE_TITLE: &str = "Saved - Arrows to nudge, save again, ESC to re-select";
+// 保存了绿框时不能微调，ESC 丢弃绿框回到红框
+const STAY_AFTER_SAVE_SUB_TITLE: &str = "Saved - Save again, ESC to drop the sub-region";
+
+// 文件名中标题的最大字符数
+const TITLE_MAX_CHARS: usize = 60;
+
+// live 覆盖模式下选区内外的颜色（ARGB）
+const LIVE_CLEAR: u32 = 0x01000000;
+const LIVE_DIM: u32 = 0x80000000;
+
+// 键盘框选：虚拟光标十字的臂长，按住方向键后开始加速的延迟和每帧最大步长
+const CROSSHAIR_ARM: i32 = 10;
+const KEY_REPEAT_DELAY: Duration = Duration::from_millis(300);
+const KEY_REPEAT_MAX_STEP: i32 = 20;
+
+// pan_and_scan：光标距窗口边缘多少像素内开始平移、每帧平移的像素，右下角视口小地图的宽度
+const PAN_EDGE: i32 = 24;
+const PAN_STEP: i32 = 24;
+const MINIMAP_WIDTH: i32 = 160;
+
 // 全局热键ID
 const HOTKEY_ID: i32 = 1;
 const SAVE_HOTKEY_ID: i32 = 2;
+const STOP_HOTKEY_ID: i32 = 3;
+const CANCEL_SAVE_HOTKEY_ID: i32 = 4;
+const SUSPEND_HOTKEY_ID: i32 = 5;
+const QUICK_HOTKEY_ID: i32 = 6;
+const COPY_PATH_HOTKEY_ID: i32 = 7;
+const VIEWER_HOTKEY_ID: i32 = 8;
+// 按屏幕截图热键从此ID起依次编号（修饰键 + 1..9）
+const MONITOR_HOTKEY_ID_BASE: i32 = 90;
+const MONITOR_HOTKEY_COUNT: usize = 9;
+// 命名区域热键从此ID起依次编号
+const PRESET_HOTKEY_ID_BASE: i32 = 100;
+// 截图热键防抖：距上次触发不足此时长的按键被忽略
+const CAPTURE_HOTKEY_DEBOUNCE: Duration = Duration::from_millis(300);
 
 fn main() {
+    // 崩溃时注销全局热键
+    hotkey::install_panic_hook();
+
+    // 读取配置
+    let mut config = Config::load();
+
+    // 保存的图像是否带 sRGB 配置文件
+    icc::set_embed(config.embed_srgb_profile);
+    // 截到 HDR 显示器时的提示和色调映射
+    hdr::configure(config.hdr_tone_map, config.hdr_gamma);
+
+    // 保存回调：二进制本身不做额外处理，嵌入时在此替换为自己的 SaveHook
+    save_hook::register(save_hook::NoopHook);
+
+    // 定时截图模式：不创建窗口，按间隔截取固定区域直到按下停止热键
+    if std::env::args().any(|arg| arg == "--timer") {
+        timer::run(&config);
+        return;
+    }
+
+    // 联系表模式：定时截取固定区域，拼成一张网格图
+    if std::env::args().any(|arg| arg == "--contact-sheet") {
+        contact_sheet::run(&config);
+        return;
+    }
+
+    // 批量裁剪模式：截一次屏，按 CSV 列出的区域逐个保存
+    if std::env::args().any(|arg| arg == "--batch") {
+        match std::env::args().skip_while(|arg| arg != "--batch").nth(1) {
+            Some(path) => batch::run(&path, &config),
+            None => eprintln!("Usage: captrue --batch <file.csv>"),
+        }
+        return;
+    }
+
+    // 进程窗口模式：逐个截取指定进程的所有顶层窗口
+    if let Some(process) = std::env::args()
+        .skip_while(|arg| arg != "--process-windows")
+        .nth(1)
+    {
+        process_windows::run(&process, &config);
+        return;
+    }
+
+    // 服务模式：常驻进程，通过标准输入接收截图命令
+    if std::env::args().any(|arg| arg == "--server") {
+        server::run(&config);
+        return;
+    }
+
+    // 获取屏幕信息
+    let screens = Screen::all().unwrap();
+    let mut primary_screen = *screens.first().unwrap();
+    let screen_width = primary_screen.display_info.width as usize;
+    let screen_height = primary_screen.display_info.height as usize;
+    let (overlay_x, overlay_y, overlay_width, overlay_height) = overlay_bounds(&screens, &config);
+
+    println!("Primary screen: {}x{}", screen_width, screen_height);
+    println!(
+        "Press {} to capture screen, ESC to exit",
+        config.capture_hotkey
+    );
+    println!("Press {} to save selected region", config.save_hotkey);
+    println!(
+        "Press {} to suspend or resume the global hotkeys",
+        config.suspend_hotkey
+    );
+
+    // 创建窗口
+    let mut window = create_window(overlay_width as usize, overlay_height as usize);
+
+    // 无边框代码
+    #[cfg(windows)]
+    unsafe {
+        use winapi::um::winuser::{GWL_STYLE, SetWindowLongPtrW};
+        use winapi::um::winuser::{WS_POPUP, WS_VISIBLE};
+
+        let hwnd = window.get_window_handle() as *mut _;
+        SetWindowLongPtrW(hwnd, GWL_STYLE, (WS_POPUP | WS_VISIBLE) as isize);
+    }
+
+    // 首次运行：显示设置向导
+    if setup::needed() {
+        setup::run(&mut window, &mut config);
+    }
+
+    // live 覆盖模式：窗口改为分层窗口，按每像素 alpha 叠加在实时桌面上
+    if config.overlay_mode == OverlayMode::Live {
+        layered::enable(window.get_window_handle() as _);
+    }
+
     // 创建通道用于线程间通信
     let (tx, rx): (Sender<AppEvent>, Receiver<AppEvent>) = channel();
 
+    // 笔和触摸输入转换为鼠标事件
+    pointer::install(window.get_window_handle() as _, tx.clone());
+
     // 启动消息处理线程
     let tx_clone = tx.clone();
+    // 暂停热键可注销/重新注册的热键：(ID, 热键, 名称)
+    let mut managed_hotkeys = vec![(
+        HOTKEY_ID,
+        config.capture_hotkey,
+        "capture hotkey".to_string(),
+    )];
+    // 保存（默认 Ctrl+S），配置为窗口内按键时不注册
+    if config.save_hotkey_global {
+        managed_hotkeys.push((
+            SAVE_HOTKEY_ID,
+            config.save_hotkey,
+            "save hotkey".to_string(),
+        ));
+    }
+    // 取消后台保存（仅 async_save 时，默认 Ctrl+Alt+X）
+    if config.async_save {
+        managed_hotkeys.push((
+            CANCEL_SAVE_HOTKEY_ID,
+            config.cancel_save_hotkey,
+            "cancel save hotkey".to_string(),
+        ));
+    }
+    // 快速截取（未配置时不注册）
+    if let Some(quick_hotkey) = config.quick_hotkey {
+        managed_hotkeys.push((
+            QUICK_HOTKEY_ID,
+            quick_hotkey,
+            "quick capture hotkey".to_string(),
+        ));
+    }
+    // 复制最近保存的文件路径（未配置时不注册）
+    if let Some(copy_path_hotkey) = config.copy_path_hotkey {
+        managed_hotkeys.push((
+            COPY_PATH_HOTKEY_ID,
+            copy_path_hotkey,
+            "copy path hotkey".to_string(),
+        ));
+    }
+    // 截图查看器（未配置时不注册）
+    if let Some(viewer_hotkey) = config.viewer_hotkey {
+        managed_hotkeys.push((VIEWER_HOTKEY_ID, viewer_hotkey, "viewer hotkey".to_string()));
+    }
+    // 命名区域
+    for (i, preset) in config.presets.iter().enumerate() {
+        managed_hotkeys.push((
+            PRESET_HOTKEY_ID_BASE + i as i32,
+            preset.hotkey,
+            format!("hotkey for preset '{}'", preset.name),
+        ));
+    }
+    let suspend_hotkey = config.suspend_hotkey;
+    let monitor_hotkey_modifiers = config.monitor_hotkey_modifiers;
+    let (mouse_gesture, gesture_modifiers) =
+        (config.mouse_gesture.clone(), config.gesture_modifiers);
     thread::spawn(move || {
-        // 注册全局热键: Ctrl+Alt+D 用于截图
-        unsafe {
-            RegisterHotKey(
-                null_mut(),
-                HOTKEY_ID,
-                MOD_CONTROL as u32 | MOD_ALT as u32,
-                'D' as u32,
+        // 线程结束（包括 panic）时自动注销热键
+        let mut hotkeys = hotkey::HotkeyGuard::new();
+
+        // 注册全局热键: 截图（默认 Ctrl+Alt+D）、保存、取消保存和命名区域
+        let register_managed = |hotkeys: &mut hotkey::HotkeyGuard| {
+            for (id, hotkey, name) in &managed_hotkeys {
+                if !hotkeys.register(*id, *hotkey) {
+                    eprintln!("Failed to register {} {}", name, hotkey);
+                }
+            }
+        };
+        register_managed(&mut hotkeys);
+        // 按屏幕截图（monitor_hotkey_modifiers + 1..9，未配置时不注册）：每个检测到的显示器一个，显示器增减时先注销再按当前数量注册
+        let register_monitors = |hotkeys: &mut hotkey::HotkeyGuard| {
+            let Some(modifiers) = monitor_hotkey_modifiers else {
+                return;
+            };
+            for i in 0..MONITOR_HOTKEY_COUNT {
+                hotkeys.unregister(MONITOR_HOTKEY_ID_BASE + i as i32);
+            }
+            let count = Screen::all()
+                .map_or(0, |screens| screens.len())
+                .min(MONITOR_HOTKEY_COUNT);
+            for i in 0..count {
+                let hotkey = config::Hotkey {
+                    modifiers,
+                    vk: '1' as u32 + i as u32,
+                };
+                if !hotkeys.register(MONITOR_HOTKEY_ID_BASE + i as i32, hotkey) {
+                    eprintln!("Failed to register hotkey {} for monitor {}", hotkey, i + 1);
+                }
+            }
+        };
+        register_monitors(&mut hotkeys);
+        if !hotkey::watch_display_changes() {
+            eprintln!(
+                "Failed to watch for display changes, restart after changing monitors or resolution"
             );
-            // 注册全局热键: Ctrl+S 用于保存
-            RegisterHotKey(null_mut(), SAVE_HOTKEY_ID, MOD_CONTROL as u32, 'S' as u32);
+        }
+        // 注册全局热键: 暂停/恢复（默认 Ctrl+Alt+F12），暂停期间保持注册
+        if !hotkeys.register(SUSPEND_HOTKEY_ID, suspend_hotkey) {
+            eprintln!("Failed to register suspend hotkey {}", suspend_hotkey);
         }
 
+        // 鼠标手势触发截图：低级鼠标钩子须装在运行消息循环的线程上，线程结束时卸载
+        let install_gesture =
+            || gesture::GestureHook::install(&mouse_gesture, gesture_modifiers, tx_clone.clone());
+        let mut _gesture_hook = install_gesture();
+        let mut suspended = false;
+        let mut last_capture: Option<Instant> = None;
+
         // Windows 消息循环
         let mut msg: MSG = unsafe { zeroed() };
         loop {
@@ -191,76 +1152,108 @@ fn main() {
 
             match msg.message {
                 WM_HOTKEY => match msg.wParam as i32 {
-                    HOTKEY_ID => {
+                    // 连按时只发送第一次，避免事件堆积
+                    HOTKEY_ID
+                        if last_capture
+                            .is_none_or(|at| at.elapsed() >= CAPTURE_HOTKEY_DEBOUNCE) =>
+                    {
+                        last_capture = Some(Instant::now());
                         tx_clone.send(AppEvent::GlobalHotkeyPressed).unwrap();
                     }
                     SAVE_HOTKEY_ID => {
-                        tx_clone.send(AppEvent::KeyPressed(Key::S)).unwrap();
+                        tx_clone.send(AppEvent::SaveHotkeyPressed).unwrap();
                     }
-                    _ => {}
-                },
-                WM_QUIT => {
-                    tx_clone.send(AppEvent::Quit).unwrap();
-                    break;
+                    QUICK_HOTKEY_ID => {
+                        tx_clone.send(AppEvent::QuickHotkeyPressed).unwrap();
+                    }
+                    CANCEL_SAVE_HOTKEY_ID => {
+                        tx_clone.send(AppEvent::CancelSaveHotkeyPressed).unwrap();
+                    }
+                    VIEWER_HOTKEY_ID => {
+                        tx_clone.send(AppEvent::ViewerHotkeyPressed).unwrap();
+                    }
+                    COPY_PATH_HOTKEY_ID => {
+                        tx_clone.send(AppEvent::CopyPathHotkeyPressed).unwrap();
+                    }
+                    // 暂停时注销其余热键并卸载手势钩子（如全屏游戏需要这些按键），恢复时重新注册
+                    SUSPEND_HOTKEY_ID => {
+                        suspended = !suspended;
+                        if suspended {
+                            for (id, _, _) in &managed_hotkeys {
+                                hotkeys.unregister(*id);
+                            }
+                            for i in 0..MONITOR_HOTKEY_COUNT {
+                                hotkeys.unregister(MONITOR_HOTKEY_ID_BASE + i as i32);
+                            }
+                            _gesture_hook = None;
+                            println!("Hotkeys suspended, press {} to resume", suspend_hotkey);
+                        } else {
+                            register_managed(&mut hotkeys);
+                            register_monitors(&mut hotkeys);
+                            _gesture_hook = install_gesture();
+                            println!("Hotkeys resumed");
+                        }
+                    }
+                    id if (MONITOR_HOTKEY_ID_BASE
+                        ..MONITOR_HOTKEY_ID_BASE + MONITOR_HOTKEY_COUNT as i32)
+                        .contains(&id) =>
+                    {
+                        let index = (id - MONITOR_HOTKEY_ID_BASE) as usize;
+                        tx_clone
+                            .send(AppEvent::MonitorHotkeyPressed(index))
+                            .unwrap();
+                    }
+                    id if id >= PRESET_HOTKEY_ID_BASE => {
+                        let index = (id - PRESET_HOTKEY_ID_BASE) as usize;
+                        tx_clone.send(AppEvent::PresetHotkeyPressed(index)).unwrap();
+                    }
+                    _ => {}
+                },
+                // 显示器增减或分辨率变化：通知主循环刷新屏幕信息，按屏幕截图热键随之重新注册（暂停期间恢复时再注册）
+                WM_DISPLAYCHANGE => {
+                    tx_clone.send(AppEvent::DisplayChanged).unwrap();
+                    if !suspended {
+                        register_monitors(&mut hotkeys);
+                    }
+                }
+                WM_QUIT => {
+                    tx_clone.send(AppEvent::Quit).unwrap();
+                    break;
                 }
                 _ => {}
             }
         }
-
-        // 取消注册热键
-        unsafe {
-            UnregisterHotKey(null_mut(), HOTKEY_ID);
-            UnregisterHotKey(null_mut(), SAVE_HOTKEY_ID);
-        }
-    });
-
-    // 获取屏幕信息
-    let screens = Screen::all().unwrap();
-    let primary_screen = screens.first().unwrap();
-    let screen_width = primary_screen.display_info.width as usize;
-    let screen_height = primary_screen.display_info.height as usize;
-
-    println!("Primary screen: {}x{}", screen_width, screen_height);
-    println!("Press Ctrl+Alt+D to capture screen, ESC to exit");
-    println!("Press Ctrl+S to save selected region");
-
-    // 创建窗口选项
-    let mut window_options = WindowOptions::default();
-    window_options.resize = false;
-    window_options.scale = Scale::X1;
-    window_options.scale_mode = ScaleMode::AspectRatioStretch;
-    window_options.topmost = true;
-    window_options.borderless = true;
-    window_options.transparency = true;
-
-    // 创建窗口
-    let mut window = Window::new(
-        "Screen Capture - Press ESC to exit",
-        screen_width,
-        screen_height,
-        window_options,
-    )
-    .unwrap_or_else(|e| {
-        panic!("{}", e);
     });
 
-    // 无边框代码
-    #[cfg(windows)]
-    unsafe {
-        use winapi::um::winuser::{GWL_STYLE, SetWindowLongPtrW};
-        use winapi::um::winuser::{WS_POPUP, WS_VISIBLE};
-
-        let hwnd = window.get_window_handle() as *mut _;
-        SetWindowLongPtrW(hwnd, GWL_STYLE, (WS_POPUP | WS_VISIBLE) as isize);
-    }
-
-    // 设置帧率限制
-    window.set_target_fps(60); // 降低帧率以减少CPU使用
-
     // 初始化状态
     let mut state = State::Idle;
+    let mut session = Session {
+        loupe_grid: config.loupe_grid,
+        save_worker: config
+            .async_save
+            .then(|| SaveWorker::spawn(config.clone(), tx.clone())),
+        events: Some(tx.clone()),
+        capture_excluded: window_info::exclude_from_capture(window.get_window_handle() as _),
+        overlay_origin: (overlay_x as isize, overlay_y as isize),
+        home_overlay: (
+            overlay_x as isize,
+            overlay_y as isize,
+            overlay_width as usize,
+            overlay_height as usize,
+        ),
+        precapture: (config.precapture_ms > 0).then(|| PreCapture::spawn(primary_screen, &config)),
+        shape: config.selection_shape,
+        print_region: std::env::args().any(|arg| arg == "--print-region"),
+        fit_to_window: config.fit_to_window,
+        ..Default::default()
+    };
     let mut mouse_pressed = false;
 
+    // 帧计数，用于边框动画
+    let mut frame: u64 = 0;
+    // 当前帧率对应的是否为空闲状态，状态切换时重新设置帧率
+    let mut paced_idle: Option<bool> = None;
+
     // 事件队列
     let mut events = VecDeque::new();
 
@@ -269,14 +1262,48 @@ fn main() {
 
     // 按键状态跟踪
     let mut key_states = std::collections::HashMap::new();
+    let mut save_vk_down = false;
 
     // 缓存图像显示缓冲区，避免频繁分配内存
     let mut display_buffer: Option<Vec<u32>> = None;
 
+    // 截图进行中收到的显示器变化，回到空闲后再处理
+    let mut display_changed = false;
+
     // 主事件循环
     while window.is_open() {
+        let cursor_before = session.cursor;
+
         // 检查来自消息线程的事件
         while let Ok(event) = rx.try_recv() {
+            // 指针消息为窗口坐标，换算为截图坐标
+            let event = match event {
+                AppEvent::MouseMoved(x, y) => {
+                    let (x, y) = to_frame_coords(&session, &config, x, y);
+                    AppEvent::MouseMoved(x, y)
+                }
+                AppEvent::MousePressed(button, x, y) => {
+                    let (x, y) = to_frame_coords(&session, &config, x, y);
+                    AppEvent::MousePressed(button, x, y)
+                }
+                AppEvent::MouseReleased(button, x, y) => {
+                    let (x, y) = to_frame_coords(&session, &config, x, y);
+                    AppEvent::MouseReleased(button, x, y)
+                }
+                event => event,
+            };
+            if let AppEvent::MouseMoved(x, y) = event {
+                session.cursor = (x as i32, y as i32);
+            }
+            // 同一帧的事件都按当前状态处理：已在截图中或已有一个截图请求排队时，丢弃多余的截图热键（含鼠标手势）
+            if matches!(event, AppEvent::GlobalHotkeyPressed)
+                && (!matches!(state, State::Idle)
+                    || events
+                        .iter()
+                        .any(|queued| matches!(queued, AppEvent::GlobalHotkeyPressed)))
+            {
+                continue;
+            }
             events.push_back(event);
         }
 
@@ -285,9 +1312,27 @@ fn main() {
             if !key_states.contains_key(key) {
                 key_states.insert(*key, true);
                 events.push_back(AppEvent::KeyPressed(*key));
+
+                // 保存键未注册为全局热键时，在窗口内匹配
+                if !config.save_hotkey_global
+                    && config.save_hotkey.key() == Some(*key)
+                    && Modifiers::poll().flags() == config.save_hotkey.modifiers
+                {
+                    events.push_back(AppEvent::SaveHotkeyPressed);
+                }
             }
         });
 
+        // 保存键没有对应的 minifb 按键（F16–F24）时直接查询虚拟键，按下的那一帧触发
+        if !config.save_hotkey_global && config.save_hotkey.key().is_none() {
+            let down = window.is_active()
+                && unsafe { GetAsyncKeyState(config.save_hotkey.vk as i32) } as u16 & 0x8000 != 0;
+            if down && !save_vk_down && Modifiers::poll().flags() == config.save_hotkey.modifiers {
+                events.push_back(AppEvent::SaveHotkeyPressed);
+            }
+            save_vk_down = down;
+        }
+
         // 检查释放的键
         let current_keys: Vec<Key> = window.get_keys().to_vec();
         let previous_keys: Vec<Key> = key_states.keys().cloned().collect();
@@ -299,9 +1344,13 @@ fn main() {
             }
         }
 
-        // 处理鼠标事件
-        if let Some((x, y)) = window.get_mouse_pos(MouseMode::Clamp) {
+        // 处理鼠标事件（笔或手指接触屏幕时由指针消息产生）
+        if !pointer::in_contact()
+            && let Some((x, y)) = window.get_mouse_pos(MouseMode::Clamp)
+        {
+            let (x, y) = to_frame_coords(&session, &config, x, y);
             events.push_back(AppEvent::MouseMoved(x, y));
+            session.cursor = (x as i32, y as i32);
 
             if window.get_mouse_down(MouseButton::Left) && !mouse_pressed {
                 mouse_pressed = true;
@@ -312,14 +1361,48 @@ fn main() {
             }
         }
 
-        // 处理所有事件
+        // 记录最近的输入时间：光标移动、按键、点击或截图/保存热键（每帧都有的 MouseMoved 不算）
+        if session.cursor != cursor_before
+            || events.iter().any(|event| {
+                matches!(
+                    event,
+                    AppEvent::KeyPressed(_)
+                        | AppEvent::KeyReleased(_)
+                        | AppEvent::MousePressed(..)
+                        | AppEvent::MouseReleased(..)
+                        | AppEvent::GlobalHotkeyPressed
+                        | AppEvent::QuickHotkeyPressed
+                        | AppEvent::MonitorHotkeyPressed(_)
+                        | AppEvent::SaveHotkeyPressed
+                )
+            })
+        {
+            session.last_input = Some(Instant::now());
+        }
+
+        events.push_back(AppEvent::Tick);
+
+        // 处理所有事件，同一帧的事件使用同一份修饰键状态
+        let modifiers = Modifiers::poll();
         let mut processed_events = Vec::new();
         while let Some(event) = events.pop_front() {
             if let AppEvent::Quit = event {
                 break;
             }
+            if let AppEvent::DisplayChanged = event {
+                display_changed = true;
+                continue;
+            }
 
-            let new_state = handle_event(event.clone(), &state, &mut window, primary_screen);
+            let new_state = handle_event(
+                event.clone(),
+                &state,
+                &mut window,
+                &primary_screen,
+                &config,
+                &mut session,
+                modifiers,
+            );
             processed_events.push((event, new_state));
         }
 
@@ -332,250 +1415,3764 @@ fn main() {
             }
         }
 
+        // 空闲时继续预截图并释放上次的可选帧，截图窗口显示期间暂停
+        let idle = matches!(state, State::Idle);
+        // 截图进行中不更换主屏和覆盖窗口大小，当前帧的坐标仍按截图时的屏幕计算
+        if display_changed && idle {
+            refresh_screens(&mut primary_screen, &mut window, &mut session, &config);
+            display_buffer = None;
+            display_changed = false;
+        }
+        if let Some(precapture) = &session.precapture {
+            precapture.set_paused(!idle);
+        }
+        if idle && !session.rewind.is_empty() {
+            session.rewind.clear();
+        }
+
+        // pan_and_scan：截图大于窗口时光标靠近窗口边缘平移视口，键盘框选时视口跟随虚拟光标
+        // fit_to_window：截图大于窗口时整帧缩小显示，此时不平移
+        session.fit_scale = match display_cache(&state) {
+            Some(cache) if session.fit_to_window => fit_scale(&window, cache),
+            _ => None,
+        };
+        session.viewport = match display_cache(&state) {
+            Some(cache) if config.pan_and_scan && session.fit_scale.is_none() => {
+                let follow = match &state {
+                    State::KeyboardSelect(_, _, cursor, _) => Some(*cursor),
+                    _ => None,
+                };
+                pan_viewport(&window, cache, session.viewport, session.cursor, follow)
+            }
+            _ => (0, 0),
+        };
+
         // 根据当前状态更新显示
-        update_display(&mut window, &mut state, &mut display_buffer);
+        frame = frame.wrapping_add(1);
+        let render_started = Instant::now();
+        update_display(
+            &mut window,
+            &mut state,
+            &session,
+            &config,
+            &mut display_buffer,
+            frame,
+        );
+        metrics::record(Metric::Render, render_started.elapsed());
+
+        // 空闲（窗口隐藏）时用 idle_fps，截图时用 active_fps；window.update 按目标帧率等待
+        if paced_idle != Some(idle) {
+            paced_idle = Some(idle);
+            window.set_target_fps(target_fps(idle, &config));
+        }
 
         // 更新窗口
         window.update();
+    }
+
+    // 通知消息线程退出并注销热键
+    hotkey::shutdown();
+}
+
+// 创建覆盖窗口。部分环境（远程桌面、虚拟机、软件渲染）不支持透明或置顶，
+// 创建失败时依次去掉透明、置顶后重试，并输出被禁用的选项；全部失败时退出
+fn create_window(width: usize, height: usize) -> Window {
+    let mut window_options = WindowOptions::default();
+    window_options.resize = false;
+    window_options.scale = Scale::X1;
+    window_options.scale_mode = ScaleMode::AspectRatioStretch;
+    window_options.topmost = true;
+    window_options.borderless = true;
+    window_options.transparency = true;
+
+    let mut disabled = Vec::new();
+    loop {
+        match Window::new(
+            "Screen Capture - Press ESC to exit",
+            width,
+            height,
+            window_options,
+        ) {
+            Ok(window) => {
+                if !disabled.is_empty() {
+                    eprintln!("Overlay window created without: {}", disabled.join(", "));
+                }
+                return window;
+            }
+            Err(e) if window_options.transparency => {
+                eprintln!(
+                    "Failed to create overlay window ({}), retrying without transparency",
+                    e
+                );
+                window_options.transparency = false;
+                disabled.push("transparency");
+            }
+            Err(e) if window_options.topmost => {
+                eprintln!(
+                    "Failed to create overlay window ({}), retrying without topmost",
+                    e
+                );
+                window_options.topmost = false;
+                disabled.push("topmost");
+            }
+            Err(e) => {
+                eprintln!("Failed to create overlay window: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+// 修饰键状态：每帧用 GetAsyncKeyState 查询 Shift、Ctrl、Alt 和 Win（左右两侧任一按下即算），
+// 不依赖 minifb 的按键轮询——它在窗口刚获得焦点、或修饰键在窗口显示前已按下时会漏报
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Modifiers {
+    shift: bool,
+    ctrl: bool,
+    alt: bool,
+    win: bool,
+}
 
-        // 短暂延迟以减少CPU使用
-        std::thread::sleep(std::time::Duration::from_millis(16)); // ~60fps
+impl Modifiers {
+    fn poll() -> Self {
+        let down = |vk: i32| unsafe { GetAsyncKeyState(vk) } as u16 & 0x8000 != 0;
+        Self {
+            shift: down(VK_SHIFT),
+            ctrl: down(VK_CONTROL),
+            alt: down(VK_MENU),
+            win: down(VK_LWIN) || down(VK_RWIN),
+        }
     }
 
-    // 发送退出消息给消息线程
-    unsafe {
-        PostQuitMessage(0);
+    // RegisterHotKey 的 MOD_* 格式
+    fn flags(self) -> u32 {
+        [
+            (self.ctrl, MOD_CONTROL),
+            (self.alt, MOD_ALT),
+            (self.shift, MOD_SHIFT),
+            (self.win, MOD_WIN),
+        ]
+        .iter()
+        .filter(|(down, _)| *down)
+        .fold(0, |flags, (_, flag)| flags | *flag as u32)
     }
 }
 
+// 主循环的目标帧率：空闲为 idle_fps，否则为 active_fps；battery_fps 非 0 且使用电池供电时不超过 battery_fps
+fn target_fps(idle: bool, config: &Config) -> usize {
+    let fps = if idle {
+        config.idle_fps
+    } else {
+        config.active_fps
+    };
+    let fps = if config.battery_fps > 0 && on_battery() {
+        fps.min(config.battery_fps)
+    } else {
+        fps
+    };
+    fps.max(1) as usize
+}
+
+// 是否正在使用电池供电（ACLineStatus 为 0；未知时按接通电源处理）
+fn on_battery() -> bool {
+    let mut status: SYSTEM_POWER_STATUS = unsafe { zeroed() };
+    unsafe { GetSystemPowerStatus(&mut status) != 0 && status.ACLineStatus == 0 }
+}
+
 // 事件处理函数
 fn handle_event(
     event: AppEvent,
     state: &State,
     window: &mut Window,
     primary_screen: &Screen,
+    config: &Config,
+    session: &mut Session,
+    modifiers: Modifiers,
 ) -> Option<State> {
+    // 空格键保存后留在同一帧上，可继续截取其他区域
+    let keep_frame = matches!(event, AppEvent::KeyPressed(Key::Space));
+
+    // ESC 同时重置亮度/对比度调整和红框锁定
+    if matches!(event, AppEvent::KeyPressed(Key::Escape)) {
+        session.adjust = Adjustment::default();
+        session.region_locked = false;
+    }
+
     match (event, state) {
         (AppEvent::KeyPressed(Key::Escape), State::Idle) => {
+            hotkey::shutdown();
             std::process::exit(0);
         }
-        (AppEvent::KeyPressed(Key::Escape), State::FullscreenCapture(img, _)) => {
+        (
+            AppEvent::KeyPressed(Key::Escape),
+            State::FullscreenCapture(img, _) | State::LivePreview(img, _, _),
+        ) => {
+            reset_session_for_idle(session);
             window.set_position(-(img.width() as isize * 2), -(img.height() as isize * 2));
-            window.set_title("Screen Capture - Press Ctrl+Alt+D to capture screen, ESC to exit");
+            set_idle_title(window, config);
             Some(State::Idle)
         }
         (AppEvent::KeyPressed(Key::Escape), State::SelectingRegion(img, cache, _, _)) => {
             window.set_title("Screen captured - Click and drag to select region, ESC to cancel");
             Some(State::FullscreenCapture(img.clone(), cache.clone()))
         }
+        (AppEvent::KeyPressed(Key::Escape), State::EditingRegion(img, cache, _, _)) => {
+            window.set_title("Screen captured - Click and drag to select region, ESC to cancel");
+            Some(State::FullscreenCapture(img.clone(), cache.clone()))
+        }
         (AppEvent::KeyPressed(Key::Escape), State::RegionSelected(img, cache, _region)) => {
             window.set_title("Screen captured - Click and drag to select region, ESC to cancel");
             Some(State::FullscreenCapture(img.clone(), cache.clone()))
         }
-        (AppEvent::KeyPressed(Key::Escape), State::SelectingSubRegion(img, cache, red_region, _, _)) => {
-            window.set_title("Region selected - Press Ctrl+S to save, or click and drag to select sub-region, ESC to re-select");
-            Some(State::RegionSelected(img.clone(), cache.clone(), *red_region))
+        (
+            AppEvent::KeyPressed(Key::Escape),
+            State::SelectingSubRegion(img, cache, red_region, _, _),
+        ) => {
+            set_region_selected_title(window, config);
+            Some(State::RegionSelected(
+                img.clone(),
+                cache.clone(),
+                *red_region,
+            ))
+        }
+        (AppEvent::KeyPressed(Key::Escape), State::KeyboardSelect(img, cache, _, _)) => {
+            window.set_title("Screen captured - Click and drag to select region, ESC to cancel");
+            Some(State::FullscreenCapture(img.clone(), cache.clone()))
+        }
+        // 已点选第一个元素时 ESC 撤销它
+        (AppEvent::KeyPressed(Key::Escape), State::ElementPick(img, cache, elements, Some(_))) => {
+            window.set_title(ELEMENT_PICK_TITLE);
+            Some(State::ElementPick(
+                img.clone(),
+                cache.clone(),
+                elements.clone(),
+                None,
+            ))
+        }
+        (AppEvent::KeyPressed(Key::Escape), State::ElementPick(img, cache, _, None)) => {
+            window.set_title("Screen captured - Click and drag to select region, ESC to cancel");
+            Some(State::FullscreenCapture(img.clone(), cache.clone()))
+        }
+        (AppEvent::KeyPressed(Key::Escape), State::WindowPick(img, cache, _, _)) => {
+            window.set_title("Screen captured - Click and drag to select region, ESC to cancel");
+            Some(State::FullscreenCapture(img.clone(), cache.clone()))
         }
-        (AppEvent::KeyPressed(Key::Escape), State::SubRegionSelected(img, cache, red_region, _)) => {
-            window.set_title("Region selected - Press Ctrl+S to save, or click and drag to select sub-region, ESC to re-select");
-            Some(State::RegionSelected(img.clone(), cache.clone(), *red_region))
+        (
+            AppEvent::KeyPressed(Key::Escape),
+            State::SubRegionSelected(img, cache, red_region, _),
+        ) => {
+            set_region_selected_title(window, config);
+            Some(State::RegionSelected(
+                img.clone(),
+                cache.clone(),
+                *red_region,
+            ))
         }
         (AppEvent::GlobalHotkeyPressed, State::Idle) => {
+            session.quick = false;
+            reset_overlay(window, session);
+            // 记录前台窗口位置，保存时区域同时按相对窗口的坐标记录
+            session.foreground_rect = if config.relative_to_window {
+                window_info::foreground_window_rect()
+            } else {
+                None
+            };
+            // 覆盖窗口显示前记录前台窗口，截图后预先框选
+            session.preselect = if config.preselect_foreground_window {
+                window_info::foreground_window_bounds(
+                    window.get_window_handle() as _,
+                    config.include_popups,
+                )
+            } else {
+                None
+            };
+
+            // 本次运行第一次触发时先显示最近保存的截图
+            if config.history_size > 0 && !session.gallery_shown {
+                session.gallery_shown = true;
+                let entries = history::load();
+                if !entries.is_empty() {
+                    window.set_position(session.overlay_origin.0, session.overlay_origin.1);
+                    window.set_title(
+                        "Recent captures - Click one to reopen it, Enter for a new capture, ESC to cancel",
+                    );
+                    return Some(State::Gallery(entries));
+                }
+            }
+
+            if config.capture_delay_ms > 0 {
+                // 延时截图：窗口缩小为倒计时大小放在覆盖区域中央，不遮挡桌面的其余部分，也不拦截那里的点击
+                let (x, y, width, height) = session.home_overlay;
+                let size = COUNTDOWN_SIZE as usize;
+                set_window_rect(
+                    window,
+                    (
+                        x + (width as isize - size as isize) / 2,
+                        y + (height as isize - size as isize) / 2,
+                        size,
+                        size,
+                    ),
+                );
+                window.set_title("Capturing soon - ESC to cancel");
+                return Some(State::Countdown(
+                    Instant::now(),
+                    Duration::from_millis(config.capture_delay_ms),
+                ));
+            }
+            Some(begin_capture(window, primary_screen, config, session))
+        }
+        // 长时间无输入：取消截图并隐藏窗口，避免遮罩一直盖在屏幕上
+        (AppEvent::Tick, _)
+            if config.idle_timeout_secs > 0
+                && !matches!(state, State::Idle)
+                && session.last_input.is_some_and(|at| {
+                    at.elapsed() >= Duration::from_secs(config.idle_timeout_secs)
+                }) =>
+        {
+            println!(
+                "No input for {} s, capture cancelled",
+                config.idle_timeout_secs
+            );
+            reset_session_for_idle(session);
             window.set_position(
                 -(primary_screen.display_info.width as isize * 2),
                 -(primary_screen.display_info.height as isize * 2),
             );
-
-            std::thread::sleep(std::time::Duration::from_millis(100));
-
-            match capture_screen(primary_screen) {
+            set_idle_title(window, config);
+            Some(State::Idle)
+        }
+        // 截图失败后按任意键重新截图（如切换显示模式期间的暂时性失败）
+        (AppEvent::KeyPressed(key), State::CaptureFailed(_)) if key != Key::Escape => {
+            Some(begin_capture(window, primary_screen, config, session))
+        }
+        (AppEvent::Tick, State::Countdown(start, delay)) if start.elapsed() >= *delay => {
+            reset_overlay(window, session);
+            Some(begin_capture(window, primary_screen, config, session))
+        }
+        // 实时预览：到间隔时重新截屏（覆盖窗口已排除在截图之外，无需移开）
+        (AppEvent::Tick, State::LivePreview(_, _, captured_at))
+            if captured_at.elapsed() >= Duration::from_millis(config.live_preview_interval_ms) =>
+        {
+            let started = Instant::now();
+            match metrics::time(Metric::Capture, || {
+                capture_target(primary_screen, config, session)
+            }) {
                 Ok(image_buffer) => {
-                    window.set_position(0, 0);
-                    window.set_title(
-                        "Screen captured - Click and drag to select region, ESC to cancel",
-                    );
-                    let cache = DisplayCache::new(&image_buffer);
-                    Some(State::FullscreenCapture(image_buffer, cache))
+                    let cache =
+                        DisplayCache::new(&image_buffer, config.dim_tint, config.dim_tint_strength);
+                    Some(State::LivePreview(Arc::new(image_buffer), cache, started))
                 }
                 Err(e) => {
-                    window.set_position(0, 0);
                     eprintln!("Failed to capture screen: {}", e);
-                    Some(State::Idle)
+                    None
                 }
             }
         }
-        (AppEvent::KeyPressed(Key::S), State::RegionSelected(img, _, region)) => {
-            save_image_webp(
-                &img,
-                region.0,
-                region.1,
-                region.2 as u32,
-                region.3 as u32,
-                primary_screen.display_info.width as u32,
-                primary_screen.display_info.height as u32,
-                None,
-            );
-
-            window.set_position(-(img.width() as isize * 2), -(img.height() as isize * 2));
-            window.set_title("Screen Capture - Press Ctrl+Alt+D to capture screen, ESC to exit");
-            Some(State::Idle)
-        }
-        (AppEvent::KeyPressed(Key::S), State::SubRegionSelected(img, _, red_region, green_region)) => {
-            save_image_webp(
-                &img,
-                red_region.0,
-                red_region.1,
-                red_region.2 as u32,
-                red_region.3 as u32,
-                primary_screen.display_info.width as u32,
-                primary_screen.display_info.height as u32,
-                Some((
-                    green_region.0,
-                    green_region.1,
-                    green_region.2 as u32,
-                    green_region.3 as u32,
-                )),
-            );
-
-            window.set_position(-(img.width() as isize * 2), -(img.height() as isize * 2));
-            window.set_title("Screen Capture - Press Ctrl+Alt+D to capture screen, ESC to exit");
-            Some(State::Idle)
+        (
+            AppEvent::KeyPressed(Key::Space | Key::Enter),
+            State::LivePreview(img, cache, captured_at),
+        ) => {
+            session.captured_at = Some(*captured_at);
+            if config.annotate_first && !session.quick {
+                session.stamp_mode = true;
+                set_stamp_title(window, session);
+                return Some(State::FullscreenCapture(img.clone(), cache.clone()));
+            }
+            window.set_title("Screen captured - Click and drag to select region, ESC to cancel");
+            Some(State::FullscreenCapture(img.clone(), cache.clone()))
         }
-        (AppEvent::MousePressed(MouseButton::Left, x, y), State::FullscreenCapture(img, cache)) => Some(
-            State::SelectingRegion(img.clone(), cache.clone(), (x as i32, y as i32), (x as i32, y as i32)),
-        ),
-        (AppEvent::MouseMoved(x, y), State::SelectingRegion(img, cache, start, _)) => Some(
-            State::SelectingRegion(img.clone(), cache.clone(), *start, (x as i32, y as i32)),
-        ),
+        // 点击时冻结当前帧并直接开始框选；annotate_first 时与空格/回车相同，先进入标注（这次点击只用于冻结）
         (
-            AppEvent::MouseReleased(MouseButton::Left, _x, _y),
-            State::SelectingRegion(img, cache, start, current),
+            AppEvent::MousePressed(MouseButton::Left, x, y),
+            State::LivePreview(img, cache, captured_at),
         ) => {
-            let width = (current.0 - start.0).abs() as u32;
-            let height = (current.1 - start.1).abs() as u32;
-
-            if width > 10 && height > 10 {
-                let region = (
-                    start.0.min(current.0),
-                    start.1.min(current.1),
-                    width as i32,
-                    height as i32,
-                );
-
-                window.set_title("Region selected - Press Ctrl+S to save, or click and drag to select sub-region, ESC to re-select");
-                Some(State::RegionSelected(img.clone(), cache.clone(), region))
-            } else {
-                window
-                    .set_title("Screen captured - Click and drag to select region, ESC to cancel");
-                Some(State::FullscreenCapture(img.clone(), cache.clone()))
+            session.captured_at = Some(*captured_at);
+            if config.annotate_first && !session.quick {
+                session.stamp_mode = true;
+                set_stamp_title(window, session);
+                return Some(State::FullscreenCapture(img.clone(), cache.clone()));
             }
+            window.set_title("Screen captured - Click and drag to select region, ESC to cancel");
+            let point = snap_point((x as i32, y as i32), cache, config, session);
+            session.selection_anchor = point;
+            Some(State::SelectingRegion(
+                img.clone(),
+                cache.clone(),
+                point,
+                point,
+            ))
         }
-        (AppEvent::MousePressed(MouseButton::Left, x, y), State::RegionSelected(img, cache, region)) => {
-            // 检查点击是否在红框内
-            if x as i32 >= region.0
-                && x as i32 <= region.0 + region.2
-                && y as i32 >= region.1
-                && y as i32 <= region.1 + region.3
-            {
-                Some(State::SelectingSubRegion(
-                    img.clone(),
-                    cache.clone(),
-                    *region,
-                    (x as i32, y as i32),
-                    (x as i32, y as i32),
-                ))
+        (AppEvent::KeyPressed(Key::Enter | Key::Space), State::Gallery(_)) => {
+            Some(begin_capture(window, primary_screen, config, session))
+        }
+        (AppEvent::MousePressed(MouseButton::Left, x, y), State::Gallery(entries)) => {
+            let index = history::hit_test(
+                entries.len(),
+                primary_screen.display_info.width,
+                x as i32,
+                y as i32,
+            )?;
+            match history::reopen(
+                &entries[index],
+                primary_screen.display_info.width,
+                primary_screen.display_info.height,
+            ) {
+                Ok(image_buffer) => {
+                    window.set_title(
+                        "Screen captured - Click and drag to select region, ESC to cancel",
+                    );
+                    let cache =
+                        DisplayCache::new(&image_buffer, config.dim_tint, config.dim_tint_strength);
+                    Some(State::FullscreenCapture(Arc::new(image_buffer), cache))
+                }
+                Err(e) => {
+                    eprintln!("Failed to reopen capture: {}", e);
+                    None
+                }
+            }
+        }
+        // 快速截取：直接显示截图，拖动一次即完成（不显示画廊、不延时、不预选窗口）
+        (AppEvent::QuickHotkeyPressed, State::Idle) => {
+            session.quick = true;
+            reset_overlay(window, session);
+            session.preselect = None;
+            session.foreground_rect = if config.relative_to_window {
+                window_info::foreground_window_rect()
             } else {
-                None // 点击在红框外，不处理
+                None
+            };
+            let state = begin_capture(window, primary_screen, config, session);
+            if matches!(state, State::FullscreenCapture(..)) && session.rewind.is_empty() {
+                let target = match config.quick_target {
+                    QuickTarget::Clipboard => "copy to the clipboard",
+                    QuickTarget::File => "save",
+                };
+                window.set_title(&format!(
+                    "Quick capture - Drag to {}, ESC to cancel",
+                    target
+                ));
             }
+            Some(state)
         }
-        (AppEvent::MouseMoved(x, y), State::SelectingSubRegion(img, cache, red_region, start, _)) => {
-            // 限制绿框在红框内
-            let clamped_x = x.clamp(
-                red_region.0 as f32,
-                red_region.0 as f32 + red_region.2 as f32,
-            );
-            let clamped_y = y.clamp(
-                red_region.1 as f32,
-                red_region.1 as f32 + red_region.3 as f32,
-            );
-
-            Some(State::SelectingSubRegion(
+        // 截图查看器：Delete 后等待确认，Y 删除，其他键（含 ESC）取消
+        (AppEvent::ViewerHotkeyPressed, State::Idle) => {
+            let Some(viewer) = viewer::Viewer::open(config) else {
+                println!("No saved captures to view");
+                return None;
+            };
+            reset_overlay(window, session);
+            window.set_position(session.overlay_origin.0, session.overlay_origin.1);
+            window.set_title(&viewer.title());
+            Some(State::Viewer(viewer))
+        }
+        (AppEvent::KeyPressed(Key::M), State::RegionSelected(img, cache, region)) => {
+            let prompt = PresetPrompt::default();
+            window.set_title(&preset_prompt_title(&prompt));
+            Some(State::PresetName(
                 img.clone(),
                 cache.clone(),
-                *red_region,
-                *start,
-                (clamped_x as i32, clamped_y as i32),
+                *region,
+                prompt,
             ))
         }
+        // 已注册为全局热键的组合不会作为按键传到窗口，提示换一个
         (
-            AppEvent::MouseReleased(MouseButton::Left, _x, _y),
-            State::SelectingSubRegion(img, cache, red_region, start, current),
+            AppEvent::GlobalHotkeyPressed
+            | AppEvent::SaveHotkeyPressed
+            | AppEvent::QuickHotkeyPressed
+            | AppEvent::ViewerHotkeyPressed
+            | AppEvent::CopyPathHotkeyPressed
+            | AppEvent::MonitorHotkeyPressed(_)
+            | AppEvent::PresetHotkeyPressed(_),
+            State::PresetName(..),
         ) => {
-            let width = (current.0 - start.0).abs() as u32;
-            let height = (current.1 - start.1).abs() as u32;
-
-            if width > 5 && height > 5 {
-                let green_region = (
-                    start.0.min(current.0),
-                    start.1.min(current.1),
-                    width as i32,
-                    height as i32,
-                );
-
-                window.set_title("Sub-region selected - Press Ctrl+S to save, ESC to re-select");
-                Some(State::SubRegionSelected(
+            window.set_title("That combination is already a captrue hotkey - Press a different one, ESC to cancel");
+            None
+        }
+        (AppEvent::KeyPressed(key), State::PresetName(img, cache, region, prompt)) => {
+            let mut prompt = prompt.clone();
+            match key {
+                Key::Escape => {
+                    set_region_selected_title(window, config);
+                    return Some(State::RegionSelected(img.clone(), cache.clone(), *region));
+                }
+                Key::Enter => {
+                    let name = prompt.name.trim().to_string();
+                    match (name.is_empty(), prompt.hotkey) {
+                        (true, _) => window.set_title("Type a name for the preset first - ESC to cancel"),
+                        (false, None) => window.set_title("Press a hotkey for the preset (e.g. Ctrl+Shift+1) first - ESC to cancel"),
+                        (false, Some(_)) if !prompt.overwrite && config::preset_exists(&name) => {
+                            prompt.overwrite = true;
+                            window.set_title(&preset_prompt_title(&prompt));
+                        }
+                        (false, Some(hotkey)) => {
+                            let (monitor, (dx, dy)) = region_monitor(*region, session.overlay_origin);
+                            let (x, y, w, h) = *region;
+                            let preset = config::Preset { name, region: (x + dx, y + dy, w, h), monitor, hotkey };
+                            match config::save_preset(&preset) {
+                                Ok(()) => {
+                                    println!("Preset '{}' saved to {} (its hotkey works after a restart)", preset.name, config::CONFIG_FILE);
+                                    window.set_title(&format!("Preset '{}' saved - Press {} to save, ESC to re-select", preset.name, config.save_hotkey));
+                                }
+                                Err(e) => {
+                                    eprintln!("Failed to write {}: {}", config::CONFIG_FILE, e);
+                                    window.set_title(&format!("Failed to save the preset - Press {} to save, ESC to re-select", config.save_hotkey));
+                                }
+                            }
+                            return Some(State::RegionSelected(img.clone(), cache.clone(), *region));
+                        }
+                    }
+                    return Some(State::PresetName(
+                        img.clone(),
+                        cache.clone(),
+                        *region,
+                        prompt,
+                    ));
+                }
+                Key::Backspace => {
+                    prompt.name.pop();
+                }
+                // 按住 Ctrl/Alt/Win 时为热键，否则为名称中的字符
+                key if modifiers.ctrl || modifiers.alt || modifiers.win => {
+                    let hotkey = config::Hotkey::from_key(modifiers.flags(), key)?;
+                    if let Some(used_by) = hotkey_conflict(hotkey, &prompt.name, config) {
+                        window.set_title(&format!(
+                            "{} is already {} - Press a different one, ESC to cancel",
+                            hotkey, used_by
+                        ));
+                        return None;
+                    }
+                    prompt.hotkey = Some(hotkey);
+                }
+                key => {
+                    let c = preset_name_char(key, modifiers.shift)?;
+                    if prompt.name.chars().count() < PRESET_NAME_MAX_CHARS {
+                        prompt.name.push(c);
+                    }
+                }
+            }
+            prompt.overwrite = false;
+            window.set_title(&preset_prompt_title(&prompt));
+            Some(State::PresetName(
+                img.clone(),
+                cache.clone(),
+                *region,
+                prompt,
+            ))
+        }
+        (AppEvent::KeyPressed(key), State::Viewer(viewer)) if viewer.confirm_delete => {
+            let mut viewer = viewer.clone();
+            if key == Key::Y && !viewer.delete_current() {
+                println!("No saved captures left");
+                window.set_position(
+                    -(primary_screen.display_info.width as isize * 2),
+                    -(primary_screen.display_info.height as isize * 2),
+                );
+                set_idle_title(window, config);
+                return Some(State::Idle);
+            }
+            viewer.confirm_delete = false;
+            window.set_title(&viewer.title());
+            Some(State::Viewer(viewer))
+        }
+        (
+            AppEvent::KeyPressed(key @ (Key::Left | Key::Right | Key::Delete)),
+            State::Viewer(viewer),
+        ) => {
+            let mut viewer = viewer.clone();
+            match key {
+                Key::Left => viewer.step(-1),
+                Key::Right => viewer.step(1),
+                _ => viewer.confirm_delete = true,
+            }
+            window.set_title(&viewer.title());
+            Some(State::Viewer(viewer))
+        }
+        (
+            AppEvent::KeyPressed(Key::Escape),
+            State::Countdown(..) | State::Gallery(_) | State::CaptureFailed(_) | State::Viewer(_),
+        ) => {
+            reset_session_for_idle(session);
+            window.set_position(
+                -(primary_screen.display_info.width as isize * 2),
+                -(primary_screen.display_info.height as isize * 2),
+            );
+            set_idle_title(window, config);
+            Some(State::Idle)
+        }
+        (
+            AppEvent::SaveHotkeyPressed | AppEvent::KeyPressed(Key::Space),
+            State::RegionSelected(img, cache, region),
+        ) => {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            save_selection(&frame, *region, None, None, primary_screen, config, session);
+            store_last_region(*region, config, session);
+            // stay_after_save：保留红框，可微调后再次保存
+            if config.stay_after_save {
+                window.set_title(STAY_AFTER_SAVE_TITLE);
+                return Some(State::RegionSelected(img.clone(), cache.clone(), *region));
+            }
+            Some(after_save(window, img, cache, config, keep_frame))
+        }
+        (
+            AppEvent::SaveHotkeyPressed | AppEvent::KeyPressed(Key::Space),
+            State::SubRegionSelected(img, cache, red_region, green_region),
+        ) => {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            // R 键切换为保存红框时不传绿框
+            let sub_region = (!session.save_red).then_some(*green_region);
+            save_selection(
+                &frame,
+                *red_region,
+                sub_region,
+                None,
+                primary_screen,
+                config,
+                session,
+            );
+            store_last_region(sub_region.unwrap_or(*red_region), config, session);
+            if config.stay_after_save {
+                window.set_title(STAY_AFTER_SAVE_SUB_TITLE);
+                return Some(State::SubRegionSelected(
+                    img.clone(),
+                    cache.clone(),
+                    *red_region,
+                    *green_region,
+                ));
+            }
+            Some(after_save(window, img, cache, config, keep_frame))
+        }
+        (AppEvent::SaveCompleted(saved), _) => {
+            if !matches!(state, State::Idle) {
+                match saved {
+                    Some(Saved::Written(file_name)) => {
+                        window.set_title(&format!("Saved {}", file_name))
+                    }
+                    Some(Saved::UploadedOnly(file_name)) => {
+                        window.set_title(&format!("Uploading {}", file_name))
+                    }
+                    Some(Saved::Duplicate(file_name)) => window.set_title(&format!(
+                        "Same as the previous save, not written again: {}",
+                        file_name
+                    )),
+                    None => window.set_title("Failed to save image"),
+                }
+            }
+            None
+        }
+        // 按屏幕截图：覆盖窗口移到该显示器并调整为其大小，截取该显示器（不显示画廊、不延时、不预选窗口）
+        (AppEvent::MonitorHotkeyPressed(index), State::Idle) => {
+            let Some(screen) = Screen::all()
+                .ok()
+                .and_then(|screens| screens.get(index).copied())
+            else {
+                eprintln!("No monitor {}", index + 1);
+                return None;
+            };
+            let (x, y, width, height) = screen_area(&screen, config);
+            session.quick = false;
+            session.preselect = None;
+            session.foreground_rect = if config.relative_to_window {
+                window_info::foreground_window_rect()
+            } else {
+                None
+            };
+            session.screen = Some(screen);
+            place_overlay(
+                window,
+                session,
+                (x as isize, y as isize, width as usize, height as usize),
+            );
+            Some(begin_capture(window, primary_screen, config, session))
+        }
+        (AppEvent::PresetHotkeyPressed(index), State::Idle) => {
+            if let Some(preset) = config.presets.get(index) {
+                save_preset(preset, config, session);
+            }
+            None
+        }
+        (AppEvent::CancelSaveHotkeyPressed, _) => {
+            if let Some(worker) = &session.save_worker {
+                worker.cancel();
+            }
+            None
+        }
+        // 最近保存文件的绝对路径复制为文本；还没有保存过时只提示
+        (AppEvent::CopyPathHotkeyPressed, _) => {
+            let last_saved = LAST_SAVED_PATH.lock().unwrap().clone();
+            match last_saved {
+                Some(path) => match clipboard::set_text(&path.to_string_lossy()) {
+                    Ok(()) => println!("Copied path: {}", path.display()),
+                    Err(e) => eprintln!("Failed to copy path: {}", e),
+                },
+                None => {
+                    println!("Nothing saved yet");
+                    notify::error_beep();
+                }
+            }
+            None
+        }
+        (AppEvent::SaveCancelled, _) => {
+            println!("Save cancelled");
+            if !matches!(state, State::Idle) {
+                window.set_title("Save cancelled");
+            }
+            None
+        }
+        (AppEvent::KeyPressed(key @ (Key::Left | Key::Right)), State::FullscreenCapture(_, _))
+            if !session.rewind.is_empty() =>
+        {
+            let last = session.rewind.len() - 1;
+            session.rewind_index = match key {
+                Key::Left => session.rewind_index.saturating_sub(1),
+                _ => (session.rewind_index + 1).min(last),
+            };
+            Some(show_rewind_frame(window, session, config))
+        }
+        (AppEvent::KeyPressed(Key::Tab), State::FullscreenCapture(img, cache)) => {
+            let windows =
+                pickable_windows(window, cache, session.overlay_origin, config.include_popups);
+            if windows.is_empty() {
+                window.set_title(
+                    "No windows to pick - Click and drag to select region, ESC to cancel",
+                );
+                return None;
+            }
+            // Shift+Tab 从最底层的窗口开始
+            let index = if modifiers.shift {
+                windows.len() - 1
+            } else {
+                0
+            };
+            set_window_pick_title(window, index, windows.len());
+            Some(State::WindowPick(
+                img.clone(),
+                cache.clone(),
+                windows,
+                index,
+            ))
+        }
+        (AppEvent::KeyPressed(Key::B), State::FullscreenCapture(img, cache)) => {
+            let elements = pickable_elements(
+                window,
+                cache,
+                session.overlay_origin,
+                config.pick_child_controls,
+            );
+            if elements.is_empty() {
+                window.set_title(
+                    "No windows to pick - Click and drag to select region, ESC to cancel",
+                );
+                return None;
+            }
+            window.set_title(ELEMENT_PICK_TITLE);
+            Some(State::ElementPick(
+                img.clone(),
+                cache.clone(),
+                elements,
+                None,
+            ))
+        }
+        (
+            AppEvent::MousePressed(MouseButton::Left, x, y),
+            State::ElementPick(img, cache, elements, first),
+        ) => {
+            let picked = element_at(elements, (x as i32, y as i32))?;
+            match first {
+                None => {
+                    let (px, py, pw, ph) = picked;
+                    window.set_title(&format!(
+                        "First element {}x{} at {},{} - Click the second element, ESC to undo",
+                        pw, ph, px, py
+                    ));
+                    Some(State::ElementPick(
+                        img.clone(),
+                        cache.clone(),
+                        elements.clone(),
+                        Some(picked),
+                    ))
+                }
+                Some(first) => {
+                    set_region_selected_title(window, config);
+                    Some(State::RegionSelected(
+                        img.clone(),
+                        cache.clone(),
+                        union_rect(*first, picked),
+                    ))
+                }
+            }
+        }
+        (AppEvent::KeyPressed(Key::Tab), State::WindowPick(img, cache, windows, index)) => {
+            let index = if modifiers.shift {
+                (index + windows.len() - 1) % windows.len()
+            } else {
+                (index + 1) % windows.len()
+            };
+            set_window_pick_title(window, index, windows.len());
+            Some(State::WindowPick(
+                img.clone(),
+                cache.clone(),
+                windows.clone(),
+                index,
+            ))
+        }
+        (
+            AppEvent::KeyPressed(Key::Enter) | AppEvent::SaveHotkeyPressed,
+            State::WindowPick(img, cache, windows, index),
+        ) => {
+            let picked = &windows[*index];
+            let region = picked.rect;
+            let title = Some(picked.title.clone()).filter(|title| !title.trim().is_empty());
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            save_selection(&frame, region, None, title, primary_screen, config, session);
+            store_last_region(region, config, session);
+            Some(after_save(window, img, cache, config, false))
+        }
+        // pan_and_scan：Ctrl+方向键每次平移窗口的四分之一（越界部分在下一帧收回）
+        (AppEvent::KeyPressed(key @ (Key::Left | Key::Right | Key::Up | Key::Down)), _)
+            if config.pan_and_scan
+                && modifiers.flags() == MOD_CONTROL as u32
+                && display_cache(state).is_some() =>
+        {
+            let (width, height) = window.get_size();
+            let (step_x, step_y) = (width as i32 / 4, height as i32 / 4);
+            let (dx, dy) = match key {
+                Key::Left => (-step_x, 0),
+                Key::Right => (step_x, 0),
+                Key::Up => (0, -step_y),
+                _ => (0, step_y),
+            };
+            session.viewport = (session.viewport.0 + dx, session.viewport.1 + dy);
+            None
+        }
+        // Z：在适合窗口和 1:1 显示之间切换（下一帧生效）
+        (AppEvent::KeyPressed(Key::Z), _) if display_cache(state).is_some() => {
+            session.fit_to_window = !session.fit_to_window;
+            None
+        }
+        // N：切换编号标记放置模式
+        (
+            AppEvent::KeyPressed(Key::N),
+            State::FullscreenCapture(..) | State::RegionSelected(..) | State::SubRegionSelected(..),
+        ) => {
+            session.stamp_mode = !session.stamp_mode;
+            if session.stamp_mode {
+                set_stamp_title(window, session);
+            } else {
+                window.set_title(&format!(
+                    "Stamps done - Continue selecting, {} to save, ESC to cancel",
+                    config.save_hotkey
+                ));
+            }
+            None
+        }
+        (
+            AppEvent::MousePressed(MouseButton::Left, x, y),
+            State::FullscreenCapture(..) | State::RegionSelected(..) | State::SubRegionSelected(..),
+        ) if session.stamp_mode => {
+            session.stamp_count += 1;
+            session.stamps.push(Stamp {
+                center: (x as i32, y as i32),
+                number: session.stamp_count,
+            });
+            set_stamp_title(window, session);
+            None
+        }
+        (AppEvent::KeyPressed(Key::Backspace), _) if session.stamp_mode => {
+            if session.stamps.pop().is_some() {
+                session.stamp_count -= 1;
+            }
+            set_stamp_title(window, session);
+            None
+        }
+        // 键盘框选：从截图中心开始，方向键移动光标，Enter 依次确定两个角
+        (AppEvent::KeyPressed(Key::K), State::FullscreenCapture(img, cache)) => {
+            session.region_locked = false;
+            set_keyboard_select_title(window, None);
+            let center = (cache.width as i32 / 2, cache.height as i32 / 2);
+            Some(State::KeyboardSelect(
+                img.clone(),
+                cache.clone(),
+                center,
+                None,
+            ))
+        }
+        (
+            AppEvent::KeyPressed(key @ (Key::Left | Key::Right | Key::Up | Key::Down)),
+            State::KeyboardSelect(img, cache, cursor, anchor),
+        ) => {
+            session.arrow_pressed_at = Some(Instant::now());
+            let step = if modifiers.shift { 10 } else { 1 };
+            let (dx, dy) = match key {
+                Key::Left => (-step, 0),
+                Key::Right => (step, 0),
+                Key::Up => (0, -step),
+                _ => (0, step),
+            };
+            let cursor = move_keyboard_cursor(*cursor, (dx, dy), cache);
+            Some(State::KeyboardSelect(
+                img.clone(),
+                cache.clone(),
+                cursor,
+                *anchor,
+            ))
+        }
+        // 按住方向键超过 KEY_REPEAT_DELAY 后每帧移动，速度随按住时间增加
+        (AppEvent::Tick, State::KeyboardSelect(img, cache, cursor, anchor)) => {
+            let pressed_at = session.arrow_pressed_at?;
+            let (mut dx, mut dy) = (0, 0);
+            for (key, (x, y)) in [
+                (Key::Left, (-1, 0)),
+                (Key::Right, (1, 0)),
+                (Key::Up, (0, -1)),
+                (Key::Down, (0, 1)),
+            ] {
+                if window.is_key_down(key) {
+                    dx += x;
+                    dy += y;
+                }
+            }
+            if (dx, dy) == (0, 0) {
+                session.arrow_pressed_at = None;
+                return None;
+            }
+            let held = pressed_at.elapsed().checked_sub(KEY_REPEAT_DELAY)?;
+            let step = (1 + held.as_millis() as i32 / 150).min(KEY_REPEAT_MAX_STEP)
+                * if modifiers.shift { 10 } else { 1 };
+            let cursor = move_keyboard_cursor(*cursor, (dx * step, dy * step), cache);
+            Some(State::KeyboardSelect(
+                img.clone(),
+                cache.clone(),
+                cursor,
+                *anchor,
+            ))
+        }
+        (AppEvent::KeyPressed(Key::Enter), State::KeyboardSelect(img, cache, cursor, None)) => {
+            set_keyboard_select_title(window, Some(*cursor));
+            Some(State::KeyboardSelect(
+                img.clone(),
+                cache.clone(),
+                *cursor,
+                Some(*cursor),
+            ))
+        }
+        (
+            AppEvent::KeyPressed(Key::Enter),
+            State::KeyboardSelect(img, cache, cursor, Some(anchor)),
+        ) => {
+            let region = (
+                anchor.0.min(cursor.0),
+                anchor.1.min(cursor.1),
+                (cursor.0 - anchor.0).abs(),
+                (cursor.1 - anchor.1).abs(),
+            );
+            if region.2 == 0 || region.3 == 0 {
+                window.set_title("The region is empty - Move the cursor away from the first corner, Enter to set the second corner");
+                return None;
+            }
+            session.arrow_pressed_at = None;
+            set_region_selected_title(window, config);
+            Some(State::RegionSelected(img.clone(), cache.clone(), region))
+        }
+        // Backspace 取消已确定的第一个角
+        (
+            AppEvent::KeyPressed(Key::Backspace),
+            State::KeyboardSelect(img, cache, cursor, Some(_)),
+        ) => {
+            set_keyboard_select_title(window, None);
+            Some(State::KeyboardSelect(
+                img.clone(),
+                cache.clone(),
+                *cursor,
+                None,
+            ))
+        }
+        (AppEvent::MousePressed(MouseButton::Left, x, y), State::FullscreenCapture(img, cache)) => {
+            session.region_locked = false;
+            let point = snap_point((x as i32, y as i32), cache, config, session);
+            session.selection_anchor = point;
+            Some(State::SelectingRegion(
+                img.clone(),
+                cache.clone(),
+                point,
+                point,
+            ))
+        }
+        (AppEvent::MouseMoved(x, y), State::SelectingRegion(img, cache, _, _)) => {
+            let point = snap_point((x as i32, y as i32), cache, config, session);
+            let anchor = session.selection_anchor;
+
+            // 按住 Alt：以按下点为中心对称扩展，半宽/半高不超过中心到截图边缘的距离
+            if modifiers.alt {
+                let dx = (point.0 - anchor.0)
+                    .abs()
+                    .min(anchor.0)
+                    .min(cache.width as i32 - anchor.0);
+                let dy = (point.1 - anchor.1)
+                    .abs()
+                    .min(anchor.1)
+                    .min(cache.height as i32 - anchor.1);
+                let (start, end) = (
+                    (anchor.0 - dx, anchor.1 - dy),
+                    (anchor.0 + dx, anchor.1 + dy),
+                );
+                return Some(State::SelectingRegion(
+                    img.clone(),
+                    cache.clone(),
+                    start,
+                    end,
+                ));
+            }
+            Some(State::SelectingRegion(
+                img.clone(),
+                cache.clone(),
+                anchor,
+                point,
+            ))
+        }
+        (
+            AppEvent::MouseReleased(MouseButton::Left, _x, _y),
+            State::SelectingRegion(img, cache, start, current),
+        ) => {
+            let width = (current.0 - start.0).abs() as u32;
+            let height = (current.1 - start.1).abs() as u32;
+
+            let region = if width > 10 && height > 10 {
+                (
+                    start.0.min(current.0),
+                    start.1.min(current.1),
+                    width as i32,
+                    height as i32,
+                )
+            } else {
+                // 拖动距离过小：按 small_drag_action 处理
+                match config.small_drag_action {
+                    SmallDragAction::Revert => {
+                        window.set_title(
+                            "Screen captured - Click and drag to select region, ESC to cancel",
+                        );
+                        return Some(State::FullscreenCapture(img.clone(), cache.clone()));
+                    }
+                    SmallDragAction::PickColor => {
+                        let frame = frame_to_save(window, img, primary_screen, config, session)?;
+                        pick_color(window, &frame, session.selection_anchor);
+                        return Some(State::FullscreenCapture(img.clone(), cache.clone()));
+                    }
+                    SmallDragAction::DefaultRegion => centered_region(
+                        config
+                            .magnet
+                            .then(|| {
+                                magnet_center(cache, session.selection_anchor, config.magnet_radius)
+                            })
+                            .flatten()
+                            .unwrap_or(session.selection_anchor),
+                        (config.small_drag_width, config.small_drag_height),
+                        (cache.width, cache.height),
+                    ),
+                }
+            };
+
+            // 快速截取：松开即复制到剪贴板或保存，然后隐藏窗口
+            if session.quick {
+                session.quick = false;
+                let frame = frame_to_save(window, img, primary_screen, config, session)?;
+                match config.quick_target {
+                    QuickTarget::Clipboard => {
+                        copy_image(window, &frame, region, None, config, session)
+                    }
+                    QuickTarget::File => {
+                        save_selection(&frame, region, None, None, primary_screen, config, session);
+                        store_last_region(region, config, session);
+                    }
+                }
+                window.set_position(-(img.width() as isize * 2), -(img.height() as isize * 2));
+                set_idle_title(window, config);
+                return Some(State::Idle);
+            }
+
+            // auto_save_on_release：松开即保存，不进入红框确认和绿框选择
+            if config.auto_save_on_release {
+                let frame = frame_to_save(window, img, primary_screen, config, session)?;
+                save_selection(&frame, region, None, None, primary_screen, config, session);
+                store_last_region(region, config, session);
+                return Some(after_save(window, img, cache, config, false));
+            }
+
+            if config.deferred_confirm {
+                window.set_title(
+                    "Drag the edges or corners to adjust, Enter to confirm, ESC to re-select",
+                );
+                return Some(State::EditingRegion(
+                    img.clone(),
+                    cache.clone(),
+                    region,
+                    None,
+                ));
+            }
+
+            set_region_selected_title(window, config);
+            Some(State::RegionSelected(img.clone(), cache.clone(), region))
+        }
+        (AppEvent::KeyPressed(Key::Enter), State::EditingRegion(img, cache, region, _)) => {
+            set_region_selected_title(window, config);
+            Some(State::RegionSelected(img.clone(), cache.clone(), *region))
+        }
+        (
+            AppEvent::MousePressed(MouseButton::Left, x, y),
+            State::EditingRegion(img, cache, region, _),
+        ) => {
+            let point = (x as i32, y as i32);
+            // 容差按屏幕像素计：缩小显示时换算为截图像素
+            let tolerance = (config.handle_grab_tolerance as f32 / session.fit_scale.unwrap_or(1.0))
+                .round() as i32;
+            match region_edges_at(*region, point, tolerance) {
+                Some(edges) => {
+                    let drag = RegionDrag {
+                        edges,
+                        origin: point,
+                        region: *region,
+                    };
+                    Some(State::EditingRegion(
+                        img.clone(),
+                        cache.clone(),
+                        *region,
+                        Some(drag),
+                    ))
+                }
+                // 点击在框外：重新框选
+                None => {
+                    let point = snap_point(point, cache, config, session);
+                    session.selection_anchor = point;
+                    Some(State::SelectingRegion(
+                        img.clone(),
+                        cache.clone(),
+                        point,
+                        point,
+                    ))
+                }
+            }
+        }
+        (AppEvent::MouseMoved(x, y), State::EditingRegion(img, cache, _, Some(drag))) => {
+            let point = snap_point((x as i32, y as i32), cache, config, session);
+            let region = adjust_region(drag, point);
+            Some(State::EditingRegion(
+                img.clone(),
+                cache.clone(),
+                region,
+                Some(*drag),
+            ))
+        }
+        (
+            AppEvent::MouseReleased(MouseButton::Left, _, _),
+            State::EditingRegion(img, cache, region, Some(_)),
+        ) => Some(State::EditingRegion(
+            img.clone(),
+            cache.clone(),
+            *region,
+            None,
+        )),
+        (AppEvent::KeyPressed(Key::L), State::RegionSelected(..)) => {
+            session.region_locked = !session.region_locked;
+            if session.region_locked {
+                window.set_title(&format!("[LOCKED] Region locked - Press {} to save, arrows to nudge, L to unlock, ESC to re-select", config.save_hotkey));
+            } else {
+                set_region_selected_title(window, config);
+            }
+            None
+        }
+        // 方向键微调红框位置（按住 Shift 每次 10 像素），不超出截图
+        (
+            AppEvent::KeyPressed(key @ (Key::Left | Key::Right | Key::Up | Key::Down)),
+            State::RegionSelected(img, cache, region),
+        ) => {
+            let step = if modifiers.shift { 10 } else { 1 };
+            let (dx, dy) = match key {
+                Key::Left => (-step, 0),
+                Key::Right => (step, 0),
+                Key::Up => (0, -step),
+                _ => (0, step),
+            };
+            let (x, y, w, h) = *region;
+            let x = (x + dx).clamp(0, (cache.width as i32 - w).max(0));
+            let y = (y + dy).clamp(0, (cache.height as i32 - h).max(0));
+            Some(State::RegionSelected(
+                img.clone(),
+                cache.clone(),
+                (x, y, w, h),
+            ))
+        }
+        // 锁定时忽略点击，只能保存、微调或 ESC
+        (
+            AppEvent::MousePressed(MouseButton::Left, x, y),
+            State::RegionSelected(img, cache, region),
+        ) if !session.region_locked => {
+            // 检查点击是否在红框内
+            if x as i32 >= region.0
+                && x as i32 <= region.0 + region.2
+                && y as i32 >= region.1
+                && y as i32 <= region.1 + region.3
+            {
+                Some(State::SelectingSubRegion(
+                    img.clone(),
+                    cache.clone(),
+                    *region,
+                    (x as i32, y as i32),
+                    (x as i32, y as i32),
+                ))
+            } else {
+                None // 点击在红框外，不处理
+            }
+        }
+        (AppEvent::MouseMoved(x, y), State::SelectingSubRegion(img, cache, red_region, start, _)) => {
+            // 限制绿框在红框内
+            let clamped_x = x.clamp(
+                red_region.0 as f32,
+                red_region.0 as f32 + red_region.2 as f32,
+            );
+            let clamped_y = y.clamp(
+                red_region.1 as f32,
+                red_region.1 as f32 + red_region.3 as f32,
+            );
+
+            Some(State::SelectingSubRegion(
+                img.clone(),
+                cache.clone(),
+                *red_region,
+                *start,
+                (clamped_x as i32, clamped_y as i32),
+            ))
+        }
+        (
+            AppEvent::MouseReleased(MouseButton::Left, _x, _y),
+            State::SelectingSubRegion(img, cache, red_region, start, current),
+        ) => {
+            let width = (current.0 - start.0).abs() as u32;
+            let height = (current.1 - start.1).abs() as u32;
+
+            if width > 5 && height > 5 {
+                let green_region = (
+                    start.0.min(current.0),
+                    start.1.min(current.1),
+                    width as i32,
+                    height as i32,
+                );
+
+                session.save_red = false;
+                set_sub_region_title(window, false, config);
+                Some(State::SubRegionSelected(
                     img.clone(),
                     cache.clone(),
                     *red_region,
                     green_region,
                 ))
             } else {
-                window.set_title("Region selected - Press Ctrl+S to save, or click and drag to select sub-region, ESC to re-select");
-                Some(State::RegionSelected(img.clone(), cache.clone(), *red_region))
-            }
+                set_region_selected_title(window, config);
+                Some(State::RegionSelected(img.clone(), cache.clone(), *red_region))
+            }
+        }
+        // 系统 OCR 可能要数百毫秒，在后台线程识别，结果以 TextDetected 事件返回
+        (AppEvent::KeyPressed(Key::T), State::RegionSelected(img, _, region))
+            if config.ocr_text_crop =>
+        {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            let events = session.events.clone()?;
+            window.set_title(&format!(
+                "Detecting text... - Press {} to save, ESC to re-select",
+                config.save_hotkey
+            ));
+            let region = *region;
+            std::thread::spawn(move || {
+                let text_box = propose_text_box(&frame, region).map_err(|e| e.to_string());
+                let _ = events.send(AppEvent::TextDetected(region, text_box));
+            });
+            None
+        }
+        // 识别期间红框已改变或已离开该状态时丢弃结果
+        (
+            AppEvent::TextDetected(detected_region, text_box),
+            State::RegionSelected(img, cache, region),
+        ) if detected_region == region => match text_box {
+            Ok(Some(text_box)) => {
+                window.set_title(
+                    "Text block found (blue) - Enter to crop to it, ESC to keep the region",
+                );
+                Some(State::TextBoxProposed(
+                    img.clone(),
+                    cache.clone(),
+                    *region,
+                    *text_box,
+                ))
+            }
+            Ok(None) => {
+                window.set_title(&format!(
+                    "No text found - Press {} to save, ESC to re-select",
+                    config.save_hotkey
+                ));
+                None
+            }
+            Err(e) => {
+                eprintln!("Text detection failed: {}", e);
+                window.set_title(&format!(
+                    "Text detection failed - Press {} to save, ESC to re-select",
+                    config.save_hotkey
+                ));
+                None
+            }
+        },
+        (
+            AppEvent::KeyPressed(Key::Enter),
+            State::TextBoxProposed(img, cache, red_region, text_box),
+        ) => {
+            session.save_red = false;
+            set_sub_region_title(window, false, config);
+            Some(State::SubRegionSelected(
+                img.clone(),
+                cache.clone(),
+                *red_region,
+                *text_box,
+            ))
+        }
+        (AppEvent::KeyPressed(Key::Escape), State::TextBoxProposed(img, cache, red_region, _)) => {
+            set_region_selected_title(window, config);
+            Some(State::RegionSelected(
+                img.clone(),
+                cache.clone(),
+                *red_region,
+            ))
+        }
+        // Shift+D 清除所有遮盖区域（放在 D 之前匹配）
+        (
+            AppEvent::KeyPressed(Key::D),
+            State::FullscreenCapture(..) | State::RegionSelected(..) | State::SubRegionSelected(..),
+        ) if modifiers.shift => {
+            session.redactions.clear();
+            window.set_title("Redactions cleared - Continue editing, ESC to cancel");
+            None
+        }
+        // D：把绿框加为遮盖区域（保存、复制、加入导出列表和 F 输出预览中涂黑），回到红框继续编辑
+        (
+            AppEvent::KeyPressed(Key::D),
+            State::SubRegionSelected(img, cache, red_region, green_region),
+        ) => {
+            session.redactions.push(*green_region);
+            session.save_red = false;
+            window.set_title(&format!(
+                "{} redaction(s) - Draw another green box and press D, F to preview the output, {} to save, Shift+D to clear",
+                session.redactions.len(),
+                config.save_hotkey
+            ));
+            Some(State::RegionSelected(
+                img.clone(),
+                cache.clone(),
+                *red_region,
+            ))
+        }
+        (AppEvent::KeyPressed(Key::R), State::SubRegionSelected(..)) => {
+            session.save_red = !session.save_red;
+            set_sub_region_title(window, session.save_red, config);
+            None
+        }
+        // X：按 coordinate_format 把要保存的框的坐标复制为文本
+        (AppEvent::KeyPressed(Key::X), State::RegionSelected(_, _, region)) => {
+            copy_coordinates_with_title(window, *region, config, session);
+            None
+        }
+        (
+            AppEvent::KeyPressed(Key::X),
+            State::SubRegionSelected(_, _, red_region, green_region),
+        ) => {
+            let region = if session.save_red {
+                *red_region
+            } else {
+                *green_region
+            };
+            copy_coordinates_with_title(window, region, config, session);
+            None
+        }
+        (AppEvent::KeyPressed(Key::U), State::RegionSelected(img, _, region)) => {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            copy_data_uri(window, &frame, *region, None, config, session);
+            None
+        }
+        (
+            AppEvent::KeyPressed(Key::U),
+            State::SubRegionSelected(img, _, red_region, green_region),
+        ) => {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            copy_data_uri(
+                window,
+                &frame,
+                *red_region,
+                Some(*green_region),
+                config,
+                session,
+            );
+            None
+        }
+        (AppEvent::KeyPressed(Key::P), State::RegionSelected(img, _, region))
+            if config.open_in_editor =>
+        {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            edit_selection(window, &frame, *region, None, config, session);
+            None
+        }
+        (
+            AppEvent::KeyPressed(Key::P),
+            State::SubRegionSelected(img, _, red_region, green_region),
+        ) if config.open_in_editor => {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            edit_selection(
+                window,
+                &frame,
+                *red_region,
+                Some(*green_region),
+                config,
+                session,
+            );
+            None
+        }
+        // 加入待导出列表，回到全屏状态继续在同一帧上选择
+        (AppEvent::KeyPressed(Key::A), State::RegionSelected(img, cache, region)) => {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            add_to_cart(
+                window,
+                &frame,
+                *region,
+                None,
+                primary_screen,
+                config,
+                session,
+            )?;
+            window.set_title(&format!(
+                "Added to export list ({}) - Select next region, E to export all, Backspace to remove last",
+                session.cart.len()
+            ));
+            Some(State::FullscreenCapture(img.clone(), cache.clone()))
+        }
+        (
+            AppEvent::KeyPressed(Key::A),
+            State::SubRegionSelected(img, cache, red_region, green_region),
+        ) => {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            // 与 Ctrl+S 相同：R 键切换为保存红框时不带绿框
+            let sub_region = (!session.save_red).then_some(*green_region);
+            add_to_cart(
+                window,
+                &frame,
+                *red_region,
+                sub_region,
+                primary_screen,
+                config,
+                session,
+            )?;
+            window.set_title(&format!(
+                "Added to export list ({}) - Select next region, E to export all, Backspace to remove last",
+                session.cart.len()
+            ));
+            Some(State::FullscreenCapture(img.clone(), cache.clone()))
+        }
+        // 只在全屏和红框状态下删除，其他状态的 Backspace 不会误删列表
+        (
+            AppEvent::KeyPressed(Key::Backspace),
+            State::FullscreenCapture(..) | State::RegionSelected(..),
+        ) => {
+            if session.cart.pop().is_some() {
+                window.set_title(&format!(
+                    "Removed last item, {} left in export list",
+                    session.cart.len()
+                ));
+            }
+            None
+        }
+        (AppEvent::KeyPressed(Key::E), _) if !matches!(state, State::Idle) => {
+            if !session.cart.is_empty() {
+                export_cart(&mut session.cart, config);
+                window.set_title(
+                    "Export list saved - Click and drag to select region, ESC to cancel",
+                );
+            }
+            None
+        }
+        (AppEvent::KeyPressed(Key::H), _) if !matches!(state, State::Idle) => {
+            session.show_histogram = !session.show_histogram;
+            None
+        }
+        (AppEvent::KeyPressed(Key::G), _) if !matches!(state, State::Idle) => {
+            session.loupe_grid = !session.loupe_grid;
+            None
+        }
+        (AppEvent::KeyPressed(Key::V), _) if !matches!(state, State::Idle) => {
+            let (x, y) = session.cursor;
+            if modifiers.shift {
+                if !session.guides_y.contains(&y) {
+                    session.guides_y.push(y);
+                }
+            } else if !session.guides_x.contains(&x) {
+                session.guides_x.push(x);
+            }
+            None
+        }
+        // W：红框展开到整个屏幕，再按 W 恢复；拖动中按下时以当前拖出的框为准
+        (AppEvent::KeyPressed(Key::W), State::SelectingRegion(img, cache, start, current)) => {
+            let region = (
+                start.0.min(current.0),
+                start.1.min(current.1),
+                (current.0 - start.0).abs(),
+                (current.1 - start.1).abs(),
+            );
+            toggle_monitor_region(window, img, cache, region, config, session)
+        }
+        (AppEvent::KeyPressed(Key::W), State::RegionSelected(img, cache, region)) => {
+            toggle_monitor_region(window, img, cache, *region, config, session)
+        }
+        (AppEvent::KeyPressed(Key::F), State::RegionSelected(img, cache, region)) => {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            let preview = output_preview(window, &frame, *region, None, config, session)?;
+            Some(State::OutputPreview(
+                img.clone(),
+                cache.clone(),
+                *region,
+                None,
+                preview,
+            ))
+        }
+        (
+            AppEvent::KeyPressed(Key::F),
+            State::SubRegionSelected(img, cache, red_region, green_region),
+        ) => {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            let preview = output_preview(
+                window,
+                &frame,
+                *red_region,
+                Some(*green_region),
+                config,
+                session,
+            )?;
+            Some(State::OutputPreview(
+                img.clone(),
+                cache.clone(),
+                *red_region,
+                Some(*green_region),
+                preview,
+            ))
+        }
+        // F 或 ESC 回到编辑
+        (
+            AppEvent::KeyPressed(Key::F | Key::Escape),
+            State::OutputPreview(img, cache, red_region, green_region, _),
+        ) => match green_region {
+            Some(green_region) => {
+                set_sub_region_title(window, session.save_red, config);
+                Some(State::SubRegionSelected(
+                    img.clone(),
+                    cache.clone(),
+                    *red_region,
+                    *green_region,
+                ))
+            }
+            None => {
+                set_region_selected_title(window, config);
+                Some(State::RegionSelected(
+                    img.clone(),
+                    cache.clone(),
+                    *red_region,
+                ))
+            }
+        },
+        (
+            AppEvent::SaveHotkeyPressed | AppEvent::KeyPressed(Key::Space),
+            State::OutputPreview(img, cache, red_region, green_region, _),
+        ) => {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            save_selection(
+                &frame,
+                *red_region,
+                *green_region,
+                None,
+                primary_screen,
+                config,
+                session,
+            );
+            store_last_region(green_region.unwrap_or(*red_region), config, session);
+            Some(after_save(window, img, cache, config, keep_frame))
+        }
+        // Shift+J 保存拼接的图像（放在 J 之前匹配）
+        (AppEvent::KeyPressed(Key::J), _) if !matches!(state, State::Idle) && modifiers.shift => {
+            save_appended(window, primary_screen, config, session);
+            None
+        }
+        (AppEvent::KeyPressed(Key::J), State::RegionSelected(img, cache, region)) => {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            append_selection(window, &frame, *region, None, config, session);
+            Some(State::FullscreenCapture(img.clone(), cache.clone()))
+        }
+        (
+            AppEvent::KeyPressed(Key::J),
+            State::SubRegionSelected(img, cache, red_region, green_region),
+        ) => {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            append_selection(
+                window,
+                &frame,
+                *red_region,
+                Some(*green_region),
+                config,
+                session,
+            );
+            Some(State::FullscreenCapture(img.clone(), cache.clone()))
+        }
+        // Ctrl+C 把选区按保存流程处理后以图像复制到剪贴板
+        (AppEvent::KeyPressed(Key::C), State::RegionSelected(img, _, region))
+            if modifiers.flags() == MOD_CONTROL as u32 =>
+        {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            copy_image(window, &frame, *region, None, config, session);
+            None
+        }
+        (
+            AppEvent::KeyPressed(Key::C),
+            State::SubRegionSelected(img, _, red_region, green_region),
+        ) if modifiers.flags() == MOD_CONTROL as u32 => {
+            let frame = frame_to_save(window, img, primary_screen, config, session)?;
+            copy_image(
+                window,
+                &frame,
+                *red_region,
+                Some(*green_region),
+                config,
+                session,
+            );
+            None
+        }
+        (AppEvent::KeyPressed(Key::C), _) if !matches!(state, State::Idle) => {
+            session.guides_x.clear();
+            session.guides_y.clear();
+            None
+        }
+        (
+            AppEvent::KeyPressed(
+                key @ (Key::Equal
+                | Key::NumPadPlus
+                | Key::Minus
+                | Key::NumPadMinus
+                | Key::LeftBracket
+                | Key::RightBracket),
+            ),
+            _,
+        ) if config.adjust_keys && !matches!(state, State::Idle) => {
+            let adjust = &mut session.adjust;
+            match key {
+                Key::Equal | Key::NumPadPlus => adjust.brightness += ADJUST_STEP,
+                Key::Minus | Key::NumPadMinus => adjust.brightness -= ADJUST_STEP,
+                Key::RightBracket => adjust.contrast += ADJUST_STEP,
+                _ => adjust.contrast -= ADJUST_STEP,
+            }
+            adjust.brightness = adjust.brightness.clamp(-ADJUST_LIMIT, ADJUST_LIMIT);
+            adjust.contrast = adjust.contrast.clamp(-ADJUST_LIMIT, ADJUST_LIMIT);
+            window.set_title(&format!(
+                "Brightness {:+}, contrast {:+}% - +/- and [ ] to adjust, ESC to reset",
+                adjust.brightness, adjust.contrast
+            ));
+            None
+        }
+        (AppEvent::KeyPressed(Key::O), _) if !matches!(state, State::Idle) => {
+            session.shape = match session.shape {
+                SelectionShape::Rectangle => SelectionShape::Ellipse,
+                SelectionShape::Ellipse => SelectionShape::Rectangle,
+            };
+            None
+        }
+        // 默认情况：不改变状态
+        _ => None,
+    }
+}
+
+// 按保存流程处理选区，编码为 data_uri_format 格式的 data URI 文本并复制到剪贴板
+fn copy_data_uri(
+    window: &mut Window,
+    img: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    region: (i32, i32, i32, i32),
+    sub_region: Option<(i32, i32, i32, i32)>,
+    config: &Config,
+    session: &Session,
+) {
+    let (x, y, w, h) = region;
+    let sub_region = sub_region.map(|(sx, sy, sw, sh)| (sx, sy, sw as u32, sh as u32));
+    let Some(image) = process_selection(
+        img,
+        (x, y, w as u32, h as u32),
+        sub_region,
+        session.shape,
+        session.adjust,
+        config,
+    ) else {
+        window.set_title("Failed to copy data URI: the selection is outside the capture");
+        return;
+    };
+
+    let encoded = match config.data_uri_format {
+        DataUriFormat::Webp => Ok(("image/webp", encode_webp(&image))),
+        DataUriFormat::Png => icc::encode_png(&image).map(|png| ("image/png", png)),
+    };
+    let copied = encoded.and_then(|(mime, data)| {
+        let uri = clipboard::data_uri(mime, &data);
+        clipboard::set_text(&uri).map(|_| uri.len())
+    });
+    match copied {
+        Ok(length) => window.set_title(&format!(
+            "Copied data URI ({} characters) to the clipboard",
+            length
+        )),
+        Err(e) => {
+            eprintln!("Failed to copy data URI: {}", e);
+            window.set_title("Failed to copy data URI");
+        }
+    }
+}
+
+// 按 coordinate_format 把选区坐标（按 coordinate_space 换算，与文件名一致）复制到剪贴板，返回复制的文本
+fn copy_coordinates(
+    region: (i32, i32, i32, i32),
+    config: &Config,
+    session: &Session,
+) -> Result<String, String> {
+    let (monitor, _) = region_monitor(region, session.overlay_origin);
+    let (dx, dy) = coordinate_offset(
+        region,
+        session.overlay_origin,
+        frame_offset(session, config),
+        config,
+    );
+    let (x, y, w, h) = region;
+    let text = format_coordinates(&config.coordinate_format, (x + dx, y + dy, w, h), monitor);
+    clipboard::set_text(&text).map(|_| text)
+}
+
+// X 键：复制选区坐标并在标题栏显示结果
+fn copy_coordinates_with_title(
+    window: &mut Window,
+    region: (i32, i32, i32, i32),
+    config: &Config,
+    session: &Session,
+) {
+    match copy_coordinates(region, config, session) {
+        Ok(text) => window.set_title(&format!("Copied {} to the clipboard", text)),
+        Err(e) => {
+            eprintln!("Failed to copy coordinates: {}", e);
+            window.set_title("Failed to copy coordinates");
+        }
+    }
+}
+
+// 填入 coordinate_format 中的坐标占位符（见 Config::coordinate_format），未知的占位符原样保留
+fn format_coordinates(format: &str, region: (i32, i32, i32, i32), monitor: usize) -> String {
+    let (x, y, w, h) = region;
+    let value = |name: &str| {
+        let value = match name {
+            "x" => x as i64,
+            "y" => y as i64,
+            "w" | "width" => w as i64,
+            "h" | "height" => h as i64,
+            "r" | "right" => x as i64 + w as i64,
+            "b" | "bottom" => y as i64 + h as i64,
+            "m" | "monitor" => monitor as i64,
+            _ => return None,
+        };
+        Some(value.to_string())
+    };
+
+    let mut text = String::new();
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        if let Some(tail) = rest.strip_prefix("%%") {
+            text.push('%');
+            rest = tail;
+            continue;
+        }
+        if let Some(tail) = rest.strip_prefix('%')
+            && let Some(value) = tail.get(..1).and_then(value)
+        {
+            text += &value;
+            rest = &tail[1..];
+            continue;
+        }
+        if let Some(tail) = rest.strip_prefix('{')
+            && let Some((name, tail)) = tail.split_once('}')
+            && let Some(value) = value(name)
+        {
+            text += &value;
+            rest = tail;
+            continue;
+        }
+        text.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    text
+}
+
+// 取截图中一个像素的颜色，以 #RRGGBB 复制到剪贴板并显示在标题栏
+fn pick_color(window: &mut Window, img: &ImageBuffer<Rgba<u8>, Vec<u8>>, point: (i32, i32)) {
+    let (x, y) = point;
+    if x < 0 || y < 0 || x as u32 >= img.width() || y as u32 >= img.height() {
+        return;
+    }
+    let [r, g, b, _] = img.get_pixel(x as u32, y as u32).0;
+    let hex = format!("#{:02X}{:02X}{:02X}", r, g, b);
+    let copied = match clipboard::set_text(&hex) {
+        Ok(()) => " copied",
+        Err(e) => {
+            eprintln!("Failed to copy color: {}", e);
+            ""
+        }
+    };
+    window.set_title(&format!(
+        "{} at ({}, {}){} - Click and drag to select region, ESC to cancel",
+        hex, x, y, copied
+    ));
+}
+
+// 以 center 为中心、大小为 size 的区域，超出截图时平移到截图内（截图更小时取整张截图）
+fn centered_region(
+    center: (i32, i32),
+    size: (u32, u32),
+    bounds: (u32, u32),
+) -> (i32, i32, i32, i32) {
+    let width = size.0.clamp(1, bounds.0.max(1)) as i32;
+    let height = size.1.clamp(1, bounds.1.max(1)) as i32;
+    let x = (center.0 - width / 2).clamp(0, (bounds.0 as i32 - width).max(0));
+    let y = (center.1 - height / 2).clamp(0, (bounds.1 as i32 - height).max(0));
+    (x, y, width, height)
+}
+
+// 磁吸：在 point 周围 radius 像素的窗口内找亮度梯度超过阈值的边缘像素，从离 point 最近的边缘像素出发，
+// 把相距不超过 MAGNET_GAP 的边缘像素连成一个特征，返回其外接框的中心；窗口内没有边缘时为 None
+fn magnet_center(cache: &DisplayCache, point: (i32, i32), radius: u32) -> Option<(i32, i32)> {
+    let (width, height) = (cache.width as i32, cache.height as i32);
+    let r = radius as i32;
+    let (left, top) = ((point.0 - r).max(1), (point.1 - r).max(1));
+    let (right, bottom) = ((point.0 + r).min(width - 2), (point.1 + r).min(height - 2));
+    if right < left || bottom < top {
+        return None;
+    }
+    let (w, h) = ((right - left + 1) as usize, (bottom - top + 1) as usize);
+
+    let luma = |x: i32, y: i32| {
+        let argb = cache.original_buffer[(y * width + x) as usize];
+        (((argb >> 16) & 0xFF) * 299 + ((argb >> 8) & 0xFF) * 587 + (argb & 0xFF) * 114) as i32
+            / 1000
+    };
+    let mut edges = vec![false; w * h];
+    let mut nearest: Option<(usize, i32)> = None;
+    for y in top..=bottom {
+        for x in left..=right {
+            let gradient = (luma(x + 1, y) - luma(x - 1, y))
+                .abs()
+                .max((luma(x, y + 1) - luma(x, y - 1)).abs());
+            if gradient < MAGNET_THRESHOLD {
+                continue;
+            }
+            let idx = (y - top) as usize * w + (x - left) as usize;
+            edges[idx] = true;
+            let distance = (x - point.0).pow(2) + (y - point.1).pow(2);
+            if nearest.is_none_or(|(_, best)| distance < best) {
+                nearest = Some((idx, distance));
+            }
+        }
+    }
+
+    // 从最近的边缘像素开始填充连通的边缘像素，记录外接框
+    let (start, _) = nearest?;
+    let mut bounds = (w, h, 0, 0);
+    let mut stack = vec![start];
+    edges[start] = false;
+    while let Some(idx) = stack.pop() {
+        let (x, y) = (idx % w, idx / w);
+        bounds = (
+            bounds.0.min(x),
+            bounds.1.min(y),
+            bounds.2.max(x),
+            bounds.3.max(y),
+        );
+        for ny in y.saturating_sub(MAGNET_GAP)..(y + MAGNET_GAP + 1).min(h) {
+            for nx in x.saturating_sub(MAGNET_GAP)..(x + MAGNET_GAP + 1).min(w) {
+                let neighbor = ny * w + nx;
+                if edges[neighbor] {
+                    edges[neighbor] = false;
+                    stack.push(neighbor);
+                }
+            }
+        }
+    }
+    Some((
+        left + ((bounds.0 + bounds.2) / 2) as i32,
+        top + ((bounds.1 + bounds.3) / 2) as i32,
+    ))
+}
+
+// 空闲（覆盖窗口隐藏）时的标题，显示配置的截图热键
+fn set_idle_title(window: &mut Window, config: &Config) {
+    window.set_title(&format!(
+        "Screen Capture - Press {} to capture screen, ESC to exit",
+        config.capture_hotkey
+    ));
+}
+
+// 红框已确定时的标题，显示配置的保存热键
+fn set_region_selected_title(window: &mut Window, config: &Config) {
+    window.set_title(&format!(
+        "Region selected - Press {} to save (Space: save and stay), or click and drag to select sub-region, ESC to re-select",
+        config.save_hotkey
+    ));
+}
+
+// 绿框已确定时的标题，标明保存热键保存的是哪个框
+fn set_sub_region_title(window: &mut Window, save_red: bool, config: &Config) {
+    if save_red {
+        window.set_title(&format!(
+            "[SAVE RED] Sub-region selected - {} saves the red box (Space: save and stay), R to save the green box, ESC to re-select",
+            config.save_hotkey
+        ));
+    } else {
+        window.set_title(&format!(
+            "Sub-region selected - Press {} to save (Space: save and stay), R to save the red box instead, ESC to re-select",
+            config.save_hotkey
+        ));
+    }
+}
+
+// 键盘框选的标题：提示下一步要确定的角，已确定第一个角时显示其坐标
+fn set_keyboard_select_title(window: &mut Window, anchor: Option<(i32, i32)>) {
+    match anchor {
+        None => window.set_title(
+            "Keyboard select - Arrows move the cursor (Shift: 10px, hold to speed up), Enter sets the first corner, ESC to cancel",
+        ),
+        Some((x, y)) => window.set_title(&format!(
+            "Keyboard select - First corner at {},{} - Arrows move, Enter sets the second corner, Backspace to undo, ESC to cancel",
+            x, y
+        )),
+    }
+}
+
+// 移动键盘框选的光标，限制在截图范围内（可到达右、下边缘，以便框选到最后一列/行）
+fn move_keyboard_cursor(cursor: (i32, i32), delta: (i32, i32), cache: &DisplayCache) -> (i32, i32) {
+    (
+        (cursor.0 + delta.0).clamp(0, cache.width as i32),
+        (cursor.1 + delta.1).clamp(0, cache.height as i32),
+    )
+}
+
+// 按保存流程处理选区，按 append_direction 拼接到已拼接的图像上
+fn append_selection(
+    window: &mut Window,
+    img: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    region: (i32, i32, i32, i32),
+    sub_region: Option<(i32, i32, i32, i32)>,
+    config: &Config,
+    session: &mut Session,
+) {
+    let (x, y, w, h) = region;
+    let sub = sub_region.map(|(sx, sy, sw, sh)| (sx, sy, sw as u32, sh as u32));
+    let Some(image) = process_selection(
+        img,
+        (x, y, w as u32, h as u32),
+        sub,
+        session.shape,
+        session.adjust,
+        config,
+    ) else {
+        window.set_title("Failed to append: the selection is outside the capture");
+        return;
+    };
+
+    let vertical = config.append_direction == AppendDirection::Vertical;
+    let combined = match session.appended.take() {
+        Some(base) => transform::append(&base, &image, vertical),
+        None => image,
+    };
+    session.appended_count += 1;
+    let count = session.appended_count;
+    window.set_title(&format!(
+        "Appended {} selections ({}x{}) - Select the next region and press J, Shift+J to save the combined image",
+        count,
+        combined.width(),
+        combined.height()
+    ));
+    session.appended = Some(combined);
+}
+
+// 保存拼接的图像（文件名带 _append{数量} 后缀）并清空
+fn save_appended(
+    window: &mut Window,
+    primary_screen: &Screen,
+    config: &Config,
+    session: &mut Session,
+) {
+    let count = session.appended_count;
+    let Some(combined) = session.appended.take() else {
+        window.set_title("Nothing appended yet - Select a region and press J");
+        return;
+    };
+    let (width, height) = combined.dimensions();
+    let extension = config.save_format.extension();
+    let file_name = format!(
+        "{}_append{}.{}",
+        output_file_name(
+            &NameContext {
+                region: (0, 0, width, height),
+                screen: (
+                    primary_screen.display_info.width,
+                    primary_screen.display_info.height
+                ),
+                title: None,
+                ext: extension,
+                dpi: None,
+                captured_at: None,
+            },
+            config,
+        ),
+        count,
+        extension
+    );
+    // 按 save_format 编码，与普通保存相同
+    let written = encode_selection(&combined, &[], config).is_some_and(|encoded| {
+        write_encoded(&encoded, &file_name, (0, 0, width as i32, height as i32))
+    });
+    if written {
+        remember_saved_path(&file_name);
+        window.set_title(&format!("Saved {}", file_name));
+        session.appended_count = 0;
+    } else {
+        window.set_title("Failed to save the combined image");
+        session.appended = Some(combined);
+    }
+}
+
+// 按保存流程生成最终输出用于预览，超过截图 90% 时按比例缩小
+fn output_preview(
+    window: &mut Window,
+    img: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    region: (i32, i32, i32, i32),
+    sub_region: Option<(i32, i32, i32, i32)>,
+    config: &Config,
+    session: &Session,
+) -> Option<Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>> {
+    let (x, y, w, h) = region;
+    let sub = sub_region.map(|(sx, sy, sw, sh)| (sx, sy, sw as u32, sh as u32));
+    let Some(output) = process_selection(
+        img,
+        (x, y, w as u32, h as u32),
+        sub,
+        session.shape,
+        session.adjust,
+        config,
+    ) else {
+        window.set_title("Nothing to preview: the selection is outside the capture");
+        return None;
+    };
+
+    let (max_width, max_height) = (img.width() as f32 * 0.9, img.height() as f32 * 0.9);
+    let ratio = (max_width / output.width() as f32)
+        .min(max_height / output.height() as f32)
+        .min(1.0);
+    window.set_title(&format!(
+        "Output preview {}x{} ({:.0}%) - F or ESC to return, {} to save",
+        output.width(),
+        output.height(),
+        ratio * 100.0,
+        config.save_hotkey
+    ));
+    Some(Arc::new(transform::scale(&output, ratio)))
+}
+
+// 按保存流程处理选区，以 clipboard_formats 中的格式复制到剪贴板
+fn copy_image(
+    window: &mut Window,
+    img: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    region: (i32, i32, i32, i32),
+    sub_region: Option<(i32, i32, i32, i32)>,
+    config: &Config,
+    session: &Session,
+) {
+    let (x, y, w, h) = region;
+    let sub = sub_region.map(|(sx, sy, sw, sh)| (sx, sy, sw as u32, sh as u32));
+    let Some(image) = process_selection(
+        img,
+        (x, y, w as u32, h as u32),
+        sub,
+        session.shape,
+        session.adjust,
+        config,
+    ) else {
+        window.set_title("Failed to copy image: the selection is outside the capture");
+        return;
+    };
+    match clipboard::set_image(&image, &config.clipboard_formats) {
+        Ok(()) => window.set_title(&format!(
+            "Copied {}x{} image to the clipboard",
+            image.width(),
+            image.height()
+        )),
+        Err(e) => {
+            eprintln!("Failed to copy image: {}", e);
+            window.set_title("Failed to copy image");
+        }
+    }
+}
+
+// 按保存流程处理选区，写入临时目录后用系统默认编辑器打开
+fn edit_selection(
+    window: &mut Window,
+    img: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    region: (i32, i32, i32, i32),
+    sub_region: Option<(i32, i32, i32, i32)>,
+    config: &Config,
+    session: &Session,
+) {
+    let (x, y, w, h) = region;
+    let sub = sub_region.map(|(sx, sy, sw, sh)| (sx, sy, sw as u32, sh as u32));
+    let Some(image) = process_selection(
+        img,
+        (x, y, w as u32, h as u32),
+        sub,
+        session.shape,
+        session.adjust,
+        config,
+    ) else {
+        window.set_title("Failed to open in editor: the selection is outside the capture");
+        return;
+    };
+
+    let path = std::env::temp_dir().join(format!(
+        "captrue_{}.webp",
+        chrono::Local::now().format("%Y%m%d_%H%M%S_%3f")
+    ));
+    let file_name = path.display().to_string();
+    // 临时文件不是保存结果：直接写入，不打印保存信息也不触发保存回调
+    if let Err(e) = std::fs::write(&path, encode_webp(&image)) {
+        eprintln!("Failed to write {}: {}", file_name, e);
+        window.set_title("Failed to write the temporary file");
+        return;
+    }
+    match editor::open(&path) {
+        Ok(()) => window.set_title(&format!("Opened {} in the default editor", file_name)),
+        Err(e) => {
+            eprintln!("{}", e);
+            window.set_title("Failed to open in editor");
+        }
+    }
+}
+
+// 可供 Tab 选择的顶层窗口：换算为截图坐标并裁剪到截图范围内，完全在截图外的窗口跳过
+fn pickable_windows(
+    window: &Window,
+    cache: &DisplayCache,
+    origin: (isize, isize),
+    include_popups: bool,
+) -> Vec<TopLevelWindow> {
+    window_info::top_level_windows(window.get_window_handle() as _, include_popups)
+        .into_iter()
+        .filter_map(|picked| {
+            clip_to_overlay(picked.rect, cache, origin)
+                .map(|rect| TopLevelWindow { rect, ..picked })
+        })
+        .collect()
+}
+
+// 可点选的界面元素，矩形换算为截图坐标；顶层窗口在截图范围外的整个跳过，子控件在范围外的丢弃
+fn pickable_elements(
+    window: &Window,
+    cache: &DisplayCache,
+    origin: (isize, isize),
+    children: bool,
+) -> Vec<UiElement> {
+    window_info::ui_elements(window.get_window_handle() as _, children)
+        .into_iter()
+        .filter_map(|element| {
+            Some(UiElement {
+                rect: clip_to_overlay(element.rect, cache, origin)?,
+                children: element
+                    .children
+                    .into_iter()
+                    .filter_map(|rect| clip_to_overlay(rect, cache, origin))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+// 点所在的界面元素：Z 序最上层包含该点的顶层窗口中，包含该点的最小子控件，没有则为窗口本身
+fn element_at(elements: &[UiElement], point: (i32, i32)) -> Option<(i32, i32, i32, i32)> {
+    let contains = |&(x, y, w, h): &(i32, i32, i32, i32)| {
+        point.0 >= x && point.0 < x + w && point.1 >= y && point.1 < y + h
+    };
+    let element = elements.iter().find(|element| contains(&element.rect))?;
+    element
+        .children
+        .iter()
+        .copied()
+        .filter(contains)
+        .min_by_key(|&(_, _, w, h)| w as i64 * h as i64)
+        .or(Some(element.rect))
+}
+
+// 两个矩形的外接矩形
+fn union_rect(a: (i32, i32, i32, i32), b: (i32, i32, i32, i32)) -> (i32, i32, i32, i32) {
+    let (left, top) = (a.0.min(b.0), a.1.min(b.1));
+    let (right, bottom) = ((a.0 + a.2).max(b.0 + b.2), (a.1 + a.3).max(b.1 + b.3));
+    (left, top, right - left, bottom - top)
+}
+
+// 屏幕矩形换算为覆盖层坐标并裁到截图范围内，完全在范围外时返回 None
+fn clip_to_overlay(
+    rect: (i32, i32, i32, i32),
+    cache: &DisplayCache,
+    origin: (isize, isize),
+) -> Option<(i32, i32, i32, i32)> {
+    let (x, y, w, h) = rect;
+    let left = (x - origin.0 as i32).max(0);
+    let top = (y - origin.1 as i32).max(0);
+    let right = (x - origin.0 as i32 + w).min(cache.width as i32);
+    let bottom = (y - origin.1 as i32 + h).min(cache.height as i32);
+    (right > left && bottom > top).then_some((left, top, right - left, bottom - top))
+}
+
+fn set_stamp_title(window: &mut Window, session: &Session) {
+    window.set_title(&format!(
+        "Step stamps - Click to place #{}, Backspace to undo, N when done",
+        session.stamp_count + 1
+    ));
+}
+
+fn set_window_pick_title(window: &mut Window, index: usize, count: usize) {
+    window.set_title(&format!(
+        "Window {}/{} - Tab/Shift+Tab to cycle, Enter to save, ESC to cancel",
+        index + 1,
+        count
+    ));
+}
+
+// 在红框内识别文字，返回最大文字块（屏幕坐标，限制在红框内）
+fn propose_text_box(
+    img: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    region: (i32, i32, i32, i32),
+) -> windows::core::Result<Option<(i32, i32, i32, i32)>> {
+    let (x, y, w, h) = region;
+    let Some(cropped) = crop_selection(img, (x, y, w as u32, h as u32), None) else {
+        return Ok(None);
+    };
+    let lines = ocr::text_lines(&cropped)?;
+
+    Ok(ocr::largest_block(&lines).map(|(bx, by, bw, bh)| {
+        let left = (x + bx).max(x);
+        let top = (y + by).max(y);
+        let right = (x + bx + bw).min(x + w);
+        let bottom = (y + by + bh).min(y + h);
+        (left, top, right - left, bottom - top)
+    }))
+}
+
+// 点击位置落在红框的哪些边上（距边不超过 tolerance）；框内非边缘处为整体移动，框外返回 None
+fn region_edges_at(region: (i32, i32, i32, i32), point: (i32, i32), tolerance: i32) -> Option<u8> {
+    let (x, y, w, h) = region;
+    let (px, py) = point;
+    if px < x - tolerance || px > x + w + tolerance || py < y - tolerance || py > y + h + tolerance
+    {
+        return None;
+    }
+
+    let mut edges = 0;
+    if (px - x).abs() <= tolerance {
+        edges |= EDGE_LEFT;
+    } else if (px - (x + w)).abs() <= tolerance {
+        edges |= EDGE_RIGHT;
+    }
+    if (py - y).abs() <= tolerance {
+        edges |= EDGE_TOP;
+    } else if (py - (y + h)).abs() <= tolerance {
+        edges |= EDGE_BOTTOM;
+    }
+    if edges == 0 {
+        edges = EDGE_LEFT | EDGE_TOP | EDGE_RIGHT | EDGE_BOTTOM;
+    }
+    Some(edges)
+}
+
+// 按拖动位移调整红框，拖过对边时自动翻转
+fn adjust_region(drag: &RegionDrag, point: (i32, i32)) -> (i32, i32, i32, i32) {
+    let (dx, dy) = (point.0 - drag.origin.0, point.1 - drag.origin.1);
+    let (x, y, w, h) = drag.region;
+    let (mut left, mut top, mut right, mut bottom) = (x, y, x + w, y + h);
+    if drag.edges & EDGE_LEFT != 0 {
+        left += dx;
+    }
+    if drag.edges & EDGE_RIGHT != 0 {
+        right += dx;
+    }
+    if drag.edges & EDGE_TOP != 0 {
+        top += dy;
+    }
+    if drag.edges & EDGE_BOTTOM != 0 {
+        bottom += dy;
+    }
+    (
+        left.min(right),
+        top.min(bottom),
+        (right - left).abs().max(1),
+        (bottom - top).abs().max(1),
+    )
+}
+
+// 拖动点吸附：先吸附到参考线，再吸附到屏幕边缘
+fn snap_point(
+    point: (i32, i32),
+    cache: &DisplayCache,
+    config: &Config,
+    session: &Session,
+) -> (i32, i32) {
+    let point = snap_to_guides(
+        point,
+        &session.guides_x,
+        &session.guides_y,
+        config.edge_snap_px as i32,
+    );
+    snap_to_edges(point, cache.width, cache.height, config)
+}
+
+// 参考线吸附：距离最近的参考线不超过 distance 时吸附到该线
+fn snap_to_guides(
+    point: (i32, i32),
+    guides_x: &[i32],
+    guides_y: &[i32],
+    distance: i32,
+) -> (i32, i32) {
+    let axis = |value: i32, guides: &[i32]| {
+        guides
+            .iter()
+            .copied()
+            .filter(|guide| (guide - value).abs() <= distance)
+            .min_by_key(|guide| (guide - value).abs())
+            .unwrap_or(value)
+    };
+    (axis(point.0, guides_x), axis(point.1, guides_y))
+}
+
+// 屏幕边缘吸附：靠近左/上边缘吸附到 0，靠近右/下边缘吸附到宽/高（选框可包含最后一行/列像素）
+fn snap_to_edges(point: (i32, i32), width: u32, height: u32, config: &Config) -> (i32, i32) {
+    if !config.edge_snap {
+        return point;
+    }
+    let snap = config.edge_snap_px as i32;
+    let axis = |value: i32, max: i32| {
+        if value <= snap {
+            0
+        } else if value >= max - 1 - snap {
+            max
+        } else {
+            value
+        }
+    };
+    (axis(point.0, width as i32), axis(point.1, height as i32))
+}
+
+// 截取屏幕（覆盖窗口未排除在截图之外时先隐藏），成功后显示全屏截图
+fn begin_capture(
+    window: &mut Window,
+    primary_screen: &Screen,
+    config: &Config,
+    session: &mut Session,
+) -> State {
+    // 每次截图从未调整的状态开始，不带上次的编号标记
+    session.adjust = Adjustment::default();
+    session.stamp_mode = false;
+    session.stamps.clear();
+    session.redactions.clear();
+    session.expanded_from = None;
+    if config.stamp_reset_per_capture {
+        session.stamp_count = 0;
+    }
+
+    // live 覆盖模式：不截图，直接在实时桌面上显示遮罩；占位图像全透明，保存时才截图
+    if config.overlay_mode == OverlayMode::Live {
+        window.set_position(session.overlay_origin.0, session.overlay_origin.1);
+        window.set_title("Live overlay - Click and drag to select region, ESC to cancel");
+        let (width, height) = window.get_size();
+        let placeholder = ImageBuffer::new(width as u32, height as u32);
+        return State::FullscreenCapture(
+            Arc::new(placeholder),
+            DisplayCache::live(width as u32, height as u32),
+        );
+    }
+
+    session.captured_at = None;
+    session.frame_source = None;
+    match grab_screen(window, primary_screen, config, session) {
+        Ok((captured_at, image_buffer)) => {
+            session.captured_at = Some(captured_at);
+            session.frame_source = frame_source(config, session);
+            // 有预截图时从最早的一帧开始，左右方向键切换
+            // 预截图只截取默认目标，按屏幕截图时不提供回看
+            session.rewind = match &session.precapture {
+                Some(precapture) if session.screen.is_none() => precapture.snapshot(),
+                _ => Vec::new(),
+            };
+            if !session.rewind.is_empty() {
+                session.rewind.push((captured_at, image_buffer));
+                session.rewind_index = 0;
+                let state = show_rewind_frame(window, session, config);
+                if config.annotate_first && !session.quick {
+                    session.stamp_mode = true;
+                    set_stamp_title(window, session);
+                }
+                return state;
+            }
+
+            let cache = DisplayCache::new(&image_buffer, config.dim_tint, config.dim_tint_strength);
+            if config.live_preview {
+                // 覆盖窗口会被截进画面时无法实时预览，直接冻结
+                if session.capture_excluded {
+                    window.set_title(
+                        "Live preview - Press Space/Enter or click to freeze, ESC to cancel",
+                    );
+                    return State::LivePreview(image_buffer, cache, captured_at);
+                }
+                eprintln!(
+                    "Live preview needs the overlay to be excluded from capture (Windows 10 2004 or later), freezing immediately"
+                );
+            }
+
+            // annotate_first：先在整帧上标注，不预选窗口（快速截取直接框选，不进入标注）
+            if config.annotate_first && !session.quick {
+                session.preselect = None;
+                session.stamp_mode = true;
+                set_stamp_title(window, session);
+                return State::FullscreenCapture(image_buffer, cache);
+            }
+
+            if let Some(region) = session
+                .preselect
+                .take()
+                .and_then(|rect| clip_to_overlay(rect, &cache, session.overlay_origin))
+            {
+                window.set_title(&format!("Foreground window selected - Press {} to save (Space: save and stay), or click and drag to select sub-region, ESC to start blank", config.save_hotkey));
+                return State::RegionSelected(image_buffer, cache, region);
+            }
+
+            window.set_title("Screen captured - Click and drag to select region, ESC to cancel");
+            State::FullscreenCapture(image_buffer, cache)
+        }
+        // 覆盖窗口留在原处显示错误，等待重试或取消
+        Err(e) => {
+            eprintln!("Failed to capture screen: {}", e);
+            window.set_title(&format!(
+                "Capture failed: {} - Press any key to retry, ESC to cancel",
+                e
+            ));
+            State::CaptureFailed(e.to_string())
+        }
+    }
+}
+
+// 截图目标当前的桌面范围：按屏幕截图时为该显示器（按 id 查找），capture_all_screens 时为整个虚拟桌面，
+// 否则为主屏；work_area_only 时为工作区。查询失败或显示器已不存在时返回 None
+fn frame_source(config: &Config, session: &Session) -> Option<(i32, i32, u32, u32)> {
+    let screens = Screen::all().ok()?;
+    match &session.screen {
+        Some(screen) => screens
+            .iter()
+            .find(|current| current.display_info.id == screen.display_info.id)
+            .map(|current| screen_area(current, config)),
+        None if config.capture_all_screens => {
+            (!screens.is_empty()).then(|| desktop::bounds(&screens))
+        }
+        None => screens.first().map(|primary| screen_area(primary, config)),
+    }
+}
+
+// 截屏：覆盖窗口未排除在截图之外时先移出屏幕，截图后放回 overlay_origin；同时返回开始截取的时刻
+fn grab_screen(
+    window: &mut Window,
+    primary_screen: &Screen,
+    config: &Config,
+    session: &Session,
+) -> Result<precapture::Frame, Box<dyn std::error::Error>> {
+    if !session.capture_excluded {
+        window.set_position(
+            -(primary_screen.display_info.width as isize * 2),
+            -(primary_screen.display_info.height as isize * 2),
+        );
+
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+
+    let captured_at = Instant::now();
+    let captured = metrics::time(Metric::Capture, || {
+        capture_target(primary_screen, config, session)
+    });
+    window.set_position(session.overlay_origin.0, session.overlay_origin.1);
+    captured.map(|image| (captured_at, Arc::new(image)))
+}
+
+// 保存/识别所用的图像：冻结模式为冻结的截图，live 覆盖模式此时才截图，失败时返回 None；
+// 放置了编号标记时返回画上标记的副本
+fn frame_to_save(
+    window: &mut Window,
+    img: &Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+    primary_screen: &Screen,
+    config: &Config,
+    session: &Session,
+) -> Option<Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>> {
+    let frame = if config.overlay_mode == OverlayMode::Frozen {
+        img.clone()
+    } else {
+        match grab_screen(window, primary_screen, config, session) {
+            Ok((_, image)) => image,
+            Err(e) => {
+                eprintln!("Failed to capture screen: {}", e);
+                window.set_title("Failed to capture screen - ESC to cancel");
+                return None;
+            }
+        }
+    };
+    if session.stamps.is_empty() && session.redactions.is_empty() {
+        return Some(frame);
+    }
+    let mut edited = (*frame).clone();
+    for &rect in &session.redactions {
+        fill_redaction(&mut edited, rect);
+    }
+    stamp::bake(
+        &mut edited,
+        &session.stamps,
+        config.stamp_radius,
+        config.stamp_color,
+    );
+    Some(Arc::new(edited))
+}
+
+// 把遮盖区域涂成不透明的黑色，超出图像的部分忽略
+fn fill_redaction(image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, rect: (i32, i32, i32, i32)) {
+    let (x, y, w, h) = rect;
+    let Some((left, top, width, height)) =
+        clamp_to_image((x, y, w as u32, h as u32), image.dimensions())
+    else {
+        return;
+    };
+    for py in top..top + height {
+        for px in left..left + width {
+            image.put_pixel(px, py, Rgba([0, 0, 0, 255]));
+        }
+    }
+}
+
+// 截取本次截图的目标：按屏幕截图热键指定的显示器，否则按 capture_frame
+fn capture_target(
+    primary_screen: &Screen,
+    config: &Config,
+    session: &Session,
+) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Box<dyn std::error::Error>> {
+    match &session.screen {
+        Some(screen) => capture_screen_area(screen, config),
+        None => capture_frame(primary_screen, config),
+    }
+}
+
+// 覆盖窗口移到 rect (x, y, 宽, 高)（虚拟桌面坐标）并作为之后显示的位置，大小不同时同时调整窗口大小
+fn place_overlay(window: &mut Window, session: &mut Session, rect: (isize, isize, usize, usize)) {
+    let (x, y, width, height) = rect;
+    session.overlay_origin = (x, y);
+    if window.get_size() != (width, height) {
+        set_window_rect(window, rect);
+    }
+}
+
+// 移动窗口并调整其大小（虚拟桌面坐标），不改变 overlay_origin
+fn set_window_rect(window: &mut Window, rect: (isize, isize, usize, usize)) {
+    let (x, y, width, height) = rect;
+    unsafe {
+        SetWindowPos(
+            window.get_window_handle() as _,
+            null_mut(),
+            x as i32,
+            y as i32,
+            width as i32,
+            height as i32,
+            SWP_NOZORDER | SWP_NOACTIVATE,
+        );
+    }
+}
+
+// 恢复覆盖窗口的默认位置和大小（上次按屏幕截图或显示器变化之后）
+// 取消截图回到 Idle（ESC 或 idle_timeout_secs）时丢弃本次截图的状态：亮度/对比度调整、红框锁定、
+// 编号标记模式和标记、遮盖区域、快速截取、保存红框、W 键展开记录、预选窗口和待导出列表
+fn reset_session_for_idle(session: &mut Session) {
+    session.adjust = Adjustment::default();
+    session.region_locked = false;
+    session.stamp_mode = false;
+    session.stamps.clear();
+    session.redactions.clear();
+    session.quick = false;
+    session.save_red = false;
+    session.expanded_from = None;
+    session.preselect = None;
+    session.cart.clear();
+}
+
+fn reset_overlay(window: &mut Window, session: &mut Session) {
+    session.screen = None;
+    place_overlay(window, session, session.home_overlay);
+}
+
+// 覆盖窗口的默认位置和大小：主屏（work_area_only 时为其工作区），capture_all_screens 时覆盖整个虚拟桌面
+fn overlay_bounds(screens: &[Screen], config: &Config) -> (i32, i32, u32, u32) {
+    if config.capture_all_screens {
+        desktop::bounds(screens)
+    } else {
+        screen_area(&screens[0], config)
+    }
+}
+
+// 整屏截图的范围（虚拟桌面坐标）：整个屏幕，work_area_only 时为该屏幕的工作区（不含任务栏），查询失败时退回整个屏幕
+fn screen_area(screen: &Screen, config: &Config) -> (i32, i32, u32, u32) {
+    let info = &screen.display_info;
+    let full = (info.x, info.y, info.width, info.height);
+    if !config.work_area_only {
+        return full;
+    }
+    let center = (
+        info.x + info.width as i32 / 2,
+        info.y + info.height as i32 / 2,
+    );
+    window_info::work_area(center)
+        .filter(|&(_, _, width, height)| width > 0 && height > 0)
+        .unwrap_or(full)
+}
+
+// 显示器增减或分辨率变化后重新查询屏幕，更新主屏和覆盖窗口的默认位置和大小，并按新尺寸调整（隐藏的）窗口。
+// 只在空闲时调用：截图进行中的变化由主循环留到回到空闲后
+fn refresh_screens(
+    primary_screen: &mut Screen,
+    window: &mut Window,
+    session: &mut Session,
+    config: &Config,
+) {
+    let screens = match Screen::all() {
+        Ok(screens) if !screens.is_empty() => screens,
+        _ => {
+            eprintln!("Failed to list screens after a display change");
+            return;
+        }
+    };
+    *primary_screen = screens[0];
+    let (x, y, width, height) = overlay_bounds(&screens, config);
+    session.home_overlay = (x as isize, y as isize, width as usize, height as usize);
+    println!(
+        "Display changed: primary screen {}x{}",
+        primary_screen.display_info.width, primary_screen.display_info.height
+    );
+    if let Some(precapture) = &session.precapture {
+        precapture.set_screen(*primary_screen);
+    }
+    reset_overlay(window, session);
+    window.set_position(-(width as isize * 2), -(height as isize * 2));
+}
+
+// 截取主屏，capture_all_screens 时截取并拼接所有屏幕
+fn capture_frame(
+    primary_screen: &Screen,
+    config: &Config,
+) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Box<dyn std::error::Error>> {
+    if config.capture_all_screens {
+        Screen::all()
+            .map_err(|e| e.into())
+            .and_then(|screens| desktop::capture_all(&screens, config.channel_order))
+    } else {
+        capture_screen_area(primary_screen, config)
+    }
+}
+
+// 截取整个屏幕，work_area_only 时只截取 screen_area 给出的工作区
+fn capture_screen_area(
+    screen: &Screen,
+    config: &Config,
+) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Box<dyn std::error::Error>> {
+    let info = &screen.display_info;
+    let (x, y, width, height) = screen_area(screen, config);
+    if (x, y, width, height) == (info.x, info.y, info.width, info.height) {
+        return capture_screen(screen, config.channel_order);
+    }
+    capture_region(
+        screen,
+        (x - info.x, y - info.y, width, height),
+        config.channel_order,
+    )
+}
+
+// 显示预截图中选中的帧
+fn show_rewind_frame(window: &mut Window, session: &mut Session, config: &Config) -> State {
+    let (captured_at, image) = &session.rewind[session.rewind_index];
+    session.captured_at = Some(*captured_at);
+    let (pressed_at, _) = session.rewind[session.rewind.len() - 1];
+    let age = pressed_at.duration_since(*captured_at).as_millis();
+    window.set_title(&format!(
+        "Frame {}/{} ({} ms before the hotkey) - Left/Right to pick another frame, drag to select region, ESC to cancel",
+        session.rewind_index + 1,
+        session.rewind.len(),
+        age
+    ));
+    State::FullscreenCapture(
+        image.clone(),
+        DisplayCache::new(image, config.dim_tint, config.dim_tint_strength),
+    )
+}
+
+// 保存选区：启用后台保存时交给保存线程，队列已满或未启用时直接保存
+fn save_selection(
+    img: &Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+    region: (i32, i32, i32, i32),
+    sub_region: Option<(i32, i32, i32, i32)>,
+    title: Option<String>,
+    primary_screen: &Screen,
+    config: &Config,
+    session: &mut Session,
+) {
+    let (screen_width, screen_height) = archive_screen_size(img, primary_screen, config, session);
+    let job = SaveJob {
+        image: Arc::clone(img),
+        region,
+        sub_region,
+        screen_width,
+        screen_height,
+        dpi: selection_dpi(sub_region.unwrap_or(region), session.overlay_origin, config),
+        offset: coordinate_offset(
+            sub_region.unwrap_or(region),
+            session.overlay_origin,
+            frame_offset(session, config),
+            config,
+        ),
+        shape: session.shape,
+        adjust: session.adjust,
+        title,
+        // capture_time_names：文件名用冻结帧的截取时刻
+        captured_at: session
+            .captured_at
+            .filter(|_| config.capture_time_names)
+            .map(|instant| SystemTime::now() - instant.elapsed()),
+        region_line: session
+            .print_region
+            .then(|| region_line(region, sub_region, session.overlay_origin)),
+    };
+    if config.copy_coordinates_on_save
+        && let Err(e) = copy_coordinates(sub_region.unwrap_or(region), config, session)
+    {
+        eprintln!("Failed to copy coordinates: {}", e);
+    }
+    submit_save(job, config, session);
+}
+
+// 归档目录 W{w}H{h} 所用的屏幕尺寸：拼接的虚拟桌面按整张图的尺寸，按屏幕截图时按该显示器的尺寸，否则为主屏
+fn archive_screen_size(
+    img: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    primary_screen: &Screen,
+    config: &Config,
+    session: &Session,
+) -> (u32, u32) {
+    if let Some(screen) = &session.screen {
+        (screen.display_info.width, screen.display_info.height)
+    } else if config.capture_all_screens {
+        img.dimensions()
+    } else {
+        (
+            primary_screen.display_info.width,
+            primary_screen.display_info.height,
+        )
+    }
+}
+
+// 把要保存的框按保存流程处理（形状、调整、边框、水印、投影等，编号标记已在 img 中）后加入待导出列表；
+// sub_region 为要保存的绿框。选区在截图外时提示并返回 None
+fn add_to_cart(
+    window: &mut Window,
+    img: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    region: (i32, i32, i32, i32),
+    sub_region: Option<(i32, i32, i32, i32)>,
+    primary_screen: &Screen,
+    config: &Config,
+    session: &mut Session,
+) -> Option<()> {
+    let (x, y, w, h) = region;
+    let sub = sub_region.map(|(sx, sy, sw, sh)| (sx, sy, sw as u32, sh as u32));
+    let Some(image) = process_selection(
+        img,
+        (x, y, w as u32, h as u32),
+        sub,
+        session.shape,
+        session.adjust,
+        config,
+    ) else {
+        window.set_title("Nothing to add: the selection is outside the capture");
+        return None;
+    };
+    let saved_box = sub_region.unwrap_or(region);
+    let item = CartItem::new(
+        image,
+        saved_box,
+        sub_region.is_some(),
+        archive_screen_size(img, primary_screen, config, session),
+        selection_dpi(saved_box, session.overlay_origin, config),
+        coordinate_offset(
+            saved_box,
+            session.overlay_origin,
+            frame_offset(session, config),
+            config,
+        ),
+        session
+            .captured_at
+            .filter(|_| config.capture_time_names)
+            .map(|instant| SystemTime::now() - instant.elapsed()),
+    );
+    session.cart.push(item);
+    Some(())
+}
+
+// --print-region 打印的一行：选区所在的屏幕序号（Screen::all() 中的序号，与 --server 的 capture 命令一致）和相对该屏幕左上角的坐标，
+// 如 "region monitor=0 x=10 y=20 w=300 h=200 sub_x=.. sub_y=.. sub_w=.. sub_h=.."，便于脚本解析。保存成功后才打印（SaveJob::run）
+fn region_line(
+    region: (i32, i32, i32, i32),
+    sub_region: Option<(i32, i32, i32, i32)>,
+    origin: (isize, isize),
+) -> String {
+    let (x, y, w, h) = region;
+    let (monitor, offset) = region_monitor(region, origin);
+    let mut line = format!(
+        "region monitor={} x={} y={} w={} h={}",
+        monitor,
+        x + offset.0,
+        y + offset.1,
+        w,
+        h
+    );
+    if let Some((sx, sy, sw, sh)) = sub_region {
+        line += &format!(
+            " sub_x={} sub_y={} sub_w={} sub_h={}",
+            sx + offset.0,
+            sy + offset.1,
+            sw,
+            sh
+        );
+    }
+    line
+}
+
+// 红框中心所在的屏幕（Screen::all() 中的序号，找不到时为 0），以及截图坐标换算为相对该屏幕左上角的坐标要加的偏移；
+// origin 为截图左上角在以主屏左上角为原点的桌面坐标中的位置
+fn region_monitor(region: (i32, i32, i32, i32), origin: (isize, isize)) -> (usize, (i32, i32)) {
+    let screens = Screen::all().unwrap_or_default();
+    let (x, y, w, h) = region;
+    let center = (x + w / 2 + origin.0 as i32, y + h / 2 + origin.1 as i32);
+    let monitor = screens
+        .iter()
+        .position(|screen| {
+            let info = &screen.display_info;
+            center.0 >= info.x
+                && center.0 < info.x + info.width as i32
+                && center.1 >= info.y
+                && center.1 < info.y + info.height as i32
+        })
+        .unwrap_or(0);
+    let monitor_origin = screens.get(monitor).map_or((0, 0), |screen| {
+        (screen.display_info.x, screen.display_info.y)
+    });
+    (
+        monitor,
+        (
+            origin.0 as i32 - monitor_origin.0,
+            origin.1 as i32 - monitor_origin.1,
+        ),
+    )
+}
+
+// 红框中心所在屏幕在截图中的范围（work_area_only 时为其工作区），裁剪到截图内
+fn monitor_region(
+    region: (i32, i32, i32, i32),
+    cache: &DisplayCache,
+    origin: (isize, isize),
+    config: &Config,
+) -> Option<(i32, i32, i32, i32)> {
+    let screens = Screen::all().ok()?;
+    let (monitor, _) = region_monitor(region, origin);
+    let (x, y, width, height) = screen_area(screens.get(monitor)?, config);
+    clip_to_overlay((x, y, width as i32, height as i32), cache, origin)
+}
+
+// W 键：红框仍是上次展开的结果时恢复展开前的红框，否则展开到所在的整个屏幕并记下原来的红框
+// （刚按下鼠标、还没拖出大小时没有可恢复的红框）
+fn toggle_monitor_region(
+    window: &mut Window,
+    img: &Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+    cache: &DisplayCache,
+    region: (i32, i32, i32, i32),
+    config: &Config,
+    session: &mut Session,
+) -> Option<State> {
+    let region = match session.expanded_from.take() {
+        Some((previous, expanded)) if expanded == region => {
+            window.set_title(&format!("Region restored - Press W to expand to the whole screen again, {} to save, ESC to re-select", config.save_hotkey));
+            previous
+        }
+        _ => {
+            let expanded = monitor_region(region, cache, session.overlay_origin, config)?;
+            session.expanded_from = (region.2 > 0 && region.3 > 0).then_some((region, expanded));
+            window.set_title(&format!(
+                "Expanded to the whole screen - Press W to restore, {} to save, ESC to re-select",
+                config.save_hotkey
+            ));
+            expanded
+        }
+    };
+    Some(State::RegionSelected(img.clone(), cache.clone(), region))
+}
+
+// 热键已被本程序的其他功能占用时返回占用它的功能；name 为正在保存的命名区域，同名区域会被覆盖，不算冲突
+fn hotkey_conflict(hotkey: config::Hotkey, name: &str, config: &Config) -> Option<String> {
+    let mut used = vec![
+        (config.capture_hotkey, "the capture hotkey".to_string()),
+        (config.save_hotkey, "the save hotkey".to_string()),
+        (
+            config.cancel_save_hotkey,
+            "the cancel save hotkey".to_string(),
+        ),
+        (config.suspend_hotkey, "the suspend hotkey".to_string()),
+        (config.stop_hotkey, "the timer stop hotkey".to_string()),
+    ];
+    let optional = [
+        (config.quick_hotkey, "the quick capture hotkey"),
+        (config.viewer_hotkey, "the viewer hotkey"),
+        (config.copy_path_hotkey, "the copy path hotkey"),
+    ];
+    used.extend(
+        optional
+            .into_iter()
+            .filter_map(|(hotkey, what)| Some((hotkey?, what.to_string()))),
+    );
+    if let Some(modifiers) = config.monitor_hotkey_modifiers {
+        used.extend((0..MONITOR_HOTKEY_COUNT).map(|i| {
+            (
+                config::Hotkey {
+                    modifiers,
+                    vk: '1' as u32 + i as u32,
+                },
+                format!("the hotkey for monitor {}", i + 1),
+            )
+        }));
+    }
+    used.extend(
+        config
+            .presets
+            .iter()
+            .filter(|preset| !preset.name.eq_ignore_ascii_case(name.trim()))
+            .map(|preset| {
+                (
+                    preset.hotkey,
+                    format!("the hotkey for preset '{}'", preset.name),
+                )
+            }),
+    );
+    used.into_iter()
+        .find(|(used, _)| *used == hotkey)
+        .map(|(_, what)| what)
+}
+
+// 命名区域名称可输入的字符：字母（Shift 为大写）、数字、空格、- 和 _（Shift+-），其余按键返回 None
+fn preset_name_char(key: Key, shift: bool) -> Option<char> {
+    if key == Key::Minus {
+        return Some(if shift { '_' } else { '-' });
+    }
+    let vk = config::Hotkey::from_key(0, key)?.vk;
+    match vk {
+        0x41..=0x5A if shift => Some(vk as u8 as char),
+        0x41..=0x5A => Some((vk as u8).to_ascii_lowercase() as char),
+        0x30..=0x39 | 0x20 => Some(vk as u8 as char),
+        _ => None,
+    }
+}
+
+// 存为命名区域时的窗口标题
+fn preset_prompt_title(prompt: &PresetPrompt) -> String {
+    let hotkey = prompt
+        .hotkey
+        .map_or("none".to_string(), |hotkey| hotkey.to_string());
+    if prompt.overwrite {
+        format!(
+            "Preset '{}' already exists - Enter to overwrite it, ESC to cancel",
+            prompt.name.trim()
+        )
+    } else {
+        format!(
+            "New preset: name '{}', hotkey {} - Type a name, press a hotkey with Ctrl/Alt, Enter to save, ESC to cancel",
+            prompt.name, hotkey
+        )
+    }
+}
+
+// 截取命名区域所在的屏幕并保存该区域
+fn save_preset(preset: &config::Preset, config: &Config, session: &Session) {
+    let screens = match Screen::all() {
+        Ok(screens) => screens,
+        Err(e) => {
+            eprintln!("Failed to list screens: {}", e);
+            return;
+        }
+    };
+    let Some(screen) = screens.get(preset.monitor) else {
+        eprintln!(
+            "Preset '{}': no such monitor {}",
+            preset.name, preset.monitor
+        );
+        return;
+    };
+
+    match capture_screen(screen, config.channel_order) {
+        Ok(image) => {
+            let info = &screen.display_info;
+            let origin = (info.x as isize, info.y as isize);
+            let job = SaveJob {
+                image: Arc::new(image),
+                region: preset.region,
+                sub_region: None,
+                screen_width: info.width,
+                screen_height: info.height,
+                dpi: selection_dpi(preset.region, origin, config),
+                offset: coordinate_offset(preset.region, origin, (0, 0), config),
+                shape: config.selection_shape,
+                adjust: Adjustment::default(),
+                title: None,
+                captured_at: None,
+                region_line: None,
+            };
+            submit_save(job, config, session);
+        }
+        Err(e) => eprintln!("Preset '{}': failed to capture screen: {}", preset.name, e),
+    }
+}
+
+// 启用后台保存时交给保存线程，队列已满或未启用时同步保存
+fn submit_save(job: SaveJob, config: &Config, session: &Session) {
+    let job = match &session.save_worker {
+        Some(worker) => match worker.submit(job) {
+            Ok(()) => return,
+            Err(job) => {
+                eprintln!("Save queue is full, saving synchronously");
+                job
+            }
+        },
+        None => job,
+    };
+    let _ = job.run(config, &AtomicBool::new(false));
+}
+
+// dpi_tag 时查询选区中心所在显示器的 DPI；origin 为截图左上角在屏幕上的位置
+fn selection_dpi(
+    region: (i32, i32, i32, i32),
+    origin: (isize, isize),
+    config: &Config,
+) -> Option<u32> {
+    if !config.dpi_tag {
+        return None;
+    }
+    let (x, y, w, h) = region;
+    window_info::monitor_dpi_at((origin.0 as i32 + x + w / 2, origin.1 as i32 + y + h / 2))
+}
+
+// coordinate_space = monitor 时文件名和保存回调中的坐标改为相对选区中心所在显示器的左上角：
+// 返回要加到截图坐标上的偏移（desktop 时为 frame_offset）；origin 为截图左上角在屏幕上的位置
+fn coordinate_offset(
+    region: (i32, i32, i32, i32),
+    origin: (isize, isize),
+    frame_offset: (i32, i32),
+    config: &Config,
+) -> (i32, i32) {
+    if config.coordinate_space == CoordinateSpace::Desktop {
+        return frame_offset;
+    }
+    let (x, y, w, h) = region;
+    let center = (x + w / 2, y + h / 2);
+    let (rx, ry) =
+        window_info::monitor_relative((origin.0 as i32 + center.0, origin.1 as i32 + center.1));
+    (rx - center.0, ry - center.1)
+}
+
+// 保存后的状态：连续截图或按空格保存时留在同一帧上继续选择，否则隐藏窗口回到空闲
+fn after_save(
+    window: &mut Window,
+    img: &Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+    cache: &DisplayCache,
+    config: &Config,
+    keep_frame: bool,
+) -> State {
+    if keep_frame || config.continuous_capture {
+        window.set_title("Saved - Click and drag to select the next region, ESC to finish");
+        State::FullscreenCapture(img.clone(), cache.clone())
+    } else {
+        window.set_position(-(img.width() as isize * 2), -(img.height() as isize * 2));
+        set_idle_title(window, config);
+        State::Idle
+    }
+}
+
+// 更新显示函数
+fn update_display(
+    window: &mut Window,
+    state: &mut State,
+    session: &Session,
+    config: &Config,
+    display_buffer: &mut Option<Vec<u32>>,
+    frame: u64,
+) {
+    // 待确认的红框需要绘制调整手柄，OCR 建议的文字框绘制为蓝框
+    let editing_region = match state {
+        State::EditingRegion(_, _, region, _) => Some(*region),
+        _ => None,
+    };
+    let text_box = match state {
+        State::TextBoxProposed(_, _, _, text_box) => Some(*text_box),
+        // 点选界面元素时已点选的第一个元素也画为蓝框
+        State::ElementPick(_, _, _, first) => *first,
+        _ => None,
+    };
+    // 键盘框选时放大镜跟随虚拟光标
+    let keyboard_cursor = match state {
+        State::KeyboardSelect(_, _, cursor, _) => Some(*cursor),
+        _ => None,
+    };
+    let preset_prompt = match state {
+        State::PresetName(.., prompt) => Some(prompt.clone()),
+        _ => None,
+    };
+
+    // 计算当前状态下的红框和绿框
+    let (cache, red_region, green_region) = match state {
+        State::Idle => {
+            // 空闲状态，无需显示
+            return;
+        }
+        State::Countdown(start, delay) => {
+            let (width, height) = window.get_size();
+            let buffer = display_buffer.get_or_insert_with(|| vec![0u32; width * height]);
+            let remaining = 1.0 - start.elapsed().as_secs_f32() / delay.as_secs_f32();
+            draw_countdown(
+                buffer,
+                width,
+                remaining.clamp(0.0, 1.0),
+                config.countdown_style,
+            );
+            if let Err(e) = present(window, buffer, width, height, config) {
+                recover_display(window, state, display_buffer, e, None, config, session);
+            }
+            return;
+        }
+        State::CaptureFailed(message) => {
+            let (width, height) = window.get_size();
+            let buffer = display_buffer.get_or_insert_with(|| vec![0u32; width * height]);
+            draw_capture_error(buffer, width, message);
+            if let Err(e) = present(window, buffer, width, height, config) {
+                recover_display(window, state, display_buffer, e, None, config, session);
+            }
+            return;
+        }
+        State::Viewer(viewer) => {
+            let (width, height) = window.get_size();
+            let buffer = display_buffer.get_or_insert_with(|| vec![0u32; width * height]);
+            viewer.draw(buffer, width);
+            if let Err(e) = present(window, buffer, width, height, config) {
+                recover_display(window, state, display_buffer, e, None, config, session);
+            }
+            return;
+        }
+        State::Gallery(entries) => {
+            let (width, height) = window.get_size();
+            let buffer = display_buffer.get_or_insert_with(|| vec![0u32; width * height]);
+            draw_gallery(buffer, width, entries, session.cursor);
+            if let Err(e) = present(window, buffer, width, height, config) {
+                recover_display(window, state, display_buffer, e, None, config, session);
+            }
+            return;
+        }
+        State::FullscreenCapture(_, cache) | State::LivePreview(_, cache, _) => (cache, None, None),
+        State::SelectingRegion(_, cache, start, current) => {
+            let region = Some((
+                start.0.min(current.0),
+                start.1.min(current.1),
+                (current.0 - start.0).abs(),
+                (current.1 - start.1).abs(),
+            ));
+            (cache, region, None)
+        }
+        State::RegionSelected(_, cache, region) | State::PresetName(_, cache, region, _) => {
+            (cache, Some(*region), None)
+        }
+        State::EditingRegion(_, cache, region, _) => (cache, Some(*region), None),
+        State::SelectingSubRegion(_, cache, red_region, start, current) => {
+            let green_region = Some((
+                start.0.min(current.0),
+                start.1.min(current.1),
+                (current.0 - start.0).abs(),
+                (current.1 - start.1).abs(),
+            ));
+            (cache, Some(*red_region), green_region)
+        }
+        State::SubRegionSelected(_, cache, red_region, green_region) => {
+            (cache, Some(*red_region), Some(*green_region))
+        }
+        State::TextBoxProposed(_, cache, red_region, _) => (cache, Some(*red_region), None),
+        State::WindowPick(_, cache, windows, index) => (cache, Some(windows[*index].rect), None),
+        // 红框预览光标下的元素，已点选第一个元素时为两者的外接框
+        State::ElementPick(_, cache, elements, first) => {
+            let hovered = element_at(elements, session.cursor);
+            let region = match (*first, hovered) {
+                (Some(first), Some(hovered)) => Some(union_rect(first, hovered)),
+                (first, hovered) => first.or(hovered),
+            };
+            (cache, region, None)
+        }
+        State::KeyboardSelect(_, cache, cursor, anchor) => {
+            let region = anchor.map(|anchor| {
+                (
+                    anchor.0.min(cursor.0),
+                    anchor.1.min(cursor.1),
+                    (cursor.0 - anchor.0).abs(),
+                    (cursor.1 - anchor.1).abs(),
+                )
+            });
+            (cache, region, None)
+        }
+        State::OutputPreview(_, cache, _, _, preview) => {
+            cache.draw_output_preview(preview);
+            let frame_size = (cache.width, cache.height);
+            if let Err(e) = present_frame(window, cache, session, config, display_buffer) {
+                recover_display(
+                    window,
+                    state,
+                    display_buffer,
+                    e,
+                    Some(frame_size),
+                    config,
+                    session,
+                );
+            }
+            return;
+        }
+    };
+
+    cache.update_display(
+        red_region,
+        green_region,
+        config.border_style,
+        frame,
+        session.adjust,
+    );
+    if let Some(region) = editing_region {
+        // 缩小显示时按比例放大，使手柄在屏幕上仍为 handle_size 像素（与抓取容差一致）
+        let size = (config.handle_size as f32 / session.fit_scale.unwrap_or(1.0)).round() as i32;
+        cache.draw_handles(region, size);
+    }
+    if let Some(text_box) = text_box {
+        cache.draw_border(text_box, 0xFF3080FF, config.border_style, frame);
+    }
+    // 在要保存的框内预览椭圆或圆角
+    let saved_box = match (red_region, green_region) {
+        (_, Some(green)) => Some((green, 0xFF00FF00)),
+        (Some(red), None) => Some((red, 0xFFFF0000)),
+        _ => None,
+    };
+    if let Some((rect, color)) = saved_box {
+        match session.shape {
+            SelectionShape::Ellipse => cache.draw_ellipse(rect, color),
+            SelectionShape::Rectangle if config.corner_radius > 0 => {
+                cache.draw_rounded_rectangle(rect, config.corner_radius as i32, color)
+            }
+            SelectionShape::Rectangle => {}
+        }
+    }
+    cache.draw_guides(&session.guides_x, &session.guides_y, 0xFF00C8FF);
+    for &rect in &session.redactions {
+        cache.draw_redaction(rect);
+    }
+    for stamp in &session.stamps {
+        stamp::draw(
+            &mut cache.display_buffer,
+            cache.width,
+            cache.height,
+            stamp,
+            config.stamp_radius,
+            config.stamp_color,
+        );
+    }
+    cache.draw_thumbnails(&session.cart);
+    // live 覆盖模式没有冻结的截图，不显示直方图和放大镜
+    let live = config.overlay_mode == OverlayMode::Live;
+    if session.show_histogram
+        && !live
+        && let Some(region) = green_region.or(red_region)
+    {
+        cache.draw_histogram(region);
+    }
+    if let Some(cursor) = keyboard_cursor {
+        cache.draw_crosshair(cursor);
+    }
+    if config.loupe_zoom > 1 && !live {
+        let grid_color = session.loupe_grid.then_some(config.loupe_grid_color);
+        cache.draw_loupe(
+            keyboard_cursor.unwrap_or(session.cursor),
+            config.loupe_zoom,
+            grid_color,
+        );
+    }
+    if config.debug_timings {
+        cache.draw_timings();
+    }
+    if let Some(prompt) = preset_prompt {
+        let hotkey = prompt
+            .hotkey
+            .map_or("-".to_string(), |hotkey| hotkey.to_string());
+        let lines = [
+            format!("Preset name: {}_", prompt.name),
+            format!("Hotkey: {}", hotkey),
+        ];
+        let color = if prompt.overwrite {
+            0xFFFF6060
+        } else {
+            0xFFFFFFFF
+        };
+        cache.draw_text_box(&lines, color);
+    }
+    let frame_size = (cache.width, cache.height);
+    if let Err(e) = present_frame(window, cache, session, config, display_buffer) {
+        recover_display(
+            window,
+            state,
+            display_buffer,
+            e,
+            Some(frame_size),
+            config,
+            session,
+        );
+    }
+}
+
+// 显示截图帧：fit_to_window 缩小时按比例缩小到 scratch 左上角并标出比例；
+// pan_and_scan 且截图大于窗口时只按 1:1 显示视口部分（复制到 scratch），并在右下角画出视口在整帧中的位置
+fn present_frame(
+    window: &mut Window,
+    cache: &DisplayCache,
+    session: &Session,
+    config: &Config,
+    scratch: &mut Option<Vec<u32>>,
+) -> Result<(), String> {
+    let (width, height) = window.get_size();
+    if let Some(scale) = session.fit_scale {
+        let buffer = scratch.get_or_insert_with(Vec::new);
+        buffer.resize(width * height, 0xFF000000);
+        draw_scaled(
+            buffer,
+            width,
+            height,
+            cache,
+            scale,
+            config.display_interpolation,
+        );
+        draw_scale_label(buffer, width, scale);
+        return present(window, buffer, width, height, config);
+    }
+    if !config.pan_and_scan || (cache.width as usize <= width && cache.height as usize <= height) {
+        return present(
+            window,
+            &cache.display_buffer,
+            cache.width as usize,
+            cache.height as usize,
+            config,
+        );
+    }
+
+    let buffer = scratch.get_or_insert_with(Vec::new);
+    buffer.resize(width * height, 0xFF000000);
+    let (vx, vy) = (session.viewport.0 as usize, session.viewport.1 as usize);
+    let visible_width = width.min(cache.width as usize - vx);
+    for y in 0..height.min(cache.height as usize - vy) {
+        let src = (vy + y) * cache.width as usize + vx;
+        buffer[y * width..y * width + visible_width]
+            .copy_from_slice(&cache.display_buffer[src..src + visible_width]);
+    }
+    draw_viewport_minimap(buffer, width, (cache.width, cache.height), session.viewport);
+    present(window, buffer, width, height, config)
+}
+
+// 截图按 scale 缩小画到 buffer 左上角，其余部分为黑色；最近邻取左上对齐的源像素，
+// 双线性按像素中心对齐混合相邻的四个源像素
+fn draw_scaled(
+    buffer: &mut [u32],
+    width: usize,
+    height: usize,
+    cache: &DisplayCache,
+    scale: f32,
+    interpolation: Interpolation,
+) {
+    let scaled_width = ((cache.width as f32 * scale) as usize).min(width);
+    let scaled_height = ((cache.height as f32 * scale) as usize).min(height);
+    let source_width = cache.width as usize;
+    // 每个目标坐标对应的两个源坐标及第二个的权重（0-256）
+    let taps = |dst: usize, len: u32| -> (usize, usize, u32) {
+        let last = len as usize - 1;
+        match interpolation {
+            Interpolation::Nearest => (((dst as f32 / scale) as usize).min(last), 0, 0),
+            Interpolation::Bilinear => {
+                let pos = ((dst as f32 + 0.5) / scale - 0.5).max(0.0);
+                let first = (pos as usize).min(last);
+                (
+                    first,
+                    (first + 1).min(last),
+                    ((pos - first as f32) * 256.0) as u32,
+                )
+            }
+        }
+    };
+    let columns: Vec<(usize, usize, u32)> =
+        (0..scaled_width).map(|x| taps(x, cache.width)).collect();
+    for (y, row) in buffer.chunks_exact_mut(width).enumerate() {
+        if y >= scaled_height {
+            row.fill(0xFF000000);
+            continue;
+        }
+        let (y0, y1, fy) = taps(y, cache.height);
+        let top = &cache.display_buffer[y0 * source_width..(y0 + 1) * source_width];
+        let bottom = &cache.display_buffer[y1 * source_width..(y1 + 1) * source_width];
+        for (dst, &(x0, x1, fx)) in row.iter_mut().zip(&columns) {
+            *dst = match interpolation {
+                Interpolation::Nearest => top[x0],
+                Interpolation::Bilinear => lerp_argb(
+                    lerp_argb(top[x0], top[x1], fx),
+                    lerp_argb(bottom[x0], bottom[x1], fx),
+                    fy,
+                ),
+            };
+        }
+        row[scaled_width..].fill(0xFF000000);
+    }
+}
+
+// 两个 ARGB 颜色逐通道线性插值，t 为 b 的权重（0-256）
+fn lerp_argb(a: u32, b: u32, t: u32) -> u32 {
+    if t == 0 {
+        return a;
+    }
+    (0..4).fold(0, |out, i| {
+        let shift = i * 8;
+        let (ca, cb) = ((a >> shift) & 0xFF, (b >> shift) & 0xFF);
+        out | ((ca * (256 - t) + cb * t) >> 8) << shift
+    })
+}
+
+// 右下角标出缩小比例，提示当前不是 1:1 显示
+fn draw_scale_label(buffer: &mut [u32], width: usize, scale: f32) {
+    let height = buffer.len() / width;
+    let label = format!("Fit {}% - Z: 1:1", (scale * 100.0).round() as u32);
+    let text_width = font::text_width(&label, 1) as i32;
+    let left = width as i32 - text_width - 16;
+    let top = height as i32 - font::GLYPH_HEIGHT as i32 - 16;
+    for y in (top - 4).max(0)..(top + font::GLYPH_HEIGHT as i32 + 4).min(height as i32) {
+        for x in (left - 6).max(0)..(left + text_width + 6).min(width as i32) {
+            let idx = y as usize * width + x as usize;
+            buffer[idx] = blend(buffer[idx], 0xA0000000);
+        }
+    }
+    let style = font::TextStyle {
+        x: left,
+        y: top,
+        color: 0xFFFFFFFF,
+        scale: 1,
+    };
+    font::draw_text(buffer, width as u32, height as u32, &label, style);
+}
+
+// 截图大于窗口时整帧放入窗口的缩小比例，不需要缩小时为 None
+fn fit_scale(window: &Window, cache: &DisplayCache) -> Option<f32> {
+    let (width, height) = window.get_size();
+    let scale = (width as f32 / cache.width as f32).min(height as f32 / cache.height as f32);
+    (scale < 1.0).then_some(scale)
+}
+
+// 窗口坐标换算为截图坐标：缩小显示时除以比例（与 draw_scaled 的采样位置一致，双线性按像素中心对齐），
+// 1:1 显示时加上视口偏移
+fn to_frame_coords(session: &Session, config: &Config, x: f32, y: f32) -> (f32, f32) {
+    match session.fit_scale {
+        Some(scale) if config.display_interpolation == Interpolation::Bilinear => {
+            ((x + 0.5) / scale, (y + 0.5) / scale)
+        }
+        Some(scale) => (x / scale, y / scale),
+        None => (x + session.viewport.0 as f32, y + session.viewport.1 as f32),
+    }
+}
+
+// 视口小地图：整帧缩为 MINIMAP_WIDTH 宽的半透明框，白框为当前视口，上方标出视口左上角坐标
+fn draw_viewport_minimap(
+    buffer: &mut [u32],
+    width: usize,
+    frame_size: (u32, u32),
+    viewport: (i32, i32),
+) {
+    let height = buffer.len() / width;
+    let scale = MINIMAP_WIDTH as f32 / frame_size.0 as f32;
+    let map_height = (frame_size.1 as f32 * scale) as i32;
+    let left = width as i32 - MINIMAP_WIDTH - 16;
+    let top = height as i32 - map_height - 16;
+    if left < 0 || top < 0 {
+        return;
+    }
+
+    let view = (
+        left + (viewport.0 as f32 * scale) as i32,
+        top + (viewport.1 as f32 * scale) as i32,
+        ((width as f32 * scale) as i32).max(2),
+        ((height as f32 * scale) as i32).max(2),
+    );
+    for y in top..top + map_height {
+        for x in left..left + MINIMAP_WIDTH {
+            let idx = y as usize * width + x as usize;
+            let on_view_edge = (x == view.0 || x == view.0 + view.2 - 1)
+                && y >= view.1
+                && y < view.1 + view.3
+                || (y == view.1 || y == view.1 + view.3 - 1) && x >= view.0 && x < view.0 + view.2;
+            buffer[idx] = if on_view_edge {
+                0xFFFFFFFF
+            } else {
+                blend(buffer[idx], 0xA0000000)
+            };
         }
-        // 默认情况：不改变状态
-        _ => None,
     }
+
+    let label = format!("{},{}", viewport.0, viewport.1);
+    font::draw_text(
+        buffer,
+        width as u32,
+        height as u32,
+        &label,
+        font::TextStyle {
+            x: left,
+            y: top - font::GLYPH_HEIGHT as i32 - 4,
+            color: 0xFFFFFFFF,
+            scale: 1,
+        },
+    );
 }
 
-// 更新显示函数
-fn update_display(window: &mut Window, state: &mut State, _display_buffer: &mut Option<Vec<u32>>) {
-    match state {
-        State::Idle => {
-            // 空闲状态，无需显示
+// 计算新的视口位置：截图不超过窗口时为 (0, 0)；follow 为键盘框选的虚拟光标时保持其可见，
+// 否则光标（截图坐标）在窗口边缘 PAN_EDGE 内时向该方向平移；结果限制在截图范围内
+fn pan_viewport(
+    window: &Window,
+    cache: &DisplayCache,
+    viewport: (i32, i32),
+    cursor: (i32, i32),
+    follow: Option<(i32, i32)>,
+) -> (i32, i32) {
+    let (width, height) = window.get_size();
+    let (width, height) = (width as i32, height as i32);
+    let max = (
+        (cache.width as i32 - width).max(0),
+        (cache.height as i32 - height).max(0),
+    );
+    if max == (0, 0) {
+        return (0, 0);
+    }
+
+    let (mut x, mut y) = viewport;
+    match follow {
+        Some((fx, fy)) => {
+            x = x.clamp(fx - width + PAN_EDGE, fx - PAN_EDGE);
+            y = y.clamp(fy - height + PAN_EDGE, fy - PAN_EDGE);
         }
-        State::FullscreenCapture(_, cache) => {
-            cache.update_display(None, None);
-            window.update_with_buffer(&cache.display_buffer, cache.width as usize, cache.height as usize).unwrap();
+        None => {
+            let (cx, cy) = (cursor.0 - viewport.0, cursor.1 - viewport.1);
+            if cx < PAN_EDGE {
+                x -= PAN_STEP;
+            } else if cx >= width - PAN_EDGE {
+                x += PAN_STEP;
+            }
+            if cy < PAN_EDGE {
+                y -= PAN_STEP;
+            } else if cy >= height - PAN_EDGE {
+                y += PAN_STEP;
+            }
         }
-        State::SelectingRegion(_, cache, start, current) => {
-            let region = Some((
-                start.0.min(current.0),
-                start.1.min(current.1),
-                (current.0 - start.0).abs(),
-                (current.1 - start.1).abs(),
-            ));
-            cache.update_display(region, None);
-            window.update_with_buffer(&cache.display_buffer, cache.width as usize, cache.height as usize).unwrap();
+    }
+    (x.clamp(0, max.0), y.clamp(0, max.1))
+}
+
+// 当前状态显示的截图帧（没有截图的状态为 None）
+fn display_cache(state: &State) -> Option<&DisplayCache> {
+    match state {
+        State::Idle
+        | State::Countdown(..)
+        | State::Gallery(_)
+        | State::CaptureFailed(_)
+        | State::Viewer(_) => None,
+        State::LivePreview(_, cache, ..)
+        | State::FullscreenCapture(_, cache)
+        | State::SelectingRegion(_, cache, ..)
+        | State::RegionSelected(_, cache, ..)
+        | State::PresetName(_, cache, ..)
+        | State::EditingRegion(_, cache, ..)
+        | State::SelectingSubRegion(_, cache, ..)
+        | State::TextBoxProposed(_, cache, ..)
+        | State::SubRegionSelected(_, cache, ..)
+        | State::WindowPick(_, cache, ..)
+        | State::ElementPick(_, cache, ..)
+        | State::KeyboardSelect(_, cache, ..)
+        | State::OutputPreview(_, cache, ..) => Some(cache),
+    }
+}
+
+// 显示缓冲区：live 覆盖模式下按每像素 alpha 叠加到桌面上，否则正常绘制
+fn present(
+    window: &mut Window,
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+    config: &Config,
+) -> Result<(), String> {
+    if config.overlay_mode == OverlayMode::Live {
+        return layered::present(window.get_window_handle() as _, buffer, width, height);
+    }
+    window
+        .update_with_buffer(buffer, width, height)
+        .map_err(|e| e.to_string())
+}
+
+// 更新窗口失败（如显示设备丢失、分辨率变化）：记录错误并跳过这一帧，下一帧按窗口当前尺寸重新分配缓冲区；
+// 重新查询屏幕尺寸，已与截图尺寸不一致时放弃当前截图回到空闲
+fn recover_display(
+    window: &mut Window,
+    state: &mut State,
+    display_buffer: &mut Option<Vec<u32>>,
+    error: String,
+    frame_size: Option<(u32, u32)>,
+    config: &Config,
+    session: &Session,
+) {
+    eprintln!("Failed to update window: {}", error);
+    *display_buffer = None;
+
+    let Some((frame_width, frame_height)) = frame_size else {
+        return;
+    };
+    // 与截图时记录的来源范围比较；来源不明或无法查询时保留截图
+    if let Some((_, _, old_width, old_height)) = session.frame_source
+        && let Some((_, _, width, height)) = frame_source(config, session)
+        && (width, height) != (old_width, old_height)
+    {
+        eprintln!(
+            "Screen size changed from {}x{} to {}x{}, discarding the capture",
+            old_width, old_height, width, height
+        );
+        window.set_position(-(frame_width as isize * 2), -(frame_height as isize * 2));
+        set_idle_title(window, config);
+        *state = State::Idle;
+    }
+}
+
+// 截图失败界面：深色背景上居中显示错误信息和重试提示（点阵字体没有的字符显示为 ?）
+fn draw_capture_error(buffer: &mut [u32], width: usize, message: &str) {
+    buffer.fill(0xFF202020);
+    let height = buffer.len() / width.max(1);
+    let lines = [
+        format!("Capture failed: {}", message),
+        "Press any key to retry, ESC to cancel".to_string(),
+    ];
+    let line_height = (font::GLYPH_HEIGHT * 2 + 8) as i32;
+    let top = height as i32 / 2 - line_height;
+    for (i, line) in lines.iter().enumerate() {
+        let left = (width as i32 - font::text_width(line, 2) as i32) / 2;
+        let color = if i == 0 { 0xFFFF6060 } else { 0xFFFFFFFF };
+        let style = font::TextStyle {
+            x: left.max(0),
+            y: top + i as i32 * line_height,
+            color,
+            scale: 2,
+        };
+        font::draw_text(buffer, width as u32, height as u32, line, style);
+    }
+}
+
+// 绘制历史画廊：深色背景上的缩略图网格，鼠标悬停的条目加框
+fn draw_gallery(buffer: &mut [u32], width: usize, entries: &[HistoryEntry], cursor: (i32, i32)) {
+    buffer.fill(0xFF202020);
+    let height = buffer.len() / width.max(1);
+    let hovered = history::hit_test(entries.len(), width as u32, cursor.0, cursor.1);
+
+    for (i, entry) in entries.iter().enumerate() {
+        let (left, top) = history::cell_origin(i, width as u32);
+        let (left, top) = (left as usize, top as usize);
+        let (tw, th) = (
+            entry.thumbnail_width as usize,
+            entry.thumbnail_height as usize,
+        );
+        if left + tw > width || top + th > height {
+            break;
         }
-        State::RegionSelected(_, cache, region) => {
-            cache.update_display(Some(*region), None);
-            window.update_with_buffer(&cache.display_buffer, cache.width as usize, cache.height as usize).unwrap();
+
+        for y in 0..th {
+            let dst = (top + y) * width + left;
+            buffer[dst..dst + tw].copy_from_slice(&entry.thumbnail[y * tw..(y + 1) * tw]);
         }
-        State::SelectingSubRegion(_, cache, red_region, start, current) => {
-            let green_region = Some((
-                start.0.min(current.0),
-                start.1.min(current.1),
-                (current.0 - start.0).abs(),
-                (current.1 - start.1).abs(),
-            ));
-            cache.update_display(Some(*red_region), green_region);
-            window.update_with_buffer(&cache.display_buffer, cache.width as usize, cache.height as usize).unwrap();
+
+        if hovered == Some(i) {
+            for x in left.saturating_sub(2)..(left + tw + 2).min(width) {
+                buffer[top.saturating_sub(2) * width + x] = 0xFFFF0000;
+                buffer[(top + th + 1).min(height - 1) * width + x] = 0xFFFF0000;
+            }
+            for y in top.saturating_sub(2)..(top + th + 2).min(height) {
+                buffer[y * width + left.saturating_sub(2)] = 0xFFFF0000;
+                buffer[y * width + (left + tw + 1).min(width - 1)] = 0xFFFF0000;
+            }
         }
-        State::SubRegionSelected(_, cache, red_region, green_region) => {
-            cache.update_display(Some(*red_region), Some(*green_region));
-            window.update_with_buffer(&cache.display_buffer, cache.width as usize, cache.height as usize).unwrap();
+    }
+}
+
+// 在缓冲区左上角 COUNTDOWN_SIZE 见方的区域绘制倒计时（环形或进度条），remaining 为剩余比例
+fn draw_countdown(buffer: &mut [u32], width: usize, remaining: f32, style: CountdownStyle) {
+    let size = COUNTDOWN_SIZE as usize;
+    let center = size as f32 / 2.0;
+
+    for y in 0..size.min(buffer.len() / width.max(1)) {
+        for x in 0..size.min(width) {
+            let color = match style {
+                CountdownStyle::Ring => {
+                    let (dx, dy) = (x as f32 + 0.5 - center, y as f32 + 0.5 - center);
+                    let distance = (dx * dx + dy * dy).sqrt();
+                    // 从12点方向顺时针计算角度比例
+                    let angle = (dx.atan2(-dy) / std::f32::consts::TAU).rem_euclid(1.0);
+                    if distance > center * 0.6 && distance < center * 0.9 {
+                        if angle < remaining {
+                            0xFFFFFFFF
+                        } else {
+                            0xFF404040
+                        }
+                    } else {
+                        0xFF202020
+                    }
+                }
+                CountdownStyle::Bar => {
+                    let bar_top = size * 2 / 5;
+                    let bar_bottom = size * 3 / 5;
+                    let inside = x >= 10 && x < size - 10 && y >= bar_top && y < bar_bottom;
+                    let filled = x.saturating_sub(10) as f32 / (size - 20) as f32 <= remaining;
+                    match (inside, filled) {
+                        (true, true) => 0xFFFFFFFF,
+                        (true, false) => 0xFF404040,
+                        _ => 0xFF202020,
+                    }
+                }
+            };
+            buffer[y * width + x] = color;
         }
     }
 }
@@ -583,17 +5180,88 @@ fn update_display(window: &mut Window, state: &mut State, _display_buffer: &mut
 // 捕获屏幕函数
 fn capture_screen(
     screen: &Screen,
+    channel_order: ChannelOrder,
 ) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Box<dyn std::error::Error>> {
     let screenshot = screen.capture()?;
     let width = screenshot.width() as u32;
     let height = screenshot.height() as u32;
-    let buffer = screenshot.to_vec();
+    let mut buffer = pack_rows(width, height, screenshot.to_vec())?;
+    normalize_channels(&mut buffer, channel_order);
+
+    let mut image = ImageBuffer::from_vec(width, height, buffer).unwrap();
+    hdr::correct(&mut image, screen);
+    Ok(image)
+}
+
+// 只截取屏幕上的一个区域（坐标相对该屏幕左上角，超出屏幕的部分被裁掉），小区域时比整屏截取再裁剪快且省内存；
+// 后端不支持区域截图或返回的尺寸与请求不符（如缩放比例不为 100%）时退回整屏截取再裁剪
+fn capture_region(
+    screen: &Screen,
+    region: (i32, i32, u32, u32),
+    channel_order: ChannelOrder,
+) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Box<dyn std::error::Error>> {
+    let info = &screen.display_info;
+    let (left, top, width, height) =
+        clamp_to_image(region, (info.width, info.height)).ok_or("region is outside the screen")?;
+    if let Ok(area) = screen.capture_area(left as i32, top as i32, width, height)
+        && area.dimensions() == (width, height)
+    {
+        let mut buffer = pack_rows(width, height, area.into_raw())?;
+        normalize_channels(&mut buffer, channel_order);
+        let mut image = ImageBuffer::from_vec(width, height, buffer).unwrap();
+        hdr::correct(&mut image, screen);
+        return Ok(image);
+    }
 
-    Ok(ImageBuffer::from_vec(width, height, buffer).unwrap())
+    let image = capture_screen(screen, channel_order)?;
+    let (left, top, width, height) =
+        clamp_to_image(region, image.dimensions()).ok_or("region is outside the screen")?;
+    Ok(image::imageops::crop_imm(&image, left, top, width, height).to_image())
 }
 
+// 统一转换为 RGBA：之后的 DisplayCache 和编码器都按 RGBA 处理
+fn normalize_channels(buffer: &mut [u8], channel_order: ChannelOrder) {
+    if channel_order == ChannelOrder::Bgra {
+        for pixel in buffer.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+}
+
+// 去除行尾填充：部分后端返回的每行字节数（stride）大于 width*4，
+// 直接按紧密排列解释会导致图像斜向错位
+fn pack_rows(width: u32, height: u32, buffer: Vec<u8>) -> Result<Vec<u8>, String> {
+    let row_len = width as usize * 4;
+    let packed_len = row_len * height as usize;
+
+    if buffer.len() == packed_len {
+        return Ok(buffer);
+    }
+
+    // 由缓冲区长度推算每行字节数
+    let stride = if height > 0 {
+        buffer.len() / height as usize
+    } else {
+        0
+    };
+    if stride < row_len || buffer.len() < stride * height as usize {
+        return Err(format!(
+            "unexpected capture buffer size {} for {}x{}",
+            buffer.len(),
+            width,
+            height
+        ));
+    }
+
+    let mut packed = Vec::with_capacity(packed_len);
+    for row in buffer.chunks(stride).take(height as usize) {
+        packed.extend_from_slice(&row[..row_len]);
+    }
+    Ok(packed)
+}
 
-// 保存为WebP格式的函数（无损）
+// 保存为WebP格式的函数（无损）：image 为截取的区域本身（左上角位于屏幕的 x, y），
+// sub_region 为屏幕坐标
 fn save_image_webp(
     image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
     x: i32,
@@ -603,46 +5271,667 @@ fn save_image_webp(
     screen_width: u32,
     screen_height: u32,
     sub_region: Option<(i32, i32, u32, u32)>,
-) {
-    // 创建目录
-    let dir_name = format!("W{}H{}", screen_width, screen_height);
-    let _ = std::fs::create_dir_all(&dir_name);
+    dpi: Option<u32>,
+    offset: (i32, i32),
+    config: &Config,
+) -> Option<String> {
+    let cropped = process_selection(
+        image,
+        (0, 0, width, height),
+        sub_region.map(|(sx, sy, sw, sh)| (sx - x, sy - y, sw, sh)),
+        config.selection_shape,
+        Adjustment::default(),
+        config,
+    )?;
+    let title = selection_title(&cropped, config);
+    let (dx, dy) = offset;
+    let file_name = save_file_name(
+        &NameContext {
+            region: (x + dx, y + dy, width, height),
+            screen: (screen_width, screen_height),
+            title: title.as_deref(),
+            ext: config.save_format.extension(),
+            dpi,
+            captured_at: None,
+        },
+        config,
+    );
+    let saved_region = sub_region.unwrap_or((x, y, width, height));
+    let saved_region = (
+        saved_region.0 + dx,
+        saved_region.1 + dy,
+        saved_region.2 as i32,
+        saved_region.3 as i32,
+    );
+    let outlines = selection_outlines(&cropped, sub_region.is_some(), config);
+    match write_selection(
+        &cropped,
+        file_name,
+        saved_region,
+        &outlines,
+        config,
+        &AtomicBool::new(false),
+    ) {
+        Ok(Some(
+            Saved::Written(file_name)
+            | Saved::UploadedOnly(file_name)
+            | Saved::Duplicate(file_name),
+        )) => Some(file_name),
+        _ => None,
+    }
+}
+
+// 编码并写入裁剪结果，返回原尺寸文件的文件名；每次编码前后检查 cancel，已取消时不再写入。
+// 配置了 scale_variants 时原尺寸对应最大倍率，其余倍率按比例缩小后写为 name@{倍率}x.webp（1 倍无后缀）。
+// 每个文件写入成功后调用保存回调，region 为保存的选区；outlines 为 SVG 的矢量标注。
+// 配置了 upload_url 时原尺寸文件另在后台上传，upload_save_file = false 时不写入磁盘
+fn write_selection(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    file_name: String,
+    region: (i32, i32, i32, i32),
+    outlines: &[svg::Outline],
+    config: &Config,
+    cancel: &AtomicBool,
+) -> Result<Option<Saved>, Cancelled> {
+    let check = || {
+        if cancel.load(Ordering::Relaxed) {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    };
 
-    // 生成文件名
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis();
+    // skip_duplicate_saves：与上一次保存的像素完全相同且那个文件还在时不再写入，返回上一次的文件名
+    let hash = config.skip_duplicate_saves.then(|| image_hash(image));
+    if let Some(hash) = hash
+        && let Some((last_hash, last_name)) = &*LAST_SAVE.lock().unwrap()
+        && *last_hash == hash
+        && std::path::Path::new(last_name).exists()
+    {
+        println!("Duplicate of the previous save suppressed: {}", last_name);
+        return Ok(Some(Saved::Duplicate(last_name.clone())));
+    }
+    // 只记录写入磁盘的文件（只上传时没有文件）
+    let remember = |saved: Option<Saved>| {
+        if let Some(Saved::Written(name)) = &saved {
+            remember_saved_path(name);
+            if let Some(hash) = hash {
+                *LAST_SAVE.lock().unwrap() = Some((hash, name.clone()));
+            }
+        }
+        saved
+    };
 
-    let mut file_name = format!(
-        "{}/screenshot_{}_Lx{}Ty{}W{}H{}",
-        dir_name, timestamp, x, y, width, height
-    );
+    if config.scale_variants.is_empty() {
+        check()?;
+        let encoded = encode_selection(image, outlines, config);
+        check()?;
+        return Ok(remember(encoded.and_then(|encoded| {
+            store_selection(encoded, &file_name, region, true, config)
+        })));
+    }
+
+    let native = config.scale_variants.iter().copied().fold(0.0, f32::max);
+    let extension = config.save_format.extension();
+    let base = file_name
+        .strip_suffix(&format!(".{}", extension))
+        .unwrap_or(&file_name);
+    let mut saved = None;
+    let mut all_written = true;
+    for &factor in &config.scale_variants {
+        check()?;
+        let name = if factor == 1.0 {
+            format!("{}.{}", base, extension)
+        } else {
+            format!("{}@{}x.{}", base, factor, extension)
+        };
+        let scaled = transform::scale(image, factor / native);
+        let scaled_outlines: Vec<svg::Outline> = outlines
+            .iter()
+            .map(|outline| outline.scaled(factor / native))
+            .collect();
+        let encoded = encode_selection(&scaled, &scaled_outlines, config);
+        check()?;
+        let stored = encoded
+            .and_then(|encoded| store_selection(encoded, &name, region, factor == native, config));
+        all_written &= stored.is_some();
+        if factor == native {
+            saved = stored;
+        }
+    }
+    Ok(remember(saved.filter(|_| all_written)))
+}
+
+// 上一次成功保存的选区像素哈希和文件名（skip_duplicate_saves），保存线程和主线程共用
+static LAST_SAVE: Mutex<Option<(u64, String)>> = Mutex::new(None);
+
+// 选区像素（含尺寸）的哈希
+fn image_hash(image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    image.dimensions().hash(&mut hasher);
+    image.as_raw().hash(&mut hasher);
+    hasher.finish()
+}
+
+// 写入一个编码好的选区文件；upload 时同时交给后台上传。upload_save_file = false 时不写磁盘，返回 UploadedOnly；
+// 写入失败时返回 None
+fn store_selection(
+    encoded: Vec<u8>,
+    file_name: &str,
+    region: (i32, i32, i32, i32),
+    upload: bool,
+    config: &Config,
+) -> Option<Saved> {
+    let saved = if config.upload_save_file {
+        write_encoded(&encoded, file_name, region).then(|| Saved::Written(file_name.to_string()))
+    } else {
+        Some(Saved::UploadedOnly(file_name.to_string()))
+    };
+    if upload && !config.upload_url.is_empty() {
+        upload::spawn(encoded, file_name, config);
+    }
+    saved
+}
+
+// 按 save_format 编码要保存的选区，失败时返回 None
+fn encode_selection(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    outlines: &[svg::Outline],
+    config: &Config,
+) -> Option<Vec<u8>> {
+    match config.save_format {
+        SaveFormat::Webp => Some(encode_webp(image)),
+        SaveFormat::Svg => svg::encode(image, config.data_uri_format, outlines)
+            .map_err(|e| eprintln!("Failed to encode SVG: {}", e))
+            .ok(),
+    }
+}
+
+// save_format = svg 且 bake_border 时选框不画进像素，而作为 SVG 矢量标注输出；投影扩展的边距不算在选框内
+fn selection_outlines(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    green: bool,
+    config: &Config,
+) -> Vec<svg::Outline> {
+    if !config.bake_border || config.save_format != SaveFormat::Svg {
+        return Vec::new();
+    }
+    let margin = config.shadow_margin as f32;
+    vec![svg::Outline {
+        rect: (
+            margin,
+            margin,
+            image.width() as f32 - margin * 2.0,
+            image.height() as f32 - margin * 2.0,
+        ),
+        color: border_color(green),
+    }]
+}
+
+// 保存的选框颜色：绿框为绿色，否则为红色
+fn border_color(green: bool) -> u32 {
+    if green { 0xFF00FF00 } else { 0xFFFF0000 }
+}
+
+// 生成文件名和归档目录用到的信息：region 为换算到屏幕上的坐标和尺寸，screen 为归档用的屏幕尺寸，
+// ext 为扩展名（也是归档目录的格式子目录），dpi 只用于 save_file_name
+struct NameContext<'a> {
+    region: (i32, i32, u32, u32),
+    screen: (u32, u32),
+    title: Option<&'a str>,
+    ext: &'a str,
+    dpi: Option<u32>,
+    captured_at: Option<SystemTime>,
+}
+
+// 保存文件名（含扩展名）
+fn save_file_name(name: &NameContext, config: &Config) -> String {
+    let mut file_name = output_file_name(name, config);
+
+    // 标记来源显示器的 DPI，便于混合 DPI 环境下区分素材尺寸
+    if let Some(dpi) = name.dpi {
+        file_name.push_str(&format!("_DPI{}", dpi));
+    }
 
     //// 添加子框信息，暂不使用
     // if let Some((sx, sy, sw, sh)) = sub_region {
     //     file_name.push_str(&format!("_Sx{}Sy{}Sw{}Sh{}", sx, sy, sw, sh));
     // }
 
-    file_name.push_str(".webp");
+    // 同名文件已存在时改用时间戳，避免覆盖
+    if name.title.is_some() && std::path::Path::new(&format!("{}.{}", file_name, name.ext)).exists()
+    {
+        return save_file_name(
+            &NameContext {
+                title: None,
+                ..*name
+            },
+            config,
+        );
+    }
 
-    // 裁剪图像
-    let cropped = if let Some((sx, sy, sw, sh)) = sub_region {
-        // 保存绿框内的图像
-        image::imageops::crop_imm(image, sx as u32, sy as u32, sw, sh).to_image()
-    } else {
-        // 保存红框内的图像
-        image::imageops::crop_imm(image, x as u32, y as u32, width, height).to_image()
+    // 时间戳仍重名（capture_time_names 下同一帧保存多次，或同一毫秒内保存）时加序号 _2、_3…
+    let mut candidate = format!("{}.{}", file_name, name.ext);
+    let mut counter = 2;
+    while std::path::Path::new(&candidate).exists() {
+        candidate = format!("{}_{}.{}", file_name, counter, name.ext);
+        counter += 1;
+    }
+    candidate
+}
+
+// title_names 时识别选区第一行文字作为文件名中的标题（已去除文件名非法字符），识别不到时为 None
+fn selection_title(image: &ImageBuffer<Rgba<u8>, Vec<u8>>, config: &Config) -> Option<String> {
+    if !config.title_names {
+        return None;
+    }
+    match ocr::first_line(image) {
+        Ok(text) => text.as_deref().and_then(sanitize_title),
+        Err(e) => {
+            eprintln!("Title detection failed: {}", e);
+            None
+        }
+    }
+}
+
+// 文件名用的标题：非法字符和空白替换为 _，最多 TITLE_MAX_CHARS 个字符，为空时返回 None
+fn sanitize_title(text: &str) -> Option<String> {
+    let mut title = String::new();
+    for c in text.chars() {
+        if c.is_whitespace() || c.is_control() || r#"\/:*?"<>|."#.contains(c) {
+            if !title.is_empty() && !title.ends_with('_') {
+                title.push('_');
+            }
+        } else {
+            title.push(c);
+        }
+        if title.chars().count() >= TITLE_MAX_CHARS {
+            break;
+        }
+    }
+    let title = title.trim_end_matches('_');
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+// 裁剪并应用保存前的变换；选区完全在截图之外时返回 None
+fn process_selection(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    region: (i32, i32, u32, u32),
+    sub_region: Option<(i32, i32, u32, u32)>,
+    shape: SelectionShape,
+    adjust: Adjustment,
+    config: &Config,
+) -> Option<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+    let mut cropped = crop_selection(image, region, sub_region)?;
+    transform::adjust(&mut cropped, adjust);
+
+    // 椭圆选区：内切椭圆之外透明；矩形选区按 corner_radius 切圆角
+    match shape {
+        SelectionShape::Ellipse => transform::ellipse_mask(&mut cropped),
+        SelectionShape::Rectangle => transform::round_corners(&mut cropped, config.corner_radius),
+    }
+
+    // 去除纯色边框
+    if config.auto_trim {
+        cropped = transform::auto_trim(cropped, config.auto_trim_tolerance);
+    }
+
+    // 把选框画进保存的图像：保存绿框时为绿色，否则为红色；放在去边之后，避免边框被当作纯色边去掉。
+    // 保存为 SVG 时选框改为矢量标注（selection_outlines）
+    if config.bake_border && config.save_format != SaveFormat::Svg {
+        transform::bake_border(&mut cropped, border_color(sub_region.is_some()));
+    }
+
+    // 水印放在选框之后、投影之前，落在图像内容上而不是投影边距里
+    watermark::apply(&mut cropped, config);
+
+    // 投影放在最后，扩展后的画布不参与去边
+    if config.shadow_margin > 0 {
+        cropped = transform::drop_shadow(
+            &cropped,
+            config.shadow_margin,
+            config.shadow_color,
+            config.shadow_blur,
+            config.shadow_offset,
+        );
+    }
+
+    // 合成到纯色背景，保存的图像不含透明区域
+    if let Some(background) = config.flatten_background {
+        transform::flatten(&mut cropped, background);
+    }
+    Some(cropped)
+}
+
+// 裁剪要保存的区域：有绿框时保存绿框，否则保存红框；坐标均相对截图（拼接截图时即虚拟桌面）左上角。
+// 区域先与截图范围求交集，交集为空时返回 None
+fn crop_selection(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    region: (i32, i32, u32, u32),
+    sub_region: Option<(i32, i32, u32, u32)>,
+) -> Option<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+    let requested = sub_region.unwrap_or(region);
+    let Some((x, y, width, height)) = clamp_to_image(requested, image.dimensions()) else {
+        eprintln!(
+            "Selection {:?} is outside the {}x{} capture, nothing to crop",
+            requested,
+            image.width(),
+            image.height()
+        );
+        return None;
+    };
+    Some(image::imageops::crop_imm(image, x, y, width, height).to_image())
+}
+
+// 矩形与图像范围 (0, 0, width, height) 的交集，为空时返回 None
+fn clamp_to_image(region: (i32, i32, u32, u32), size: (u32, u32)) -> Option<(u32, u32, u32, u32)> {
+    let (x, y, width, height) = region;
+    let left = (x as i64).clamp(0, size.0 as i64);
+    let top = (y as i64).clamp(0, size.1 as i64);
+    let right = (x as i64 + width as i64).clamp(0, size.0 as i64);
+    let bottom = (y as i64 + height as i64).clamp(0, size.1 as i64);
+    (right > left && bottom > top).then(|| {
+        (
+            left as u32,
+            top as u32,
+            (right - left) as u32,
+            (bottom - top) as u32,
+        )
+    })
+}
+
+// 保存目录：该格式的 format_dir（未配置时为 output_dir）下按屏幕尺寸分 W{w}H{h} 子目录，
+// date_folders 时再按本地日期分 YYYY/MM/DD 子目录；目录不存在时创建
+fn output_dir(
+    screen_width: u32,
+    screen_height: u32,
+    format: &str,
+    captured_at: Option<SystemTime>,
+    config: &Config,
+) -> String {
+    let mut dir_name = format!("W{}H{}", screen_width, screen_height);
+    let base = config.output_dir_for(format);
+    if !base.is_empty() {
+        dir_name = format!("{}/{}", base.trim_end_matches(['/', '\\']), dir_name);
+    }
+    if config.date_folders {
+        let date: chrono::DateTime<chrono::Local> =
+            captured_at.unwrap_or_else(SystemTime::now).into();
+        dir_name = format!("{}/{}", dir_name, date.format("%Y/%m/%d"));
+    }
+    let _ = std::fs::create_dir_all(&dir_name);
+    dir_name
+}
+
+// 生成保存文件名（不含扩展名），并创建所在目录；时间戳和日期子目录按 captured_at，为空时按当前时间
+fn output_file_name(name: &NameContext, config: &Config) -> String {
+    let (x, y, width, height) = name.region;
+    let (screen_width, screen_height) = name.screen;
+    let dir_name = output_dir(
+        screen_width,
+        screen_height,
+        name.ext,
+        name.captured_at,
+        config,
+    );
+
+    // 生成文件名：有标题时用标题代替时间戳
+    let label = match name.title {
+        Some(title) => title.to_string(),
+        None => name
+            .captured_at
+            .unwrap_or_else(SystemTime::now)
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+            .to_string(),
     };
 
-    // 转换为WebP格式（无损）
-    let encoder = Encoder::from_rgba(cropped.as_raw(), cropped.width(), cropped.height());
-    let webp_data: WebPMemory = encoder.encode_lossless();
+    format!(
+        "{}/screenshot_{}_Lx{}Ty{}W{}H{}",
+        dir_name, label, x, y, width, height
+    )
+}
+
+// 转换为WebP格式（无损），embed_srgb_profile 时嵌入 sRGB 配置文件
+fn encode_webp(image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Vec<u8> {
+    metrics::time(Metric::Encode, || {
+        let encoded =
+            Encoder::from_rgba(image.as_raw(), image.width(), image.height()).encode_lossless();
+        icc::tag_webp(&encoded, image)
+    })
+}
+
+// 编码并保存图像，成功返回 true
+fn write_webp(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    file_name: &str,
+    region: (i32, i32, i32, i32),
+) -> bool {
+    write_encoded(&encode_webp(image), file_name, region)
+}
+
+// 最近一次成功保存的选区文件的绝对路径（复制路径热键），保存线程和主线程共用；
+// 只记录原尺寸文件，不含 scale_variants 的其他倍率和临时文件
+static LAST_SAVED_PATH: Mutex<Option<std::path::PathBuf>> = Mutex::new(None);
+
+fn remember_saved_path(file_name: &str) {
+    if let Ok(path) = std::path::absolute(file_name) {
+        *LAST_SAVED_PATH.lock().unwrap() = Some(path);
+    }
+}
 
-    // 保存图像
-    if let Err(e) = std::fs::write(&file_name, webp_data.as_ref()) {
+// 写入已编码的数据，成功返回 true
+fn write_encoded(data: &[u8], file_name: &str, region: (i32, i32, i32, i32)) -> bool {
+    if let Err(e) = std::fs::write(file_name, data) {
         eprintln!("Failed to save image: {}", e);
+        false
     } else {
         println!("Image saved as: {}", file_name);
+        save_hook::run(std::path::Path::new(file_name), data, region);
+        true
+    }
+}
+
+// 截图坐标换算为所截屏幕上的坐标要加的偏移：work_area_only 且任务栏在上边或左边时截图从工作区左上角开始，
+// 偏移为工作区相对屏幕左上角的位置，否则为 0。按屏幕截图时相对该显示器，否则相对主屏；
+// capture_all_screens 时截图坐标本就相对虚拟桌面左上角，为 0
+fn frame_offset(session: &Session, config: &Config) -> (i32, i32) {
+    if session.screen.is_none() && config.capture_all_screens {
+        return (0, 0);
+    }
+    let screen_origin = session.screen.map_or((0, 0), |screen| {
+        (screen.display_info.x, screen.display_info.y)
+    });
+    (
+        session.overlay_origin.0 as i32 - screen_origin.0,
+        session.overlay_origin.1 as i32 - screen_origin.1,
+    )
+}
+
+// 记录最近保存的选区供 --timer 使用：换算为所截屏幕上的坐标（见 frame_offset）
+fn store_last_region(region: (i32, i32, i32, i32), config: &Config, session: &Session) {
+    let (dx, dy) = frame_offset(session, config);
+    let (x, y, w, h) = region;
+    timer::store_last_region((x + dx, y + dy, w, h), session.foreground_rect);
+}
+
+// 导出待导出列表：逐个按保存流程写入（save_format、scale_variants、上传、保存回调等与普通保存相同）和/或打包为一个 zip。
+// 文件名加上序号（_1、_2…），同一框在同一毫秒内导出的多项不会互相覆盖，zip 内也不会重名
+fn export_cart(cart: &mut Vec<CartItem>, config: &Config) {
+    let mut archive = Vec::new();
+
+    for (index, item) in cart.iter().enumerate() {
+        let (x, y, w, h) = item.region;
+        let (dx, dy) = item.offset;
+        let (screen_width, screen_height) = item.screen_size;
+        let file_name = save_file_name(
+            &NameContext {
+                region: (x + dx, y + dy, w as u32, h as u32),
+                screen: item.screen_size,
+                title: None,
+                ext: config.save_format.extension(),
+                dpi: item.dpi,
+                captured_at: item.captured_at,
+            },
+            config,
+        );
+        let extension = config.save_format.extension();
+        let base = file_name
+            .strip_suffix(&format!(".{}", extension))
+            .unwrap_or(&file_name);
+        let file_name = format!("{}_{}.{}", base, index + 1, extension);
+
+        let outlines = selection_outlines(&item.image, item.green, config);
+        if config.cart_export_files {
+            let _ = write_selection(
+                &item.image,
+                file_name.clone(),
+                (x + dx, y + dy, w, h),
+                &outlines,
+                config,
+                &AtomicBool::new(false),
+            );
+        }
+        if config.cart_export_zip
+            && let Some(encoded) = encode_selection(&item.image, &outlines, config)
+        {
+            let entry_name = file_name
+                .rsplit('/')
+                .next()
+                .unwrap_or(&file_name)
+                .to_string();
+            archive.push((entry_name, encoded));
+        }
+    }
+
+    if config.cart_export_zip
+        && let Some(first) = cart.first()
+    {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        let (screen_width, screen_height) = first.screen_size;
+        let zip_name = format!(
+            "{}/export_{}.zip",
+            output_dir(screen_width, screen_height, "zip", None, config),
+            timestamp
+        );
+
+        match cart::write_zip(&zip_name, &archive) {
+            Ok(()) => println!("Exported {} images to: {}", archive.len(), zip_name),
+            Err(e) => eprintln!("Failed to write archive: {}", e),
+        }
+    }
+
+    cart.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bgra_capture_is_swapped_to_rgba_for_display_and_encoding() {
+        // 一个像素：B=0x30 G=0x20 R=0x10 A=0xFF
+        let mut buffer = vec![0x30, 0x20, 0x10, 0xFF];
+        normalize_channels(&mut buffer, ChannelOrder::Bgra);
+        assert_eq!(buffer, [0x10, 0x20, 0x30, 0xFF]);
+
+        let image = ImageBuffer::from_vec(1, 1, buffer).unwrap();
+        let cache = DisplayCache::new(&image, None, 0);
+        assert_eq!(cache.original_buffer[0], 0xFF102030);
+        assert_eq!(image.as_raw()[..3], [0x10, 0x20, 0x30]);
+    }
+
+    #[test]
+    fn rgba_capture_is_left_untouched() {
+        let mut buffer = vec![0x10, 0x20, 0x30, 0xFF];
+        normalize_channels(&mut buffer, ChannelOrder::Rgba);
+        assert_eq!(buffer, [0x10, 0x20, 0x30, 0xFF]);
+    }
+
+    #[test]
+    fn crop_across_two_stitched_screens_of_different_sizes() {
+        // 主屏 4x3（红），副屏 2x5（蓝）位于主屏右侧且上移 1 像素
+        let primary = ImageBuffer::from_pixel(4, 3, Rgba([255, 0, 0, 255]));
+        let secondary = ImageBuffer::from_pixel(2, 5, Rgba([0, 0, 255, 255]));
+        let desktop = desktop::stitch(&[((0, 0), primary), ((4, -1), secondary)]);
+        assert_eq!(desktop.dimensions(), (6, 5));
+
+        // 跨越两屏边界的选区：虚拟桌面中 x 2..6、y 1..3（主屏 y 0..2）
+        let cropped = crop_selection(&desktop, (2, 1, 4, 2), None).unwrap();
+        assert_eq!(cropped.dimensions(), (4, 2));
+        for y in 0..2 {
+            assert_eq!(cropped.get_pixel(0, y).0, [255, 0, 0, 255]);
+            assert_eq!(cropped.get_pixel(1, y).0, [255, 0, 0, 255]);
+            assert_eq!(cropped.get_pixel(2, y).0, [0, 0, 255, 255]);
+            assert_eq!(cropped.get_pixel(3, y).0, [0, 0, 255, 255]);
+        }
+
+        // 主屏下方没有屏幕的区域填充为黑色
+        let cropped = crop_selection(&desktop, (0, 4, 6, 1), None).unwrap();
+        assert_eq!(cropped.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(cropped.get_pixel(5, 0).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn crop_is_clamped_to_the_image_bounds() {
+        let image = ImageBuffer::from_pixel(4, 3, Rgba([255, 0, 0, 255]));
+
+        // 超出左上和右下的部分被裁掉
+        assert_eq!(
+            crop_selection(&image, (-2, -1, 4, 3), None)
+                .unwrap()
+                .dimensions(),
+            (2, 2)
+        );
+        assert_eq!(
+            crop_selection(&image, (3, 2, 5, 5), None)
+                .unwrap()
+                .dimensions(),
+            (1, 1)
+        );
+
+        // 与截图没有交集或尺寸为 0 时不裁剪
+        assert!(crop_selection(&image, (4, 0, 2, 2), None).is_none());
+        assert!(crop_selection(&image, (-3, 0, 3, 2), None).is_none());
+        assert!(crop_selection(&image, (1, 1, 0, 2), None).is_none());
+    }
+
+    #[test]
+    fn magnet_centers_on_the_nearest_feature() {
+        // 黑底上一个白色方块 x 60..70、y 40..46
+        let mut cache = DisplayCache::live(100, 100);
+        for y in 40..46 {
+            for x in 60..70 {
+                cache.original_buffer[y * 100 + x] = 0xFFFFFFFF;
+            }
+        }
+
+        // 边缘像素落在方块边界两侧，外接框为 x 59..=70、y 39..=46
+        assert_eq!(magnet_center(&cache, (50, 50), 32), Some((64, 42)));
+        // 搜索窗口内没有边缘时用原始点击位置
+        assert_eq!(magnet_center(&cache, (10, 10), 20), None);
+    }
+
+    #[test]
+    fn coordinate_format_fills_both_token_styles() {
+        let region = (10, 20, 300, 40);
+        assert_eq!(format_coordinates("%x,%y,%w,%h", region, 0), "10,20,300,40");
+        assert_eq!(
+            format_coordinates(r#"{"x":%x,"y":%y,"w":%w,"h":%h}"#, region, 0),
+            r#"{"x":10,"y":20,"w":300,"h":40}"#
+        );
+        assert_eq!(
+            format_coordinates("{x} {y} {right} {bottom} @{monitor}", region, 1),
+            "10 20 310 60 @1"
+        );
+        // %% 为 %，未知的占位符原样保留
+        assert_eq!(
+            format_coordinates("%x%% %q {z} %", region, 0),
+            "10% %q {z} %"
+        );
     }
 }
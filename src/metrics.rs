@@ -0,0 +1,40 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+// 调试计时（debug_timings）：各阶段最近一次的耗时，保存线程也会写入，因此用原子变量
+#[derive(Debug, Clone, Copy)]
+pub enum Metric {
+    Capture,    // 截屏
+    CacheBuild, // 构建 DisplayCache
+    Render,     // 一帧的绘制和显示
+    Encode,     // WebP 编码
+}
+
+const NAMES: [&str; 4] = ["capture", "cache", "render", "encode"];
+
+// 最近一次耗时（微秒）
+static LAST: [AtomicU64; 4] = [const { AtomicU64::new(0) }; 4];
+
+pub fn record(metric: Metric, elapsed: Duration) {
+    LAST[metric as usize].store(elapsed.as_micros() as u64, Ordering::Relaxed);
+}
+
+// 执行 f 并记录耗时
+pub fn time<T>(metric: Metric, f: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = f();
+    record(metric, started.elapsed());
+    result
+}
+
+// 每项一行，如 "capture: 12.34 ms"
+pub fn lines() -> Vec<String> {
+    NAMES
+        .iter()
+        .zip(&LAST)
+        .map(|(name, micros)| {
+            let micros = micros.load(Ordering::Relaxed);
+            format!("{}: {}.{:02} ms", name, micros / 1000, micros % 1000 / 10)
+        })
+        .collect()
+}
@@ -0,0 +1,147 @@
+use crate::config::Config;
+use std::mem::{size_of, zeroed};
+use std::ptr::null_mut;
+use std::sync::OnceLock;
+use std::time::Duration;
+use winapi::um::playsoundapi::{PlaySoundW, SND_ASYNC, SND_MEMORY, SND_NODEFAULT};
+use winapi::um::shellapi::{
+    NIF_ICON, NIF_INFO, NIIF_ERROR, NIIF_INFO, NIIF_NOSOUND, NIM_ADD, NIM_DELETE, NOTIFYICONDATAW,
+    Shell_NotifyIconW,
+};
+use winapi::um::winuser::{
+    CreateWindowExW, DestroyWindow, HWND_MESSAGE, IDI_APPLICATION, LoadIconW, MB_ICONHAND,
+    MessageBeep,
+};
+
+// 通知气泡的显示时长，之后移除托盘图标
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+// 保存结果反馈：按配置播放快门声、弹出通知（失败时为错误提示音和错误通知）
+pub fn save_result(saved: &Option<String>, config: &Config) {
+    if config.save_sound {
+        match saved {
+            Some(_) => play_shutter(),
+            None => unsafe {
+                MessageBeep(MB_ICONHAND);
+            },
+        }
+    }
+    if config.save_toast {
+        match saved {
+            Some(file_name) => toast("Screenshot saved", file_name, false),
+            None => toast("Screenshot failed", "Failed to save image", true),
+        }
+    }
+}
+
+// 错误提示音
+pub fn error_beep() {
+    unsafe {
+        MessageBeep(MB_ICONHAND);
+    }
+}
+
+// 上传结果通知：成功时显示复制到剪贴板的链接
+pub fn upload_result(result: &Result<String, String>) {
+    match result {
+        Ok(link) => toast("Screenshot uploaded", link, false),
+        Err(e) => toast("Upload failed", e, true),
+    }
+}
+
+fn play_shutter() {
+    // SND_ASYNC 播放期间缓冲区必须保持有效，因此放在静态变量中
+    static SHUTTER: OnceLock<Vec<u8>> = OnceLock::new();
+    let wav = SHUTTER.get_or_init(shutter_wav);
+    unsafe {
+        PlaySoundW(
+            wav.as_ptr() as _,
+            null_mut(),
+            SND_MEMORY | SND_ASYNC | SND_NODEFAULT,
+        );
+    }
+}
+
+// 合成一段约 80ms 的快门声：衰减的白噪声，16 位单声道 WAV
+fn shutter_wav() -> Vec<u8> {
+    const SAMPLE_RATE: u32 = 22050;
+    let samples = SAMPLE_RATE * 80 / 1000;
+
+    let mut seed = 0x2545F491u32;
+    let mut data = Vec::with_capacity(samples as usize * 2);
+    for i in 0..samples {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        let noise = (seed >> 16) as i16 as f32 / i16::MAX as f32;
+        let envelope = (-(i as f32) / (samples as f32 / 6.0)).exp();
+        let sample = (noise * envelope * i16::MAX as f32 * 0.6) as i16;
+        data.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    let mut wav = Vec::with_capacity(44 + data.len());
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // 单声道
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&data);
+    wav
+}
+
+// 通过临时托盘图标显示通知气泡（Windows 10 起显示为系统通知），不阻塞调用方
+fn toast(title: &str, message: &str, error: bool) {
+    let title = title.to_string();
+    let message = message.to_string();
+    std::thread::spawn(move || unsafe {
+        let class: Vec<u16> = "STATIC\0".encode_utf16().collect();
+        let hwnd = CreateWindowExW(
+            0,
+            class.as_ptr(),
+            null_mut(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            null_mut(),
+            null_mut(),
+            null_mut(),
+        );
+        if hwnd.is_null() {
+            return;
+        }
+
+        let mut data: NOTIFYICONDATAW = zeroed();
+        data.cbSize = size_of::<NOTIFYICONDATAW>() as u32;
+        data.hWnd = hwnd;
+        data.uID = 1;
+        data.uFlags = NIF_ICON | NIF_INFO;
+        data.hIcon = LoadIconW(null_mut(), IDI_APPLICATION);
+        data.dwInfoFlags = NIIF_NOSOUND | if error { NIIF_ERROR } else { NIIF_INFO };
+        copy_wide(&mut data.szInfoTitle, &title);
+        copy_wide(&mut data.szInfo, &message);
+
+        if Shell_NotifyIconW(NIM_ADD, &mut data) != 0 {
+            std::thread::sleep(TOAST_DURATION);
+            Shell_NotifyIconW(NIM_DELETE, &mut data);
+        }
+        DestroyWindow(hwnd);
+    });
+}
+
+// 复制到定长 UTF-16 缓冲区，超长时截断并保留结尾的 0
+fn copy_wide(dst: &mut [u16], text: &str) {
+    let max_len = dst.len() - 1;
+    for (slot, unit) in dst.iter_mut().zip(text.encode_utf16().take(max_len)) {
+        *slot = unit;
+    }
+}
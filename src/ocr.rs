@@ -0,0 +1,102 @@
+use image::{ImageBuffer, Rgba};
+use windows::Graphics::Imaging::{BitmapPixelFormat, SoftwareBitmap};
+use windows::Media::Ocr::{OcrEngine, OcrResult};
+use windows::Storage::Streams::DataWriter;
+
+// 文字框四周保留的边距
+const TEXT_BOX_PADDING: i32 = 2;
+
+// 用系统 OCR（Windows.Media.Ocr，按用户语言）识别图像中的文字
+fn recognize(image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> windows::core::Result<OcrResult> {
+    let bgra: Vec<u8> = image
+        .pixels()
+        .flat_map(|p| [p[2], p[1], p[0], p[3]])
+        .collect();
+    let writer = DataWriter::new()?;
+    writer.WriteBytes(&bgra)?;
+    let bitmap = SoftwareBitmap::CreateCopyFromBuffer(
+        &writer.DetachBuffer()?,
+        BitmapPixelFormat::Bgra8,
+        image.width() as i32,
+        image.height() as i32,
+    )?;
+
+    let engine = OcrEngine::TryCreateFromUserProfileLanguages()?;
+    engine.RecognizeAsync(&bitmap)?.get()
+}
+
+// 识别文字行，返回每行的外接矩形（相对图像左上角）
+pub fn text_lines(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+) -> windows::core::Result<Vec<(i32, i32, i32, i32)>> {
+    let result = recognize(image)?;
+
+    let mut lines = Vec::new();
+    for line in result.Lines()? {
+        let mut bounds: Option<(f32, f32, f32, f32)> = None;
+        for word in line.Words()? {
+            let rect = word.BoundingRect()?;
+            let (left, top, right, bottom) =
+                (rect.X, rect.Y, rect.X + rect.Width, rect.Y + rect.Height);
+            bounds = Some(match bounds {
+                Some((l, t, r, b)) => (l.min(left), t.min(top), r.max(right), b.max(bottom)),
+                None => (left, top, right, bottom),
+            });
+        }
+        if let Some((left, top, right, bottom)) = bounds {
+            lines.push((
+                left.floor() as i32,
+                top.floor() as i32,
+                (right - left.floor()).ceil() as i32,
+                (bottom - top.floor()).ceil() as i32,
+            ));
+        }
+    }
+    Ok(lines)
+}
+
+// 识别到的第一行非空文字（按阅读顺序，通常为标题）
+pub fn first_line(image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> windows::core::Result<Option<String>> {
+    for line in recognize(image)?.Lines()? {
+        let text = line.Text()?.to_string();
+        if !text.trim().is_empty() {
+            return Ok(Some(text));
+        }
+    }
+    Ok(None)
+}
+
+// 把上下相邻（行距不超过一行高）且水平方向有重叠的文字行合并为文字块，返回面积最大的块（含边距）
+pub fn largest_block(lines: &[(i32, i32, i32, i32)]) -> Option<(i32, i32, i32, i32)> {
+    let mut lines = lines.to_vec();
+    lines.sort_by_key(|line| line.1);
+
+    // 文字块以 (left, top, right, bottom) 表示
+    let mut blocks: Vec<(i32, i32, i32, i32)> = Vec::new();
+    for (x, y, w, h) in lines {
+        let joined = blocks
+            .iter_mut()
+            .find(|block| y - block.3 <= h && x <= block.2 && x + w >= block.0);
+        match joined {
+            Some(block) => {
+                block.0 = block.0.min(x);
+                block.1 = block.1.min(y);
+                block.2 = block.2.max(x + w);
+                block.3 = block.3.max(y + h);
+            }
+            None => blocks.push((x, y, x + w, y + h)),
+        }
+    }
+
+    blocks
+        .into_iter()
+        .max_by_key(|(left, top, right, bottom)| (right - left) * (bottom - top))
+        .map(|(left, top, right, bottom)| {
+            (
+                left - TEXT_BOX_PADDING,
+                top - TEXT_BOX_PADDING,
+                right - left + TEXT_BOX_PADDING * 2,
+                bottom - top + TEXT_BOX_PADDING * 2,
+            )
+        })
+}
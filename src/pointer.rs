@@ -0,0 +1,99 @@
+use crate::AppEvent;
+use minifb::MouseButton;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+use std::sync::mpsc::Sender;
+use winapi::shared::minwindef::{LPARAM, LRESULT, UINT, WPARAM};
+use winapi::shared::windef::{HWND, POINT};
+use winapi::um::winuser::{
+    CallWindowProcW, GWLP_WNDPROC, GetPointerType, PT_PEN, PT_TOUCH, ScreenToClient,
+    SetWindowLongPtrW, WM_POINTERCAPTURECHANGED, WM_POINTERDOWN, WM_POINTERUP, WM_POINTERUPDATE,
+    WNDPROC,
+};
+
+// 笔和触摸输入：minifb 只轮询鼠标左键，快速点按和笔的按下/抬起容易漏掉。
+// 子类化覆盖窗口，把主指针（第一根手指或笔尖）的 WM_POINTER* 消息直接转换为鼠标事件，
+// 不再交给系统合成鼠标消息；鼠标和触控板仍走原有的鼠标轮询
+
+// 指针消息 wParam 高位中的主指针标志
+const POINTER_FLAG_PRIMARY: usize = 0x2000;
+
+static ORIGINAL_PROC: AtomicIsize = AtomicIsize::new(0);
+static EVENTS: OnceLock<Sender<AppEvent>> = OnceLock::new();
+static IN_CONTACT: AtomicBool = AtomicBool::new(false);
+
+// 替换窗口过程，笔/触摸事件发送到 events（只能安装一次）
+pub fn install(hwnd: HWND, events: Sender<AppEvent>) {
+    if EVENTS.set(events).is_err() {
+        return;
+    }
+    unsafe {
+        let original = SetWindowLongPtrW(hwnd, GWLP_WNDPROC, window_proc as *const () as isize);
+        ORIGINAL_PROC.store(original, Ordering::SeqCst);
+    }
+}
+
+// 笔或手指正接触屏幕；期间跳过鼠标轮询，避免未移动的鼠标位置覆盖笔的位置
+pub fn in_contact() -> bool {
+    IN_CONTACT.load(Ordering::Relaxed)
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if let Some(event) = unsafe { pointer_event(hwnd, msg, wparam, lparam) } {
+        if let Some(events) = EVENTS.get() {
+            let _ = events.send(event);
+        }
+        return 0;
+    }
+
+    unsafe {
+        let original: WNDPROC = std::mem::transmute(ORIGINAL_PROC.load(Ordering::SeqCst));
+        CallWindowProcW(original, hwnd, msg, wparam, lparam)
+    }
+}
+
+// 主指针为笔或触摸时转换为鼠标事件（坐标换算到窗口客户区），其余返回 None
+unsafe fn pointer_event(hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM) -> Option<AppEvent> {
+    if msg == WM_POINTERCAPTURECHANGED && IN_CONTACT.swap(false, Ordering::Relaxed) {
+        return Some(AppEvent::MouseReleased(MouseButton::Left, 0.0, 0.0));
+    }
+    if !matches!(msg, WM_POINTERDOWN | WM_POINTERUPDATE | WM_POINTERUP)
+        || (wparam >> 16) & POINTER_FLAG_PRIMARY == 0
+    {
+        return None;
+    }
+
+    let mut pointer_type = 0;
+    if unsafe { GetPointerType((wparam & 0xFFFF) as u32, &mut pointer_type) } == 0
+        || (pointer_type != PT_PEN && pointer_type != PT_TOUCH)
+    {
+        return None;
+    }
+
+    // lParam 为屏幕坐标
+    let mut point = POINT {
+        x: (lparam & 0xFFFF) as i16 as i32,
+        y: ((lparam >> 16) & 0xFFFF) as i16 as i32,
+    };
+    unsafe {
+        ScreenToClient(hwnd, &mut point);
+    }
+    let (x, y) = (point.x as f32, point.y as f32);
+
+    Some(match msg {
+        WM_POINTERDOWN => {
+            IN_CONTACT.store(true, Ordering::Relaxed);
+            AppEvent::MousePressed(MouseButton::Left, x, y)
+        }
+        WM_POINTERUP => {
+            IN_CONTACT.store(false, Ordering::Relaxed);
+            AppEvent::MouseReleased(MouseButton::Left, x, y)
+        }
+        _ => AppEvent::MouseMoved(x, y),
+    })
+}
@@ -0,0 +1,93 @@
+use crate::capture_frame;
+use crate::config::Config;
+use image::{ImageBuffer, Rgba};
+use screenshots::Screen;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// 一帧预截图：截取时间和图像
+pub type Frame = (Instant, Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>);
+
+// 预截图：后台按 precapture_interval_ms 截屏，只保留最近 precapture_ms 内的帧，
+// 且总大小不超过 precapture_max_mb（单帧超过上限时不保留）
+pub struct PreCapture {
+    frames: Arc<Mutex<VecDeque<Frame>>>,
+    paused: Arc<AtomicBool>,
+    screen: Arc<Mutex<Screen>>, // 与按下截图热键时相同的截取目标（主屏）
+}
+
+impl PreCapture {
+    pub fn spawn(screen: Screen, config: &Config) -> Self {
+        let frames = Arc::new(Mutex::new(VecDeque::new()));
+        let paused = Arc::new(AtomicBool::new(false));
+        let screen = Arc::new(Mutex::new(screen));
+        let (buffer, pause, target) = (
+            Arc::clone(&frames),
+            Arc::clone(&paused),
+            Arc::clone(&screen),
+        );
+        let config = config.clone();
+
+        thread::spawn(move || {
+            let interval = Duration::from_millis(config.precapture_interval_ms.max(1));
+            let span = Duration::from_millis(config.precapture_ms);
+            let max_bytes = config.precapture_max_mb * 1024 * 1024;
+            // 连续失败时只报告第一次，成功后重新报告
+            let mut failing = false;
+
+            loop {
+                let started = Instant::now();
+                if !pause.load(Ordering::Relaxed) {
+                    let screen = *target.lock().unwrap();
+                    match capture_frame(&screen, &config) {
+                        Ok(image) => {
+                            failing = false;
+                            let capacity = max_bytes / image.as_raw().len().max(1);
+                            let mut frames = buffer.lock().unwrap();
+                            frames.push_back((Instant::now(), Arc::new(image)));
+                            while frames.len() > capacity
+                                || frames.front().is_some_and(|(at, _)| at.elapsed() > span)
+                            {
+                                frames.pop_front();
+                            }
+                        }
+                        Err(e) if !failing => {
+                            eprintln!(
+                                "Pre-capture failed: {} (not reported again until it works)",
+                                e
+                            );
+                            failing = true;
+                        }
+                        Err(_) => {}
+                    }
+                }
+                thread::sleep(interval.saturating_sub(started.elapsed()));
+            }
+        });
+
+        Self {
+            frames,
+            paused,
+            screen,
+        }
+    }
+
+    // 显示器变化后换成新的主屏，丢弃按旧屏幕截取的帧
+    pub fn set_screen(&self, screen: Screen) {
+        *self.screen.lock().unwrap() = screen;
+        self.frames.lock().unwrap().clear();
+    }
+
+    // 截图窗口显示期间暂停后台截屏
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    // 当前缓冲的帧（从旧到新），与缓冲区共享图像数据
+    pub fn snapshot(&self) -> Vec<Frame> {
+        self.frames.lock().unwrap().iter().cloned().collect()
+    }
+}
@@ -0,0 +1,146 @@
+use crate::config::Config;
+use crate::window_info::{process_windows, with_popups};
+use crate::{NameContext, capture_region, output_file_name, sanitize_title, write_webp};
+use image::{ImageBuffer, Rgba};
+use screenshots::Screen;
+use std::mem::{size_of, zeroed};
+use std::ptr::null_mut;
+use winapi::shared::windef::HWND;
+use winapi::um::wingdi::{
+    BI_RGB, BITMAPINFO, BITMAPINFOHEADER, CreateCompatibleBitmap, CreateCompatibleDC,
+    DIB_RGB_COLORS, DeleteDC, DeleteObject, GetDIBits, SelectObject,
+};
+use winapi::um::winuser::{GetDC, PW_RENDERFULLCONTENT, PrintWindow, ReleaseDC};
+
+// 进程窗口截图（--process-windows <进程名>）：用 PrintWindow 逐个截取该进程的可见顶层窗口，
+// 被遮挡的窗口也能截到完整内容；文件名带窗口标题，最后报告截取的数量。
+// include_popups 时窗口打开了菜单等弹出窗口（PrintWindow 截不到）则改为从屏幕截取包含弹出窗口的区域
+pub fn run(process: &str, config: &Config) {
+    let windows = process_windows(process);
+    if windows.is_empty() {
+        eprintln!("No visible windows found for process '{}'", process);
+        return;
+    }
+
+    let screens = match Screen::all() {
+        Ok(screens) if !screens.is_empty() => screens,
+        Ok(_) => {
+            eprintln!("Failed to enumerate screens: no screen found");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Failed to enumerate screens: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let primary_screen = &screens[0];
+    let (screen_width, screen_height) = (
+        primary_screen.display_info.width,
+        primary_screen.display_info.height,
+    );
+
+    let mut saved = 0;
+    for (hwnd, window) in &windows {
+        let rect = if config.include_popups {
+            with_popups(*hwnd, window.rect, &windows)
+        } else {
+            window.rect
+        };
+        let (x, y, w, h) = rect;
+        let image = if rect == window.rect {
+            print_window(*hwnd, w, h)
+        } else {
+            capture_from_screen(rect, config)
+        };
+        let Some(image) = image else {
+            eprintln!("Failed to capture window '{}'", window.title);
+            continue;
+        };
+        let title = sanitize_title(&window.title);
+        let file_name = format!(
+            "{}.webp",
+            output_file_name(
+                &NameContext {
+                    region: (x, y, w as u32, h as u32),
+                    screen: (screen_width, screen_height),
+                    title: title.as_deref(),
+                    ext: "webp",
+                    dpi: None,
+                    captured_at: None,
+                },
+                config,
+            )
+        );
+        if write_webp(&image, &file_name, rect) {
+            saved += 1;
+        }
+    }
+    println!(
+        "Captured {}/{} windows of '{}'",
+        saved,
+        windows.len(),
+        process
+    );
+}
+
+// 从窗口中心所在的屏幕截取桌面区域（超出该屏幕的部分被裁掉）
+fn capture_from_screen(
+    rect: (i32, i32, i32, i32),
+    config: &Config,
+) -> Option<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+    let (x, y, w, h) = rect;
+    let screen = Screen::from_point(x + w / 2, y + h / 2).ok()?;
+    let info = &screen.display_info;
+    capture_region(
+        &screen,
+        (x - info.x, y - info.y, w as u32, h as u32),
+        config.channel_order,
+    )
+    .map_err(|e| eprintln!("Failed to capture window area: {}", e))
+    .ok()
+}
+
+// 让窗口把自身绘制到内存位图中（PW_RENDERFULLCONTENT 以便截到 DirectX/硬件加速的内容），返回 RGBA 图像
+fn print_window(hwnd: HWND, width: i32, height: i32) -> Option<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+    unsafe {
+        let screen_dc = GetDC(null_mut());
+        let dc = CreateCompatibleDC(screen_dc);
+        let bitmap = CreateCompatibleBitmap(screen_dc, width, height);
+        let previous = SelectObject(dc, bitmap as _);
+        let printed = PrintWindow(hwnd, dc, PW_RENDERFULLCONTENT) != 0;
+        // GetDIBits 要求位图未被选入设备上下文
+        SelectObject(dc, previous);
+
+        let mut info: BITMAPINFO = zeroed();
+        info.bmiHeader.biSize = size_of::<BITMAPINFOHEADER>() as u32;
+        info.bmiHeader.biWidth = width;
+        info.bmiHeader.biHeight = -height; // 负高度：自上而下
+        info.bmiHeader.biPlanes = 1;
+        info.bmiHeader.biBitCount = 32;
+        info.bmiHeader.biCompression = BI_RGB;
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        let lines = GetDIBits(
+            dc,
+            bitmap,
+            0,
+            height as u32,
+            pixels.as_mut_ptr() as _,
+            &mut info,
+            DIB_RGB_COLORS,
+        );
+
+        DeleteObject(bitmap as _);
+        DeleteDC(dc);
+        ReleaseDC(null_mut(), screen_dc);
+        if !printed || lines != height {
+            return None;
+        }
+
+        // BGRX -> RGBA，GDI 位图的 alpha 无意义，统一设为不透明
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+            pixel[3] = 255;
+        }
+        ImageBuffer::from_vec(width as u32, height as u32, pixels)
+    }
+}
@@ -0,0 +1,39 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+// 保存回调：每个文件成功写入磁盘之后调用（写盘失败或被取消的文件不调用），
+// 在历史记录和通知之前、保存线程或主线程上同步执行。参数为文件路径、写入的编码数据、
+// 选区 (x, y, w, h)（截图坐标，coordinate_space = monitor 时相对所在显示器）。
+// scale_variants 的每个文件、导出列表的每个文件各调用一次
+pub trait SaveHook: Send {
+    fn saved(&mut self, path: &Path, data: &[u8], region: (i32, i32, i32, i32));
+}
+
+impl<F> SaveHook for F
+where
+    F: FnMut(&Path, &[u8], (i32, i32, i32, i32)) + Send,
+{
+    fn saved(&mut self, path: &Path, data: &[u8], region: (i32, i32, i32, i32)) {
+        self(path, data, region)
+    }
+}
+
+// 默认回调：不做任何处理
+pub struct NoopHook;
+
+impl SaveHook for NoopHook {
+    fn saved(&mut self, _path: &Path, _data: &[u8], _region: (i32, i32, i32, i32)) {}
+}
+
+static HOOKS: Mutex<Vec<Box<dyn SaveHook>>> = Mutex::new(Vec::new());
+
+// 注册回调，按注册顺序依次调用
+pub fn register(hook: impl SaveHook + 'static) {
+    HOOKS.lock().unwrap().push(Box::new(hook));
+}
+
+pub fn run(path: &Path, data: &[u8], region: (i32, i32, i32, i32)) {
+    for hook in HOOKS.lock().unwrap().iter_mut() {
+        hook.saved(path, data, region);
+    }
+}
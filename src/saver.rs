@@ -0,0 +1,164 @@
+use crate::config::{Config, SelectionShape};
+use crate::history;
+use crate::notify;
+use crate::transform::Adjustment;
+use crate::{
+    AppEvent, NameContext, process_selection, sanitize_title, save_file_name, selection_outlines,
+    selection_title, write_selection,
+};
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Sender, SyncSender, TrySendError, sync_channel};
+use std::thread;
+use std::time::SystemTime;
+
+// 后台保存队列上限，队列满时退回同步保存，避免无限堆积
+const SAVE_QUEUE_LIMIT: usize = 4;
+
+// 一次保存任务：共享截图帧（Arc，无需复制整帧）以及要保存的区域
+pub struct SaveJob {
+    pub image: Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+    pub region: (i32, i32, i32, i32),
+    pub sub_region: Option<(i32, i32, i32, i32)>,
+    pub screen_width: u32,
+    pub screen_height: u32,
+    pub dpi: Option<u32>, // 来源显示器 DPI（dpi_tag 启用时）
+    pub shape: SelectionShape,
+    pub adjust: Adjustment,    // 亮度/对比度调整（adjust_keys 时按键设置）
+    pub title: Option<String>, // 文件名中的标题（窗口选择时为窗口标题），title_names 时为空则 OCR 识别
+    pub captured_at: Option<SystemTime>, // 文件名时间戳和日期子目录所用时刻，为空时为保存时刻
+    pub offset: (i32, i32),    // 文件名和保存回调中加到选区坐标上的偏移（coordinate_offset）
+    pub region_line: Option<String>, // --print-region：保存成功后打印到标准输出的选区坐标行
+}
+
+// 保存任务被取消（在写入文件之前）
+pub struct Cancelled;
+
+// 选区保存成功的结果
+#[derive(Debug, Clone)]
+pub enum Saved {
+    Written(String),      // 已写入该文件
+    UploadedOnly(String), // upload_save_file = false：只交给后台上传，没有写入磁盘
+    Duplicate(String),    // skip_duplicate_saves：与上一次保存的像素相同，没有写入，为上一次的文件
+}
+
+impl SaveJob {
+    // 依次裁剪、编码、写入；每个阶段之间检查 cancel，已取消时不写入文件
+    pub fn run(&self, config: &Config, cancel: &AtomicBool) -> Result<Option<Saved>, Cancelled> {
+        let check = || {
+            if cancel.load(Ordering::Relaxed) {
+                Err(Cancelled)
+            } else {
+                Ok(())
+            }
+        };
+
+        let (x, y, w, h) = self.region;
+        let sub_region = self
+            .sub_region
+            .map(|(sx, sy, sw, sh)| (sx, sy, sw as u32, sh as u32));
+
+        check()?;
+        let Some(cropped) = process_selection(
+            &self.image,
+            (x, y, w as u32, h as u32),
+            sub_region,
+            self.shape,
+            self.adjust,
+            config,
+        ) else {
+            return Ok(None);
+        };
+
+        // 窗口标题优先，否则识别选区文字
+        let title = match &self.title {
+            Some(title) if config.title_names => sanitize_title(title),
+            _ => None,
+        }
+        .or_else(|| selection_title(&cropped, config));
+        check()?;
+        let (dx, dy) = self.offset;
+        let file_name = save_file_name(
+            &NameContext {
+                region: (x + dx, y + dy, w as u32, h as u32),
+                screen: (self.screen_width, self.screen_height),
+                title: title.as_deref(),
+                ext: config.save_format.extension(),
+                dpi: self.dpi,
+                captured_at: self.captured_at,
+            },
+            config,
+        );
+        let region = self.sub_region.unwrap_or(self.region);
+        let outlines = selection_outlines(&cropped, self.sub_region.is_some(), config);
+        let (rx, ry, rw, rh) = region;
+        let saved = write_selection(
+            &cropped,
+            file_name,
+            (rx + dx, ry + dy, rw, rh),
+            &outlines,
+            config,
+            cancel,
+        )?;
+
+        match &saved {
+            // 记录到历史
+            Some(Saved::Written(file_name)) => {
+                if config.history_size > 0 {
+                    history::record(&self.image, region, file_name, config.history_size);
+                }
+                notify::save_result(&Some(file_name.clone()), config);
+            }
+            // 只上传时没有文件，结果由上传通知；重复的选区没有新文件，不记录也不通知
+            Some(Saved::UploadedOnly(_) | Saved::Duplicate(_)) => {}
+            None => notify::save_result(&None, config),
+        }
+        if saved.is_some()
+            && let Some(line) = &self.region_line
+        {
+            println!("{}", line);
+        }
+        Ok(saved)
+    }
+}
+
+// 后台保存线程：编码和写盘在此完成，结果以 AppEvent::SaveCompleted 发回主循环
+pub struct SaveWorker {
+    jobs: SyncSender<SaveJob>,
+    cancel: Arc<AtomicBool>, // 取消正在进行的保存
+}
+
+impl SaveWorker {
+    pub fn spawn(config: Config, events: Sender<AppEvent>) -> Self {
+        let (jobs, queue) = sync_channel::<SaveJob>(SAVE_QUEUE_LIMIT);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let token = Arc::clone(&cancel);
+        thread::spawn(move || {
+            for job in queue {
+                // 取消只作用于开始之后的任务
+                token.store(false, Ordering::Relaxed);
+                let event = match job.run(&config, &token) {
+                    Ok(saved) => AppEvent::SaveCompleted(saved),
+                    Err(Cancelled) => AppEvent::SaveCancelled,
+                };
+                if events.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { jobs, cancel }
+    }
+
+    // 通知保存线程放弃当前任务
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    // 提交任务；队列已满或线程已退出时把任务交还调用方
+    pub fn submit(&self, job: SaveJob) -> Result<(), SaveJob> {
+        self.jobs.try_send(job).map_err(|e| match e {
+            TrySendError::Full(job) | TrySendError::Disconnected(job) => job,
+        })
+    }
+}
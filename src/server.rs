@@ -0,0 +1,99 @@
+use crate::config::Config;
+use crate::{capture_region, capture_screen_area, encode_webp};
+use screenshots::Screen;
+use std::io::{BufRead, Write};
+
+// 常驻服务模式（--server）：从标准输入逐行读取命令，每条命令回复一行结果
+//
+//   capture <monitor> <x,y,w,h|full> <path>   截取指定屏幕的区域并保存为 WebP
+//   screens                                   列出屏幕：<index> <x>,<y> <width>x<height>
+//   quit                                      退出
+//
+// 回复以 "ok" 或 "error" 开头
+pub fn run(config: &Config) {
+    let screens = match Screen::all() {
+        Ok(screens) => screens,
+        Err(e) => {
+            println!("error failed to enumerate screens: {}", e);
+            return;
+        }
+    };
+
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let args: Vec<&str> = line.split_whitespace().collect();
+
+        let reply = match args.as_slice() {
+            [] => continue,
+            ["quit"] | ["exit"] | ["shutdown"] => {
+                let _ = writeln!(stdout, "ok bye");
+                break;
+            }
+            ["screens"] => {
+                let list: Vec<String> = screens
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| {
+                        let info = &s.display_info;
+                        format!("{} {},{} {}x{}", i, info.x, info.y, info.width, info.height)
+                    })
+                    .collect();
+                format!("ok {}", list.join("; "))
+            }
+            ["capture", monitor, region, path @ ..] if !path.is_empty() => {
+                match capture(&screens, monitor, region, &path.join(" "), config) {
+                    Ok(saved) => format!("ok {}", saved),
+                    Err(e) => format!("error {}", e),
+                }
+            }
+            _ => format!("error unknown command '{}'", line.trim()),
+        };
+
+        let _ = writeln!(stdout, "{}", reply);
+        let _ = stdout.flush();
+    }
+}
+
+fn capture(
+    screens: &[Screen],
+    monitor: &str,
+    region: &str,
+    path: &str,
+    config: &Config,
+) -> Result<String, String> {
+    let screen = monitor
+        .parse::<usize>()
+        .ok()
+        .and_then(|i| screens.get(i))
+        .ok_or_else(|| format!("no such monitor '{}'", monitor))?;
+
+    // 指定区域时只截取该区域
+    let cropped = if region == "full" {
+        capture_screen_area(screen, config)
+    } else {
+        let (x, y, w, h) = crate::config::parse_region(region)?;
+        let (width, height) = (screen.display_info.width, screen.display_info.height);
+        // 用 i64 比较，x + w 在 i32 上溢出时也不会越界通过
+        if x < 0
+            || y < 0
+            || x as i64 + w as i64 > width as i64
+            || y as i64 + h as i64 > height as i64
+        {
+            return Err(format!(
+                "region {} is outside the {}x{} screen",
+                region, width, height
+            ));
+        }
+        capture_region(screen, (x, y, w as u32, h as u32), config.channel_order)
+    }
+    .map_err(|e| format!("capture failed: {}", e))?;
+
+    let webp_data = encode_webp(&cropped);
+    std::fs::write(path, webp_data).map_err(|e| format!("failed to write {}: {}", path, e))?;
+    Ok(path.to_string())
+}
@@ -0,0 +1,140 @@
+use crate::config::{CONFIG_FILE, Config, Hotkey, SaveFormat};
+use crate::font;
+use minifb::{Key, KeyRepeat, Window};
+
+// 向导面板尺寸与文字缩放
+const PANEL_WIDTH: u32 = 760;
+const PANEL_HEIGHT: u32 = 300;
+const TEXT_SCALE: u32 = 2;
+const LINE_HEIGHT: i32 = (font::GLYPH_HEIGHT * TEXT_SCALE + 18) as i32;
+
+// 配置文件不存在时视为首次运行
+pub fn needed() -> bool {
+    !std::path::Path::new(CONFIG_FILE).exists()
+}
+
+// 首次运行向导：选择保存目录、保存格式，确认截图热键后写入配置文件；ESC 跳过（下次启动仍会显示）
+pub fn run(window: &mut Window, config: &mut Config) {
+    let (width, height) = window.get_size();
+    let mut buffer = vec![0xFF181818u32; width * height];
+    window.set_title("captrue setup");
+
+    while window.is_open() {
+        if window.is_key_pressed(Key::Escape, KeyRepeat::No) {
+            return;
+        }
+        if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+            match config.write_setup() {
+                Ok(()) => println!("Settings written to {}", CONFIG_FILE),
+                Err(e) => eprintln!("Failed to write {}: {}", CONFIG_FILE, e),
+            }
+            return;
+        }
+        if window.is_key_pressed(Key::O, KeyRepeat::No)
+            && crate::Modifiers::poll().flags() == 0
+            && let Some(dir) = rfd::FileDialog::new().pick_folder()
+        {
+            config.output_dir = dir.display().to_string();
+        }
+        // F 在 WebP 与 SVG 之间切换保存格式
+        if window.is_key_pressed(Key::F, KeyRepeat::No) && crate::Modifiers::poll().flags() == 0 {
+            config.save_format = match config.save_format {
+                SaveFormat::Webp => SaveFormat::Svg,
+                SaveFormat::Svg => SaveFormat::Webp,
+            };
+        }
+
+        // 按住修饰键再按一个键即设为截图热键
+        let modifiers = crate::Modifiers::poll().flags();
+        if modifiers != 0
+            && let Some(hotkey) = window
+                .get_keys_pressed(KeyRepeat::No)
+                .into_iter()
+                .find_map(|key| Hotkey::from_key(modifiers, key))
+        {
+            config.capture_hotkey = hotkey;
+        }
+
+        draw(&mut buffer, width as u32, height as u32, config);
+        if window.update_with_buffer(&buffer, width, height).is_err() {
+            return;
+        }
+    }
+}
+
+fn draw(buffer: &mut [u32], width: u32, height: u32, config: &Config) {
+    buffer.fill(0xFF181818);
+
+    let left = width.saturating_sub(PANEL_WIDTH) / 2;
+    let top = height.saturating_sub(PANEL_HEIGHT) / 2;
+    for y in top..(top + PANEL_HEIGHT).min(height) {
+        let row = (y * width) as usize;
+        buffer[row + left as usize..row + (left + PANEL_WIDTH).min(width) as usize]
+            .fill(0xFF2A2A2A);
+    }
+
+    // 路径过长时只显示末尾部分
+    let max_chars = ((PANEL_WIDTH - 48) / ((font::GLYPH_WIDTH + 1) * TEXT_SCALE)) as usize;
+    let output_dir = if config.output_dir.is_empty() {
+        "(working directory)".to_string()
+    } else if config.output_dir.chars().count() + 15 > max_chars {
+        let tail: String = config
+            .output_dir
+            .chars()
+            .rev()
+            .take(max_chars.saturating_sub(18))
+            .collect();
+        format!("...{}", tail.chars().rev().collect::<String>())
+    } else {
+        config.output_dir.clone()
+    };
+    let title = "CAPTRUE SETUP";
+    font::draw_text(
+        buffer,
+        width,
+        height,
+        title,
+        font::TextStyle {
+            x: (left + PANEL_WIDTH.saturating_sub(font::text_width(title, TEXT_SCALE)) / 2) as i32,
+            y: top as i32 + 20,
+            color: 0xFFFFFFFF,
+            scale: TEXT_SCALE,
+        },
+    );
+
+    let lines = [
+        (format!("Output folder: {}", output_dir), 0xFFE0E0E0),
+        ("  O: choose folder".to_string(), 0xFF909090),
+        (
+            match config.save_format {
+                SaveFormat::Webp => "Format: WebP (lossless)   F: switch to SVG".to_string(),
+                SaveFormat::Svg => "Format: SVG (embedded image)   F: switch to WebP".to_string(),
+            },
+            0xFFE0E0E0,
+        ),
+        (
+            format!("Capture hotkey: {}", config.capture_hotkey),
+            0xFFE0E0E0,
+        ),
+        (
+            "  hold modifiers and press a key to change".to_string(),
+            0xFF909090,
+        ),
+        (format!("Save key: {}", config.save_hotkey), 0xFFE0E0E0),
+        ("Enter: save settings   Esc: skip".to_string(), 0xFF40C040),
+    ];
+    for (i, (text, color)) in lines.iter().enumerate() {
+        font::draw_text(
+            buffer,
+            width,
+            height,
+            text,
+            font::TextStyle {
+                x: left as i32 + 24,
+                y: top as i32 + 20 + (i as i32 + 1) * LINE_HEIGHT,
+                color: *color,
+                scale: TEXT_SCALE,
+            },
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{blend, font};
+use image::{ImageBuffer, Rgba};
+
+// 编号标记（N 键进入放置模式后单击放置）：实心圆，圆心为序号；坐标为截图坐标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stamp {
+    pub center: (i32, i32),
+    pub number: u32,
+}
+
+// 在 ARGB 缓冲区中画一个标记：抗锯齿的实心圆，序号按圆的大小放大后居中，
+// 浅色圆用黑字、深色圆用白字；超出缓冲区的部分被裁掉
+pub fn draw(buffer: &mut [u32], width: u32, height: u32, stamp: &Stamp, radius: u32, color: u32) {
+    let (cx, cy) = stamp.center;
+    let r = radius as i32;
+    let alpha = (color >> 24) as f32;
+    for y in (cy - r - 1).max(0)..(cy + r + 2).min(height as i32) {
+        for x in (cx - r - 1).max(0)..(cx + r + 2).min(width as i32) {
+            let distance = (((x - cx) * (x - cx) + (y - cy) * (y - cy)) as f32).sqrt();
+            let coverage = (radius as f32 + 0.5 - distance).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                let idx = y as usize * width as usize + x as usize;
+                let a = (alpha * coverage) as u32;
+                buffer[idx] = blend(buffer[idx], (a << 24) | (color & 0xFFFFFF));
+            }
+        }
+    }
+
+    let text = stamp.number.to_string();
+    // 字高约为直径的一半，至少 1 倍
+    let scale = (radius / font::GLYPH_HEIGHT).max(1);
+    let text_width = font::text_width(&text, scale) as i32;
+    let text_height = (font::GLYPH_HEIGHT * scale) as i32;
+    let [r, g, b] = [(color >> 16) & 0xFF, (color >> 8) & 0xFF, color & 0xFF];
+    let luma = (r * 299 + g * 587 + b * 114) / 1000;
+    let text_color = if luma > 160 { 0xFF000000 } else { 0xFFFFFFFF };
+    font::draw_text(
+        buffer,
+        width,
+        height,
+        &text,
+        font::TextStyle {
+            x: cx - text_width / 2,
+            y: cy - text_height / 2,
+            color: text_color,
+            scale,
+        },
+    );
+}
+
+// 把标记画进图像：只转换每个标记周围的小块像素，不复制整张图
+pub fn bake(image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, stamps: &[Stamp], radius: u32, color: u32) {
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    let r = radius as i32 + 1;
+    for stamp in stamps {
+        let (cx, cy) = stamp.center;
+        let (left, top) = ((cx - r).max(0), (cy - r).max(0));
+        let (right, bottom) = ((cx + r + 1).min(width), (cy + r + 1).min(height));
+        if right <= left || bottom <= top {
+            continue;
+        }
+        let (patch_width, patch_height) = ((right - left) as u32, (bottom - top) as u32);
+
+        let mut patch: Vec<u32> = Vec::with_capacity((patch_width * patch_height) as usize);
+        for y in top..bottom {
+            for x in left..right {
+                let [r, g, b, a] = image.get_pixel(x as u32, y as u32).0;
+                patch.push((a as u32) << 24 | (r as u32) << 16 | (g as u32) << 8 | b as u32);
+            }
+        }
+        let local = Stamp {
+            center: (cx - left, cy - top),
+            ..*stamp
+        };
+        draw(&mut patch, patch_width, patch_height, &local, radius, color);
+
+        for (i, argb) in patch.into_iter().enumerate() {
+            let (x, y) = (
+                left as u32 + i as u32 % patch_width,
+                top as u32 + i as u32 / patch_width,
+            );
+            image.put_pixel(
+                x,
+                y,
+                Rgba([
+                    (argb >> 16) as u8,
+                    (argb >> 8) as u8,
+                    argb as u8,
+                    (argb >> 24) as u8,
+                ]),
+            );
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use crate::clipboard::data_uri;
+use crate::config::DataUriFormat;
+use crate::{encode_webp, icc};
+use image::{ImageBuffer, Rgba};
+
+// 矢量标注：1px 矩形描边 (x, y, w, h)，颜色为 0xAARRGGBB
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Outline {
+    pub rect: (f32, f32, f32, f32),
+    pub color: u32,
+}
+
+impl Outline {
+    // 随图像一起缩放
+    pub fn scaled(self, factor: f32) -> Self {
+        let (x, y, w, h) = self.rect;
+        Self {
+            rect: (x * factor, y * factor, w * factor, h * factor),
+            ..self
+        }
+    }
+}
+
+// 生成 SVG：图像按 raster 格式编码后以 base64 内嵌为 <image>，尺寸与图像一致；标注输出为可编辑的 <rect>
+pub fn encode(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    raster: DataUriFormat,
+    outlines: &[Outline],
+) -> Result<Vec<u8>, String> {
+    let (mime, data) = match raster {
+        DataUriFormat::Webp => ("image/webp", encode_webp(image)),
+        DataUriFormat::Png => ("image/png", icc::encode_png(image)?),
+    };
+    let (width, height) = image.dimensions();
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+         width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n\
+         <image width=\"{w}\" height=\"{h}\" style=\"image-rendering:pixelated\" xlink:href=\"{uri}\"/>\n",
+        w = width,
+        h = height,
+        uri = data_uri(mime, &data)
+    );
+    for outline in outlines {
+        // 描边以线条中心定位，内缩半个像素使 1px 线条正好落在最外圈像素上
+        let (x, y, w, h) = outline.rect;
+        svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"#{:06X}\" stroke-width=\"1\"/>\n",
+            x + 0.5,
+            y + 0.5,
+            (w - 1.0).max(0.0),
+            (h - 1.0).max(0.0),
+            outline.color & 0xFFFFFF
+        ));
+    }
+    svg.push_str("</svg>\n");
+    Ok(svg.into_bytes())
+}
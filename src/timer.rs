@@ -0,0 +1,182 @@
+use crate::config::{Config, parse_region};
+use crate::hotkey::{self, HotkeyGuard};
+use crate::window_info::foreground_window_rect;
+use crate::{
+    STOP_HOTKEY_ID, capture_region, clamp_to_image, coordinate_offset, save_image_webp,
+    selection_dpi,
+};
+use screenshots::Screen;
+use std::mem::zeroed;
+use std::ptr::null_mut;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, channel};
+use std::thread;
+use std::time::Duration;
+use winapi::um::winuser::{GetMessageW, MSG, WM_HOTKEY};
+
+// 上次保存的区域，供定时截图在未配置 timer_region 时使用
+const LAST_REGION_FILE: &str = "captrue.last";
+
+// 上次保存的区域：绝对坐标，以及相对前台窗口左上角的坐标（relative_to_window 时记录）
+pub struct LastRegion {
+    pub absolute: (i32, i32, i32, i32),
+    pub relative: Option<(i32, i32, i32, i32)>,
+}
+
+impl LastRegion {
+    // 有相对坐标时按前台窗口当前位置换算，窗口移动后区域跟随
+    pub fn resolve(&self) -> (i32, i32, i32, i32) {
+        match (self.relative, foreground_window_rect()) {
+            (Some((rx, ry, w, h)), Some((wx, wy, _, _))) => (wx + rx, wy + ry, w, h),
+            _ => self.absolute,
+        }
+    }
+}
+
+// 文件第一行为绝对坐标，第二行（可选）为相对窗口的坐标
+pub fn store_last_region(region: (i32, i32, i32, i32), window_rect: Option<(i32, i32, i32, i32)>) {
+    let (x, y, w, h) = region;
+    let mut text = format!("{},{},{},{}", x, y, w, h);
+    if let Some((wx, wy, _, _)) = window_rect {
+        text.push_str(&format!("\n{},{},{},{}", x - wx, y - wy, w, h));
+    }
+    if let Err(e) = std::fs::write(LAST_REGION_FILE, text) {
+        eprintln!("Failed to store last region: {}", e);
+    }
+}
+
+pub fn load_last_region() -> Option<LastRegion> {
+    let text = std::fs::read_to_string(LAST_REGION_FILE).ok()?;
+    let mut lines = text.lines();
+    let absolute = parse_region(lines.next()?.trim()).ok()?;
+    let relative = lines.next().and_then(|line| parse_region(line.trim()).ok());
+    Some(LastRegion { absolute, relative })
+}
+
+// 停止热键线程：按下 stop_hotkey 时向返回的通道发送一次
+pub fn spawn_stop_listener(config: &Config) -> Receiver<()> {
+    let (tx, rx) = channel();
+    let stop_hotkey = config.stop_hotkey;
+    thread::spawn(move || {
+        let mut hotkeys = HotkeyGuard::new();
+        if !hotkeys.register(STOP_HOTKEY_ID, stop_hotkey) {
+            eprintln!("Failed to register stop hotkey {}", stop_hotkey);
+        }
+
+        let mut msg: MSG = unsafe { zeroed() };
+        while unsafe { GetMessageW(&mut msg, null_mut(), 0, 0) } > 0 {
+            if msg.message == WM_HOTKEY && msg.wParam as i32 == STOP_HOTKEY_ID {
+                let _ = tx.send(());
+                break;
+            }
+        }
+    });
+    rx
+}
+
+// 定时截图：不显示窗口，按间隔截取并保存区域，直到按下停止热键
+pub fn run(config: &Config) {
+    let region = match config.timer_region {
+        Some(absolute) => Some(LastRegion {
+            absolute,
+            relative: None,
+        }),
+        None => load_last_region(),
+    };
+    let Some(region) = region else {
+        eprintln!(
+            "No region for timed capture: set timer_region in captrue.ini or save a region first"
+        );
+        return;
+    };
+
+    let rx = spawn_stop_listener(config);
+
+    let screens = match Screen::all() {
+        Ok(screens) if !screens.is_empty() => screens,
+        Ok(_) => {
+            eprintln!("Failed to enumerate screens: no screen found");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Failed to enumerate screens: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let interval = Duration::from_millis(config.timer_interval_ms);
+
+    let (x, y, w, h) = region.absolute;
+    println!(
+        "Timed capture of Lx{}Ty{}W{}H{}{} every {} ms, press {} to stop",
+        x,
+        y,
+        w,
+        h,
+        if region.relative.is_some() {
+            " (relative to the foreground window)"
+        } else {
+            ""
+        },
+        config.timer_interval_ms,
+        config.stop_hotkey
+    );
+
+    loop {
+        let (x, y, w, h) = region.resolve();
+        let screen = screen_at(&screens, (x + w / 2, y + h / 2));
+        let info = &screen.display_info;
+        // 选区超出其所在屏幕时 capture_region 只截取屏幕内的部分，文件名和坐标也按这部分计算
+        let Some((left, top, width, height)) = clamp_to_image(
+            (x - info.x, y - info.y, w as u32, h as u32),
+            (info.width, info.height),
+        ) else {
+            eprintln!("Timer region {},{} {}x{} is outside the screen", x, y, w, h);
+            match rx.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => continue,
+                _ => break,
+            }
+        };
+        let (x, y) = (left as i32 + info.x, top as i32 + info.y);
+        let clamped = (x, y, width as i32, height as i32);
+        let local = (left as i32, top as i32, width, height);
+        match capture_region(screen, local, config.channel_order) {
+            Ok(image) => {
+                save_image_webp(
+                    &image,
+                    x,
+                    y,
+                    width,
+                    height,
+                    info.width,
+                    info.height,
+                    None,
+                    selection_dpi(clamped, (0, 0), config),
+                    coordinate_offset(clamped, (0, 0), (0, 0), config),
+                    config,
+                );
+            }
+            Err(e) => eprintln!("Failed to capture screen: {}", e),
+        }
+
+        match rx.recv_timeout(interval) {
+            Err(RecvTimeoutError::Timeout) => continue,
+            _ => break,
+        }
+    }
+
+    hotkey::shutdown();
+    println!("Timed capture stopped");
+}
+
+// 虚拟桌面上的点所在的屏幕，不在任何屏幕上时为主屏
+fn screen_at(screens: &[Screen], (x, y): (i32, i32)) -> &Screen {
+    screens
+        .iter()
+        .find(|screen| {
+            let info = &screen.display_info;
+            x >= info.x
+                && y >= info.y
+                && (x as i64) < info.x as i64 + info.width as i64
+                && (y as i64) < info.y as i64 + info.height as i64
+        })
+        .unwrap_or(&screens[0])
+}
@@ -0,0 +1,270 @@
+use image::imageops::FilterType;
+use image::{ImageBuffer, Rgba};
+
+// 裁剪后的图像变换（保存前应用）
+
+// 颜色各通道差值都不超过容差时视为相同
+fn similar(a: &Rgba<u8>, b: &Rgba<u8>, tolerance: u8) -> bool {
+    a.0.iter()
+        .zip(b.0.iter())
+        .all(|(x, y)| x.abs_diff(*y) <= tolerance)
+}
+
+// 去除四周与左上角颜色一致的行/列，返回内容区域 (x, y, w, h)；
+// 整张图都是同一颜色时返回 None（不裁剪）
+pub fn trim_bounds(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    tolerance: u8,
+) -> Option<(u32, u32, u32, u32)> {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let background = *image.get_pixel(0, 0);
+    let row_uniform =
+        |y: u32| (0..width).all(|x| similar(image.get_pixel(x, y), &background, tolerance));
+
+    let top = (0..height).find(|&y| !row_uniform(y))?;
+    let bottom = (top..height).rev().find(|&y| !row_uniform(y))?;
+    let column_uniform =
+        |x: u32| (top..=bottom).all(|y| similar(image.get_pixel(x, y), &background, tolerance));
+    let left = (0..width).find(|&x| !column_uniform(x))?;
+    let right = (left..width).rev().find(|&x| !column_uniform(x))?;
+
+    Some((left, top, right - left + 1, bottom - top + 1))
+}
+
+// 自动去除纯色边框
+pub fn auto_trim(
+    image: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    tolerance: u8,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    match trim_bounds(&image, tolerance) {
+        Some((x, y, w, h)) if (w, h) != image.dimensions() => {
+            image::imageops::crop_imm(&image, x, y, w, h).to_image()
+        }
+        _ => image,
+    }
+}
+
+// 椭圆遮罩：像素中心落在内切椭圆之外的像素 alpha 置 0
+pub fn ellipse_mask(image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>) {
+    let (width, height) = image.dimensions();
+    let (rx, ry) = (width as f32 / 2.0, height as f32 / 2.0);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let dx = (x as f32 + 0.5 - rx) / rx;
+        let dy = (y as f32 + 0.5 - ry) / ry;
+        if dx * dx + dy * dy > 1.0 {
+            pixel[3] = 0;
+        }
+    }
+}
+
+// 圆角遮罩：四角圆弧之外的像素 alpha 置 0，半径限制为短边的一半
+pub fn round_corners(image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, radius: u32) {
+    let (width, height) = image.dimensions();
+    let radius = radius.min(width / 2).min(height / 2);
+    if radius == 0 {
+        return;
+    }
+    let r = radius as f32;
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        // 到所在角圆心的距离，不在四角时为 0
+        let dx = if x < radius {
+            r - (x as f32 + 0.5)
+        } else if x >= width - radius {
+            x as f32 + 0.5 - (width - radius) as f32
+        } else {
+            0.0
+        };
+        let dy = if y < radius {
+            r - (y as f32 + 0.5)
+        } else if y >= height - radius {
+            y as f32 + 0.5 - (height - radius) as f32
+        } else {
+            0.0
+        };
+        if dx > 0.0 && dy > 0.0 && dx * dx + dy * dy > r * r {
+            pixel[3] = 0;
+        }
+    }
+}
+
+// 投影：画布四周扩展 margin，在右下偏移 offset 处按原图 alpha 生成阴影（ARGB 颜色），
+// 三次盒式模糊近似高斯模糊，再把原图叠加在中央；超出画布的阴影被裁掉
+pub fn drop_shadow(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    margin: u32,
+    color: u32,
+    blur: u32,
+    offset: u32,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let (width, height) = (image.width() + margin * 2, image.height() + margin * 2);
+    let (w, h) = (width as usize, height as usize);
+
+    // 阴影的不透明度
+    let mut alpha = vec![0f32; w * h];
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let (sx, sy) = (
+            (x + margin + offset) as usize,
+            (y + margin + offset) as usize,
+        );
+        if sx < w && sy < h {
+            alpha[sy * w + sx] = pixel[3] as f32 / 255.0;
+        }
+    }
+    let radius = (blur / 3) as usize;
+    if radius > 0 {
+        for _ in 0..3 {
+            box_blur(&mut alpha, w, h, radius, 1, w);
+            box_blur(&mut alpha, h, w, radius, w, 1);
+        }
+    }
+
+    let strength = (color >> 24) as f32;
+    let [r, g, b] = [(color >> 16) as u8, (color >> 8) as u8, color as u8];
+    let mut canvas = ImageBuffer::from_fn(width, height, |x, y| {
+        let a = alpha[y as usize * w + x as usize] * strength;
+        Rgba([r, g, b, a.round().clamp(0.0, 255.0) as u8])
+    });
+    image::imageops::overlay(&mut canvas, image, margin as i64, margin as i64);
+    canvas
+}
+
+// 一维盒式模糊：对 lines 条长度为 len 的线逐条处理，step 为线内相邻元素的间隔，stride 为相邻线的间隔
+fn box_blur(
+    values: &mut [f32],
+    len: usize,
+    lines: usize,
+    radius: usize,
+    step: usize,
+    stride: usize,
+) {
+    let window = (radius * 2 + 1) as f32;
+    let mut line = vec![0f32; len];
+    for l in 0..lines {
+        for (i, v) in line.iter_mut().enumerate() {
+            *v = values[l * stride + i * step];
+        }
+        let mut sum: f32 = line[..radius.min(len)].iter().sum();
+        for i in 0..len {
+            if i + radius < len {
+                sum += line[i + radius];
+            }
+            if i > radius {
+                sum -= line[i - radius - 1];
+            }
+            values[l * stride + i * step] = sum / window;
+        }
+    }
+}
+
+// 把图像按 alpha 合成到纯色背景（ARGB，忽略背景的 alpha）上，结果完全不透明
+pub fn flatten(image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, background: u32) {
+    let bg = [
+        (background >> 16) as u8,
+        (background >> 8) as u8,
+        background as u8,
+    ];
+    for pixel in image.pixels_mut() {
+        let alpha = pixel[3] as u32;
+        for (channel, &bg) in pixel.0[..3].iter_mut().zip(bg.iter()) {
+            *channel = ((*channel as u32 * alpha + bg as u32 * (255 - alpha) + 127) / 255) as u8;
+        }
+        pixel[3] = 255;
+    }
+}
+
+// 亮度/对比度调整：contrast 为以 128 为中心的拉伸百分比，brightness 为再加到各通道上的偏移，均以 0 为不变
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Adjustment {
+    pub brightness: i32,
+    pub contrast: i32,
+}
+
+impl Adjustment {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // 通道值映射表，预览和保存共用
+    pub fn lut(&self) -> [u8; 256] {
+        let factor = (100 + self.contrast) as f32 / 100.0;
+        let mut lut = [0u8; 256];
+        for (value, slot) in lut.iter_mut().enumerate() {
+            let adjusted = (value as f32 - 128.0) * factor + 128.0 + self.brightness as f32;
+            *slot = adjusted.round().clamp(0.0, 255.0) as u8;
+        }
+        lut
+    }
+}
+
+// 按映射表调整 ARGB 像素的 RGB 通道，alpha 不变
+pub fn adjust_argb(pixel: u32, lut: &[u8; 256]) -> u32 {
+    let r = lut[(pixel >> 16) as u8 as usize] as u32;
+    let g = lut[(pixel >> 8) as u8 as usize] as u32;
+    let b = lut[pixel as u8 as usize] as u32;
+    (pixel & 0xFF000000) | (r << 16) | (g << 8) | b
+}
+
+// 对图像应用亮度/对比度调整，alpha 不变
+pub fn adjust(image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, adjustment: Adjustment) {
+    if adjustment.is_identity() {
+        return;
+    }
+    let lut = adjustment.lut();
+    for pixel in image.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = lut[*channel as usize];
+        }
+    }
+}
+
+// 在图像最外圈画 1px 不透明边框（ARGB，忽略 alpha），与截图时显示的选框位置一致
+pub fn bake_border(image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, color: u32) {
+    let (width, height) = image.dimensions();
+    let pixel = Rgba([(color >> 16) as u8, (color >> 8) as u8, color as u8, 255]);
+    for x in 0..width {
+        image.put_pixel(x, 0, pixel);
+        image.put_pixel(x, height - 1, pixel);
+    }
+    for y in 0..height {
+        image.put_pixel(0, y, pixel);
+        image.put_pixel(width - 1, y, pixel);
+    }
+}
+
+// 把 next 拼接到 base 的右侧（vertical 时为下方），较短的一边用透明像素补齐
+pub fn append(
+    base: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    next: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    vertical: bool,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let (width, height, offset) = if vertical {
+        (
+            base.width().max(next.width()),
+            base.height() + next.height(),
+            (0, base.height()),
+        )
+    } else {
+        (
+            base.width() + next.width(),
+            base.height().max(next.height()),
+            (base.width(), 0),
+        )
+    };
+    let mut combined = ImageBuffer::new(width, height);
+    image::imageops::replace(&mut combined, base, 0, 0);
+    image::imageops::replace(&mut combined, next, offset.0 as i64, offset.1 as i64);
+    combined
+}
+
+// 按比例缩放（Lanczos3）；宽高分别四舍五入且至少为 1 像素，因此宽高比可能有不超过 1 像素的偏差
+pub fn scale(image: &ImageBuffer<Rgba<u8>, Vec<u8>>, ratio: f32) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    if ratio == 1.0 {
+        return image.clone();
+    }
+    let width = ((image.width() as f32 * ratio).round() as u32).max(1);
+    let height = ((image.height() as f32 * ratio).round() as u32).max(1);
+    image::imageops::resize(image, width, height, FilterType::Lanczos3)
+}
@@ -0,0 +1,107 @@
+use crate::clipboard;
+use crate::config::Config;
+use crate::notify;
+use windows::Data::Json::{IJsonValue, JsonValue, JsonValueType};
+use windows::Foundation::Uri;
+use windows::Storage::Streams::DataWriter;
+use windows::Web::Http::Headers::HttpMediaTypeHeaderValue;
+use windows::Web::Http::{HttpBufferContent, HttpClient, HttpMultipartFormDataContent};
+use windows::core::{ComInterface, HSTRING};
+
+// 后台上传（upload_url 非空时）：把保存的编码数据以 multipart/form-data POST 到 upload_url，
+// 从响应中按 upload_response_path 取出链接并复制到剪贴板；结果打印到控制台，save_toast 时弹出通知。
+// 在新线程中执行，不阻塞界面和保存
+pub fn spawn(data: Vec<u8>, file_name: &str, config: &Config) {
+    let url = config.upload_url.clone();
+    let field = config.upload_field.clone();
+    let path = config.upload_response_path.clone();
+    let toast = config.save_toast;
+    // 只上传文件名部分，不带保存目录
+    let file_name = std::path::Path::new(file_name).file_name().map_or_else(
+        || file_name.to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    std::thread::spawn(move || {
+        let result = upload(&data, &file_name, &url, &field)
+            .and_then(|body| response_link(&body, &path))
+            .and_then(|link| clipboard::set_text(&link).map(|_| link));
+        match &result {
+            Ok(link) => println!("Uploaded {}: {} (copied to clipboard)", file_name, link),
+            Err(e) => eprintln!("Failed to upload {}: {}", file_name, e),
+        }
+        if toast {
+            notify::upload_result(&result);
+        }
+    });
+}
+
+// 发送请求并返回响应正文；非 2xx 状态码视为失败
+fn upload(data: &[u8], file_name: &str, url: &str, field: &str) -> Result<String, String> {
+    let send = || -> windows::core::Result<(bool, String, String)> {
+        let writer = DataWriter::new()?;
+        writer.WriteBytes(data)?;
+        let file = HttpBufferContent::CreateFromBuffer(&writer.DetachBuffer()?)?;
+        file.Headers()?
+            .SetContentType(&HttpMediaTypeHeaderValue::Create(&HSTRING::from(
+                mime_type(file_name),
+            ))?)?;
+        let form = HttpMultipartFormDataContent::new()?;
+        form.AddWithNameAndFileName(&file, &HSTRING::from(field), &HSTRING::from(file_name))?;
+
+        let client = HttpClient::new()?;
+        let response = client
+            .PostAsync(&Uri::CreateUri(&HSTRING::from(url))?, &form)?
+            .get()?;
+        let status = format!("{} {}", response.StatusCode()?.0, response.ReasonPhrase()?);
+        let body = response.Content()?.ReadAsStringAsync()?.get()?.to_string();
+        Ok((response.IsSuccessStatusCode()?, status, body))
+    };
+    match send() {
+        Ok((true, _, body)) => Ok(body),
+        Ok((false, status, _)) => Err(format!("server returned {}", status)),
+        Err(e) => Err(e.message().to_string()),
+    }
+}
+
+// 按扩展名确定上传文件的 Content-Type
+fn mime_type(file_name: &str) -> &'static str {
+    match file_name
+        .rsplit('.')
+        .next()
+        .map(|ext| ext.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        _ => "application/octet-stream",
+    }
+}
+
+// 从响应中取出链接：path 为空时整个正文即链接；否则按 JSON 解析，
+// path 以点分隔逐级取字段，数字段为数组下标（如 "data.url"、"files.0.url"）
+fn response_link(body: &str, path: &str) -> Result<String, String> {
+    if path.is_empty() {
+        let link = body.trim();
+        return if link.is_empty() {
+            Err("empty response".to_string())
+        } else {
+            Ok(link.to_string())
+        };
+    }
+
+    let lookup = || -> windows::core::Result<String> {
+        let mut value: IJsonValue = JsonValue::Parse(&HSTRING::from(body))?.cast()?;
+        for segment in path.split('.') {
+            value = match (value.ValueType()?, segment.parse::<u32>()) {
+                (JsonValueType::Array, Ok(index)) => value.GetArray()?.GetAt(index)?,
+                _ => value
+                    .GetObject()?
+                    .GetNamedValue(&HSTRING::from(segment))?
+                    .cast()?,
+            };
+        }
+        Ok(value.GetString()?.to_string())
+    };
+    lookup().map_err(|_| format!("no string at '{}' in response: {}", path, body.trim()))
+}
@@ -0,0 +1,281 @@
+use crate::config::Config;
+use crate::{blend, font};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+// 可查看的图片扩展名（SVG 不支持，不列出）
+const IMAGE_EXTENSIONS: [&str; 5] = ["webp", "png", "jpg", "jpeg", "bmp"];
+// 顶部状态栏高度
+const STATUS_HEIGHT: usize = 24;
+// 可查看的最大像素数，更大的文件不解码，显示错误信息
+const MAX_PIXELS: u64 = 64_000_000;
+
+// 解码后的 ARGB 像素和尺寸
+type Pixels = (Vec<u32>, u32, u32);
+
+// 当前文件的解码状态
+enum Image {
+    Loading,
+    Ready(Arc<Pixels>),
+    Failed(String),
+}
+
+// 查看已保存的截图：输出目录下的图片文件（最新的在前），左右方向键切换，Delete 确认后删除。
+// 图片在后台线程解码，结果放在共享的 image 中，克隆 Viewer 不复制像素
+#[derive(Clone)]
+pub struct Viewer {
+    files: Vec<PathBuf>,
+    index: usize,
+    image: Arc<Mutex<(PathBuf, Image)>>, // 正在显示的文件及其解码状态
+    pub confirm_delete: bool,            // 已按 Delete，等待按 Y 确认
+}
+
+impl Viewer {
+    // 列出输出目录中的图片并显示最新的一张，没有图片时返回 None
+    pub fn open(config: &Config) -> Option<Self> {
+        let files = list(config);
+        if files.is_empty() {
+            return None;
+        }
+        let viewer = Self {
+            image: Arc::new(Mutex::new((files[0].clone(), Image::Loading))),
+            files,
+            index: 0,
+            confirm_delete: false,
+        };
+        viewer.load();
+        Some(viewer)
+    }
+
+    // 在后台线程解码当前文件；解码完成时已切换到其他文件则丢弃结果
+    fn load(&self) {
+        let path = self.files[self.index].clone();
+        *self.image.lock().unwrap() = (path.clone(), Image::Loading);
+        let slot = Arc::clone(&self.image);
+        thread::spawn(move || {
+            let image = match decode(&path) {
+                Ok(pixels) => Image::Ready(Arc::new(pixels)),
+                Err(e) => Image::Failed(e),
+            };
+            let mut slot = slot.lock().unwrap();
+            if slot.0 == path {
+                slot.1 = image;
+            }
+        });
+    }
+
+    // 切换到前一张（-1）或后一张（1），到两端时循环
+    pub fn step(&mut self, delta: isize) {
+        let count = self.files.len() as isize;
+        self.index = (self.index as isize + delta).rem_euclid(count) as usize;
+        self.load();
+        self.confirm_delete = false;
+    }
+
+    // 删除当前文件并显示下一张；删除后没有剩余文件时返回 false
+    pub fn delete_current(&mut self) -> bool {
+        self.confirm_delete = false;
+        let path = &self.files[self.index];
+        if let Err(e) = std::fs::remove_file(path) {
+            eprintln!("Failed to delete {}: {}", path.display(), e);
+            return true;
+        }
+        println!("Deleted {}", path.display());
+        self.files.remove(self.index);
+        if self.files.is_empty() {
+            return false;
+        }
+        self.index = self.index.min(self.files.len() - 1);
+        self.load();
+        true
+    }
+
+    pub fn title(&self) -> String {
+        let name = self.current_name();
+        if self.confirm_delete {
+            format!(
+                "Delete {}? Press Y to confirm, any other key to keep it",
+                name
+            )
+        } else {
+            format!(
+                "Viewer {}/{}: {} - Left/Right to browse, Delete to remove, ESC to close",
+                self.index + 1,
+                self.files.len(),
+                name
+            )
+        }
+    }
+
+    fn current_name(&self) -> String {
+        let path = &self.files[self.index];
+        path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    }
+
+    // 深色背景上按比例缩小（不放大）居中显示当前图片，顶部为序号和文件名；解码中或读取失败时显示提示
+    pub fn draw(&self, buffer: &mut [u32], width: usize) {
+        buffer.fill(0xFF202020);
+        let height = buffer.len() / width.max(1);
+        let area_height = height.saturating_sub(STATUS_HEIGHT);
+
+        let message = match &self.image.lock().unwrap().1 {
+            Image::Loading => Some(("Loading...".to_string(), 0xFFFFFFFF)),
+            Image::Failed(e) => Some((format!("Cannot open this file: {}", e), 0xFFFF6060)),
+            Image::Ready(pixels) => {
+                let (pixels, image_width, image_height) = &**pixels;
+                let (iw, ih) = (*image_width as usize, *image_height as usize);
+                let scale = (width as f32 / iw as f32)
+                    .min(area_height as f32 / ih as f32)
+                    .min(1.0);
+                let (dw, dh) = (
+                    ((iw as f32 * scale) as usize).max(1),
+                    ((ih as f32 * scale) as usize).max(1),
+                );
+                let left = (width.saturating_sub(dw)) / 2;
+                let top = STATUS_HEIGHT + area_height.saturating_sub(dh) / 2;
+                for y in 0..dh.min(height.saturating_sub(top)) {
+                    let sy = ((y as f32 / scale) as usize).min(ih - 1);
+                    for x in 0..dw.min(width - left) {
+                        let sx = ((x as f32 / scale) as usize).min(iw - 1);
+                        buffer[(top + y) * width + left + x] =
+                            blend(0xFF202020, pixels[sy * iw + sx]);
+                    }
+                }
+                None
+            }
+        };
+        if let Some((message, color)) = message {
+            let text_width = font::text_width(&message, 2) as i32;
+            font::draw_text(
+                buffer,
+                width as u32,
+                height as u32,
+                &message,
+                font::TextStyle {
+                    x: ((width as i32 - text_width) / 2).max(0),
+                    y: height as i32 / 2,
+                    color,
+                    scale: 2,
+                },
+            );
+        }
+
+        let status = if self.confirm_delete {
+            format!("Delete {}? Y / any key", self.current_name())
+        } else {
+            format!(
+                "{}/{}  {}",
+                self.index + 1,
+                self.files.len(),
+                self.current_name()
+            )
+        };
+        let color = if self.confirm_delete {
+            0xFFFF6060
+        } else {
+            0xFFFFFFFF
+        };
+        font::draw_text(
+            buffer,
+            width as u32,
+            height as u32,
+            &status,
+            font::TextStyle {
+                x: 8,
+                y: 5,
+                color,
+                scale: 2,
+            },
+        );
+    }
+}
+
+// 各保存根目录（output_dir 和 format_dir 覆盖的目录，未配置时为工作目录）下 W{宽}H{高} 子目录中的图片文件，
+// 按修改时间从新到旧
+fn list(config: &Config) -> Vec<PathBuf> {
+    let mut roots: Vec<&str> = vec![&config.output_dir];
+    for (_, dir) in &config.format_dirs {
+        if !roots.contains(&dir.as_str()) {
+            roots.push(dir);
+        }
+    }
+    let mut files = Vec::new();
+    for root in roots {
+        let base = match root {
+            "" => Path::new("."),
+            dir => Path::new(dir),
+        };
+        let Ok(entries) = std::fs::read_dir(base) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() && is_size_folder(&entry.file_name().to_string_lossy()) {
+                collect(&path, &mut files);
+            }
+        }
+    }
+    files.sort();
+    files.dedup();
+    let modified = |path: &PathBuf| {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    };
+    files.sort_by_cached_key(|path| std::cmp::Reverse(modified(path)));
+    files
+}
+
+// 截图按屏幕尺寸归档的目录名，如 W1920H1080
+fn is_size_folder(name: &str) -> bool {
+    name.strip_prefix('W')
+        .and_then(|rest| rest.split_once('H'))
+        .is_some_and(|(width, height)| {
+            !width.is_empty()
+                && !height.is_empty()
+                && width.bytes().all(|b| b.is_ascii_digit())
+                && height.bytes().all(|b| b.is_ascii_digit())
+        })
+}
+
+// 递归收集目录中的图片文件（含 date_folders 的日期子目录）
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect(&path, files);
+        } else if path.extension().is_some_and(|ext| {
+            IMAGE_EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        }) {
+            files.push(path);
+        }
+    }
+}
+
+// 读取图片为 ARGB 像素；超过 MAX_PIXELS 的图片不解码
+fn decode(path: &Path) -> Result<Pixels, String> {
+    let (width, height) = image::image_dimensions(path).map_err(|e| e.to_string())?;
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return Err(format!("{}x{} is too large to view", width, height));
+    }
+    let image = image::open(path).map_err(|e| e.to_string())?.to_rgba8();
+    let (width, height) = image.dimensions();
+    let pixels = image
+        .pixels()
+        .map(|p| {
+            let [r, g, b, a] = p.0;
+            (a as u32) << 24 | (r as u32) << 16 | (g as u32) << 8 | b as u32
+        })
+        .collect();
+    Ok((pixels, width, height))
+}
@@ -0,0 +1,109 @@
+use crate::config::{Config, WatermarkCorner};
+use crate::font;
+use image::{ImageBuffer, Rgba, RgbaImage};
+use std::sync::{Arc, Mutex};
+
+// 水印与图像边缘的距离
+const MARGIN: u32 = 8;
+
+// 已读取的水印图片：(路径, 图片)，读取失败时为 None，同一路径不再重复读取和报错
+static LOADED: Mutex<Option<(String, Option<Arc<RgbaImage>>)>> = Mutex::new(None);
+
+// 把水印合成到保存的图像中：watermark_image 为图片路径时按其 alpha 混合，否则绘制 watermark_text
+// （点阵字体，字号随图像大小放大）；放在 watermark_corner 指定的角，整体不透明度为 watermark_opacity%。
+// 两者都未配置、图片读取失败或图像放不下水印时不做任何修改
+pub fn apply(image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, config: &Config) {
+    let mark = if !config.watermark_image.is_empty() {
+        match load_image(&config.watermark_image) {
+            Some(mark) => mark,
+            None => return,
+        }
+    } else if !config.watermark_text.is_empty() {
+        let scale = (image.width().min(image.height()) / 200).max(1);
+        Arc::new(render_text(
+            &config.watermark_text,
+            config.watermark_color,
+            scale,
+        ))
+    } else {
+        return;
+    };
+
+    let (width, height) = image.dimensions();
+    let (mark_width, mark_height) = mark.dimensions();
+    if mark_width + MARGIN * 2 > width || mark_height + MARGIN * 2 > height {
+        return;
+    }
+    let left = match config.watermark_corner {
+        WatermarkCorner::TopLeft | WatermarkCorner::BottomLeft => MARGIN,
+        WatermarkCorner::TopRight | WatermarkCorner::BottomRight => width - mark_width - MARGIN,
+    };
+    let top = match config.watermark_corner {
+        WatermarkCorner::TopLeft | WatermarkCorner::TopRight => MARGIN,
+        WatermarkCorner::BottomLeft | WatermarkCorner::BottomRight => height - mark_height - MARGIN,
+    };
+
+    let opacity = config.watermark_opacity.min(100);
+    for (x, y, source) in mark.enumerate_pixels() {
+        let alpha = source[3] as u32 * opacity / 100;
+        if alpha == 0 {
+            continue;
+        }
+        let pixel = image.get_pixel_mut(left + x, top + y);
+        for channel in 0..3 {
+            pixel[channel] = ((source[channel] as u32 * alpha
+                + pixel[channel] as u32 * (255 - alpha))
+                / 255) as u8;
+        }
+        pixel[3] = pixel[3].max(alpha as u8);
+    }
+}
+
+// 水印图片只在第一次保存时读取一次，之后复用
+fn load_image(path: &str) -> Option<Arc<RgbaImage>> {
+    let mut loaded = LOADED.lock().unwrap();
+    if let Some((loaded_path, mark)) = loaded.as_ref()
+        && loaded_path == path
+    {
+        return mark.clone();
+    }
+    let mark = match image::open(path) {
+        Ok(mark) => Some(Arc::new(mark.to_rgba8())),
+        Err(e) => {
+            eprintln!("Failed to load watermark image '{}': {}", path, e);
+            None
+        }
+    };
+    *loaded = Some((path.to_string(), mark.clone()));
+    mark
+}
+
+// 用点阵字体把一行文字画成透明底的 RGBA 图像
+fn render_text(text: &str, color: u32, scale: u32) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let (width, height) = (
+        font::text_width(text, scale).max(1),
+        font::GLYPH_HEIGHT * scale,
+    );
+    let mut buffer = vec![0u32; (width * height) as usize];
+    font::draw_text(
+        &mut buffer,
+        width,
+        height,
+        text,
+        font::TextStyle {
+            x: 0,
+            y: 0,
+            color,
+            scale,
+        },
+    );
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let argb = buffer[(y * width + x) as usize];
+        Rgba([
+            (argb >> 16) as u8,
+            (argb >> 8) as u8,
+            argb as u8,
+            (argb >> 24) as u8,
+        ])
+    })
+}
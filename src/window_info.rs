@@ -0,0 +1,331 @@
+use std::mem::{size_of, zeroed};
+use std::ptr::null_mut;
+use winapi::shared::minwindef::{BOOL, DWORD, LPARAM, TRUE};
+use winapi::shared::windef::{HMONITOR, HWND, POINT, RECT};
+use winapi::shared::winerror::S_OK;
+use winapi::um::dwmapi::{DWMWA_CLOAKED, DWMWA_EXTENDED_FRAME_BOUNDS, DwmGetWindowAttribute};
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::processthreadsapi::OpenProcess;
+use winapi::um::shellscalingapi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
+use winapi::um::winbase::QueryFullProcessImageNameW;
+use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+use winapi::um::winuser::{
+    EnumChildWindows, EnumWindows, GW_OWNER, GWL_STYLE, GetForegroundWindow, GetMonitorInfoW,
+    GetWindow, GetWindowLongW, GetWindowRect, GetWindowTextW, GetWindowThreadProcessId, IsIconic,
+    IsWindowVisible, MONITOR_DEFAULTTONEAREST, MONITORINFO, MonitorFromPoint,
+    SetWindowDisplayAffinity, WS_POPUP,
+};
+
+// Windows 10 2004 起支持：窗口仍然显示，但不会出现在截图中（winapi 未定义）
+const WDA_EXCLUDEFROMCAPTURE: DWORD = 0x00000011;
+
+// 前台窗口在屏幕上的矩形 (x, y, w, h)
+pub fn foreground_window_rect() -> Option<(i32, i32, i32, i32)> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.is_null() {
+        return None;
+    }
+    window_rect(hwnd)
+}
+
+// 前台窗口的可见边框 (x, y, w, h)：DWM 扩展边框不含 Windows 10 起不可见的缩放边，取不到时退回窗口矩形；
+// include_popups 时并入它打开的弹出窗口；前台是 exclude（覆盖窗口自身）或最小化时返回 None
+pub fn foreground_window_bounds(
+    exclude: HWND,
+    include_popups: bool,
+) -> Option<(i32, i32, i32, i32)> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.is_null() || hwnd == exclude || unsafe { IsIconic(hwnd) } != 0 {
+        return None;
+    }
+    let bounds = frame_bounds(hwnd)?;
+    if include_popups {
+        return Some(with_popups(hwnd, bounds, &visible_windows(exclude)));
+    }
+    Some(bounds)
+}
+
+// 窗口的可见边框，DWM 取不到时为窗口矩形
+fn frame_bounds(hwnd: HWND) -> Option<(i32, i32, i32, i32)> {
+    unsafe {
+        let mut rect: RECT = zeroed();
+        if DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_EXTENDED_FRAME_BOUNDS,
+            &mut rect as *mut RECT as _,
+            size_of::<RECT>() as DWORD,
+        ) == S_OK
+        {
+            return Some((
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+            ));
+        }
+    }
+    window_rect(hwnd)
+}
+
+// 顶层窗口：标题和屏幕上的矩形 (x, y, w, h)
+#[derive(Debug, Clone)]
+pub struct TopLevelWindow {
+    pub title: String,
+    pub rect: (i32, i32, i32, i32),
+}
+
+// 按 Z 序（从上到下）列出顶层窗口，跳过不可见、最小化、被系统隐藏（cloaked）、零尺寸的窗口以及 exclude；
+// include_popups 时每个窗口的矩形并入它打开的弹出窗口
+pub fn top_level_windows(exclude: HWND, include_popups: bool) -> Vec<TopLevelWindow> {
+    let windows = visible_windows(exclude);
+    windows
+        .iter()
+        .map(|(hwnd, window)| TopLevelWindow {
+            title: window.title.clone(),
+            rect: if include_popups {
+                with_popups(*hwnd, window.rect, &windows)
+            } else {
+                window.rect
+            },
+        })
+        .collect()
+}
+
+// 窗口矩形与其弹出窗口矩形的并集。弹出窗口指 windows 中所有者链（GW_OWNER）指向 hwnd 的窗口（提示框、对话框），
+// 以及同一线程创建、与窗口重叠或相接的 WS_POPUP 窗口（菜单、下拉列表，它们没有所有者但会画到窗口范围之外）；
+// 不与窗口相接的 WS_POPUP 窗口多为同一程序的其他主窗口，不并入
+pub fn with_popups(
+    hwnd: HWND,
+    rect: (i32, i32, i32, i32),
+    windows: &[(HWND, TopLevelWindow)],
+) -> (i32, i32, i32, i32) {
+    let thread = unsafe { GetWindowThreadProcessId(hwnd, null_mut()) };
+    let (mut left, mut top, mut right, mut bottom) =
+        (rect.0, rect.1, rect.0 + rect.2, rect.1 + rect.3);
+    for (candidate, window) in windows {
+        if *candidate == hwnd {
+            continue;
+        }
+        let popup = unsafe {
+            GetWindowThreadProcessId(*candidate, null_mut()) == thread
+                && GetWindowLongW(*candidate, GWL_STYLE) as u32 & WS_POPUP != 0
+        } && touches(rect, window.rect);
+        if popup || is_owned_by(*candidate, hwnd) {
+            let (x, y, w, h) = window.rect;
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x + w);
+            bottom = bottom.max(y + h);
+        }
+    }
+    (left, top, right - left, bottom - top)
+}
+
+// 可点选的界面元素：顶层窗口的可见边框，以及（children 时）它的可见子窗口（控件），均为屏幕坐标
+#[derive(Debug, Clone)]
+pub struct UiElement {
+    pub rect: (i32, i32, i32, i32),
+    pub children: Vec<(i32, i32, i32, i32)>,
+}
+
+// 按 Z 序（从上到下）列出界面元素，顶层窗口的筛选同 top_level_windows
+pub fn ui_elements(exclude: HWND, children: bool) -> Vec<UiElement> {
+    visible_windows(exclude)
+        .into_iter()
+        .map(|(hwnd, window)| UiElement {
+            rect: frame_bounds(hwnd).unwrap_or(window.rect),
+            children: if children {
+                child_rects(hwnd)
+            } else {
+                Vec::new()
+            },
+        })
+        .collect()
+}
+
+// 所有层级的可见、非零尺寸的子窗口矩形
+fn child_rects(parent: HWND) -> Vec<(i32, i32, i32, i32)> {
+    unsafe extern "system" fn collect(hwnd: HWND, handles: LPARAM) -> BOOL {
+        unsafe { (*(handles as *mut Vec<HWND>)).push(hwnd) };
+        TRUE
+    }
+    let mut handles: Vec<HWND> = Vec::new();
+    unsafe {
+        EnumChildWindows(
+            parent,
+            Some(collect),
+            &mut handles as *mut Vec<HWND> as LPARAM,
+        );
+    }
+    handles
+        .into_iter()
+        .filter(|&hwnd| unsafe { IsWindowVisible(hwnd) } != 0)
+        .filter_map(window_rect)
+        .filter(|&(_, _, w, h)| w > 0 && h > 0)
+        .collect()
+}
+
+// 两个矩形重叠或边缘相接
+fn touches(a: (i32, i32, i32, i32), b: (i32, i32, i32, i32)) -> bool {
+    a.0 <= b.0 + b.2 && b.0 <= a.0 + a.2 && a.1 <= b.1 + b.3 && b.1 <= a.1 + a.3
+}
+
+// 沿所有者链查找 owner
+fn is_owned_by(hwnd: HWND, owner: HWND) -> bool {
+    let mut current = unsafe { GetWindow(hwnd, GW_OWNER) };
+    while !current.is_null() {
+        if current == owner {
+            return true;
+        }
+        current = unsafe { GetWindow(current, GW_OWNER) };
+    }
+    false
+}
+
+// 属于指定进程（可执行文件名，不区分大小写，可省略 .exe）的可见顶层窗口，按 Z 序
+pub fn process_windows(process: &str) -> Vec<(HWND, TopLevelWindow)> {
+    let process = process.to_lowercase();
+    let process = process.strip_suffix(".exe").unwrap_or(&process);
+    visible_windows(null_mut())
+        .into_iter()
+        .filter(|(hwnd, _)| {
+            process_name(*hwnd).is_some_and(|name| {
+                let name = name.to_lowercase();
+                name.strip_suffix(".exe").unwrap_or(&name) == process
+            })
+        })
+        .collect()
+}
+
+// 窗口所属进程的可执行文件名（如 notepad.exe）
+fn process_name(hwnd: HWND) -> Option<String> {
+    unsafe {
+        let mut pid: DWORD = 0;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process.is_null() {
+            return None;
+        }
+        let mut buffer = [0u16; 1024];
+        let mut len = buffer.len() as DWORD;
+        let ok = QueryFullProcessImageNameW(process, 0, buffer.as_mut_ptr(), &mut len) != 0;
+        CloseHandle(process);
+        if !ok {
+            return None;
+        }
+        let path = String::from_utf16_lossy(&buffer[..len as usize]);
+        path.rsplit(['\\', '/']).next().map(str::to_string)
+    }
+}
+
+fn visible_windows(exclude: HWND) -> Vec<(HWND, TopLevelWindow)> {
+    unsafe extern "system" fn collect(hwnd: HWND, handles: LPARAM) -> BOOL {
+        unsafe { (*(handles as *mut Vec<HWND>)).push(hwnd) };
+        TRUE
+    }
+    let mut handles: Vec<HWND> = Vec::new();
+    unsafe {
+        EnumWindows(Some(collect), &mut handles as *mut Vec<HWND> as LPARAM);
+    }
+
+    handles
+        .into_iter()
+        .filter(|&hwnd| hwnd != exclude)
+        .filter(|&hwnd| unsafe {
+            let mut cloaked: DWORD = 0;
+            let hidden = DwmGetWindowAttribute(
+                hwnd,
+                DWMWA_CLOAKED,
+                &mut cloaked as *mut DWORD as _,
+                size_of::<DWORD>() as DWORD,
+            ) == S_OK
+                && cloaked != 0;
+            IsWindowVisible(hwnd) != 0 && IsIconic(hwnd) == 0 && !hidden
+        })
+        .filter_map(|hwnd| {
+            let rect = window_rect(hwnd).filter(|&(_, _, w, h)| w > 0 && h > 0)?;
+            let window = TopLevelWindow {
+                title: window_title(hwnd),
+                rect,
+            };
+            Some((hwnd, window))
+        })
+        .collect()
+}
+
+fn window_title(hwnd: HWND) -> String {
+    let mut buffer = [0u16; 256];
+    let len = unsafe { GetWindowTextW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32) };
+    String::from_utf16_lossy(&buffer[..len.max(0) as usize])
+}
+
+fn window_rect(hwnd: HWND) -> Option<(i32, i32, i32, i32)> {
+    unsafe {
+        let mut rect: RECT = zeroed();
+        if GetWindowRect(hwnd, &mut rect) == 0 {
+            return None;
+        }
+        Some((
+            rect.left,
+            rect.top,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+        ))
+    }
+}
+
+// 将窗口排除在屏幕截图之外，系统不支持时返回 false
+pub fn exclude_from_capture(hwnd: HWND) -> bool {
+    unsafe { SetWindowDisplayAffinity(hwnd, WDA_EXCLUDEFROMCAPTURE) != 0 }
+}
+
+// 屏幕上某点所在（或最近）的显示器
+fn monitor_at(point: (i32, i32)) -> Option<HMONITOR> {
+    let monitor = unsafe {
+        MonitorFromPoint(
+            POINT {
+                x: point.0,
+                y: point.1,
+            },
+            MONITOR_DEFAULTTONEAREST,
+        )
+    };
+    (!monitor.is_null()).then_some(monitor)
+}
+
+// 屏幕上某点所在（或最近）显示器的范围和工作区，查询失败时返回 None
+fn monitor_info_at(point: (i32, i32)) -> Option<MONITORINFO> {
+    let monitor = monitor_at(point)?;
+    unsafe {
+        let mut info: MONITORINFO = zeroed();
+        info.cbSize = size_of::<MONITORINFO>() as u32;
+        (GetMonitorInfoW(monitor, &mut info) != 0).then_some(info)
+    }
+}
+
+// 屏幕上某点相对其所在（或最近）显示器左上角的坐标；查询失败时原样返回
+pub fn monitor_relative(point: (i32, i32)) -> (i32, i32) {
+    match monitor_info_at(point) {
+        Some(info) => (point.0 - info.rcMonitor.left, point.1 - info.rcMonitor.top),
+        None => point,
+    }
+}
+
+// 屏幕上某点所在（或最近）显示器的工作区 (x, y, w, h)，即去掉任务栏和停靠工具栏的部分；查询失败时返回 None
+pub fn work_area(point: (i32, i32)) -> Option<(i32, i32, u32, u32)> {
+    let work = monitor_info_at(point)?.rcWork;
+    Some((
+        work.left,
+        work.top,
+        (work.right - work.left).max(0) as u32,
+        (work.bottom - work.top).max(0) as u32,
+    ))
+}
+
+// 屏幕上某点所在（或最近）显示器的有效 DPI（96 为 100% 缩放）
+pub fn monitor_dpi_at(point: (i32, i32)) -> Option<u32> {
+    let monitor = monitor_at(point)?;
+    let (mut dpi_x, mut dpi_y) = (0, 0);
+    let result = unsafe { GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) };
+    (result == S_OK).then_some(dpi_x)
+}